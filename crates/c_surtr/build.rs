@@ -0,0 +1,23 @@
+extern crate cbindgen;
+
+use std::env;
+use std::path::PathBuf;
+
+
+/// Generate the C Header File for libc_surtr, using the settings in cbindgen.toml.
+fn main() {
+    let crate_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+
+    let config = cbindgen::Config::from_file(crate_dir.join("cbindgen.toml")).unwrap();
+    let output_file = crate_dir.join("include").join("c_surtr.h");
+
+    cbindgen::Builder::new()
+        .with_crate(&crate_dir)
+        .with_config(config)
+        .generate()
+        .unwrap()
+        .write_to_file(output_file);
+
+    println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+}
@@ -0,0 +1,22 @@
+C_WORKING_DIR := crates/c_surtr
+PREFIX ?= /usr/local
+C_SURTR_VERSION := $(shell sed -n 's/^version = "\(.*\)"/\1/p' $(C_WORKING_DIR)/Cargo.toml)
+
+
+build-c-surtr:
+	@cargo build -p c_surtr
+
+build-c-surtr-release:
+	@cargo build -p c_surtr --release
+
+test-c-surtr:
+	@cargo test -p c_surtr
+
+install-c-surtr: build-c-surtr-release
+	@install -d $(DESTDIR)$(PREFIX)/lib $(DESTDIR)$(PREFIX)/lib/pkgconfig $(DESTDIR)$(PREFIX)/include
+	@install -m 644 ./target/release/libc_surtr.a $(DESTDIR)$(PREFIX)/lib/
+	@install -m 755 ./target/release/libc_surtr.so $(DESTDIR)$(PREFIX)/lib/ 2>/dev/null || \
+		install -m 755 ./target/release/libc_surtr.dylib $(DESTDIR)$(PREFIX)/lib/
+	@install -m 644 ./$(C_WORKING_DIR)/include/c_surtr.h $(DESTDIR)$(PREFIX)/include/
+	@sed -e 's|@PREFIX@|$(PREFIX)|' -e 's|@VERSION@|$(C_SURTR_VERSION)|' ./$(C_WORKING_DIR)/c_surtr.pc.in \
+		> $(DESTDIR)$(PREFIX)/lib/pkgconfig/c_surtr.pc
@@ -0,0 +1,175 @@
+#ifndef C_SURTR_H
+#define C_SURTR_H
+
+/* Generated by cbindgen from crates/c_surtr/src/lib.rs. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// No error occurred.
+#define SURTR_OK 0
+
+// The URL could not be parsed. Maps to `SurtrError::UrlParseError`.
+#define SURTR_URL_PARSE_ERROR 1
+
+// The URL was expected to contain a scheme. Maps to `SurtrError::NoSchemeFoundError`.
+#define SURTR_NO_SCHEME_FOUND_ERROR 2
+
+// The URL could not be canonicalized. Maps to `SurtrError::CanonicalizerError`.
+#define SURTR_CANONICALIZER_ERROR 3
+
+// A pointer was null, a string was not UTF-8 encoded, or an option or value was not recognised.
+#define SURTR_INVALID_INPUT 4
+
+// Surtr panicked. This is always a bug in Surtr.
+#define SURTR_PANIC 5
+
+// An opaque set of options.
+//
+// Created with `surtr_options_new` or `surtr_options_profile`, and freed with `surtr_options_free`.
+// A handle may be shared between threads, as long as it is not modified or freed while in use.
+typedef struct SurtrOptionsHandle SurtrOptionsHandle;
+
+// The result of generating a SURT.
+typedef struct SurtrResult {
+  // SURTR_OK, or the error code.
+  int32_t code;
+  // The SURT. Null if the code is not SURTR_OK.
+  char *output;
+  // The error description. Null if the code is SURTR_OK.
+  char *error;
+} SurtrResult;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Get the version of the library.
+//
+// # Returns
+//
+// A static, NUL terminated string. It must not be freed.
+const char *surtr_version(void);
+
+// Create a set of options with the default values, which produce the same keys as IA's Python `surt`.
+//
+// # Returns
+//
+// A handle to the options, or null if they could not be created. Free it with `surtr_options_free`.
+struct SurtrOptionsHandle *surtr_options_new(void);
+
+// Create a set of options from a named profile: `default`, `heritrix` or `canonical`.
+//
+// # Arguments
+//
+// * `name` - The name of the profile.
+//
+// # Returns
+//
+// A handle to the options, or null if the profile is not known. Free it with `surtr_options_free`.
+//
+// # Safety
+//
+// The name must be null or point to a NUL terminated string.
+struct SurtrOptionsHandle *surtr_options_profile(const char *name);
+
+// Free a set of options. Null handles are ignored.
+//
+// # Safety
+//
+// The handle must have been created by this library, and must not be used after this call.
+void surtr_options_free(struct SurtrOptionsHandle *options);
+
+// Set an option, such as `with_scheme` or `trailing_comma`.
+//
+// # Arguments
+//
+// * `options` - The options to modify.
+// * `name` - The name of the option.
+// * `value` - The value of the option.
+//
+// # Returns
+//
+// SURTR_OK, or SURTR_INVALID_INPUT if a pointer is null or the option is not known.
+//
+// # Safety
+//
+// The handle must be null or valid, and the name must be null or point to a NUL terminated string.
+int32_t surtr_options_set(struct SurtrOptionsHandle *options, const char *name, bool value);
+
+// Set how much of the host is kept: `full`, `suffix`, `registered_domain` or `registered_domain+N`.
+//
+// # Returns
+//
+// SURTR_OK, or SURTR_INVALID_INPUT if a pointer is null or the granularity is not valid.
+//
+// # Safety
+//
+// The handle must be null or valid, and the granularity must be null or point to a NUL terminated string.
+int32_t surtr_options_set_host_granularity(struct SurtrOptionsHandle *options,
+                                           const char *granularity);
+
+// Set the number of path segments kept. A negative depth keeps the full path, which is the default.
+//
+// # Returns
+//
+// SURTR_OK, or SURTR_INVALID_INPUT if the handle is null.
+//
+// # Safety
+//
+// The handle must be null or valid.
+int32_t surtr_options_set_path_depth(struct SurtrOptionsHandle *options, int64_t depth);
+
+// Generate a SURT from a URL.
+//
+// # Arguments
+//
+// * `url` - The URL to be transformed.
+// * `options` - The options to be used. Null uses the default options.
+//
+// # Returns
+//
+// A SurtrResult, which must be freed with `surtr_result_free`.
+//
+// # Safety
+//
+// The URL must be null or point to a NUL terminated string. The handle must be null or valid.
+struct SurtrResult surtr_surt(const char *url, const struct SurtrOptionsHandle *options);
+
+// Generate a SURT from each URL in an array, writing the results into an array provided by the caller.
+//
+// # Arguments
+//
+// * `urls` - An array of `len` URLs. A null URL produces a SURTR_INVALID_INPUT result.
+// * `len` - The number of URLs.
+// * `options` - The options to be used. Null uses the default options.
+// * `results` - An array with room for `len` results. Each result must be freed with `surtr_result_free`.
+//
+// # Returns
+//
+// The number of URLs which produced an error.
+//
+// # Safety
+//
+// Both arrays must hold `len` elements, and every non-null URL must point to a NUL terminated string.
+// The handle must be null or valid. Nothing is written if either array is null.
+size_t surtr_surt_many(const char *const *urls,
+                       size_t len,
+                       const struct SurtrOptionsHandle *options,
+                       struct SurtrResult *results);
+
+// Free the strings within a SurtrResult, and set them to null. Freeing a result twice has no effect.
+//
+// # Safety
+//
+// The result must be null, or have been returned by this library.
+void surtr_result_free(struct SurtrResult *result);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* C_SURTR_H */
@@ -0,0 +1,106 @@
+//! The FFI plumbing shared by the C and Go bindings.
+//!
+//! `go_surtr` builds its own exported functions on these, so both bindings map errors,
+//! report panics, read strings and validate options in the same way.
+
+use std::any::Any;
+use std::ffi::{CStr, CString, c_char};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use surtr::{SurtrError, SurtrOptions};
+
+use crate::{
+    SurtrResult, SURTR_CANONICALIZER_ERROR, SURTR_INVALID_INPUT, SURTR_NO_SCHEME_FOUND_ERROR, SURTR_OK,
+    SURTR_PANIC, SURTR_URL_PARSE_ERROR,
+};
+
+/// Map a SurtrError onto its stable error code.
+pub fn error_code(e: &SurtrError) -> i32 {
+    match e {
+        SurtrError::UrlParseError(_) => SURTR_URL_PARSE_ERROR,
+        SurtrError::NoSchemeFoundError => SURTR_NO_SCHEME_FOUND_ERROR,
+        SurtrError::CanonicalizerError(_) => SURTR_CANONICALIZER_ERROR,
+    }
+}
+
+/// Describe the payload of a caught panic.
+pub fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    let message = match payload.downcast_ref::<&str>() {
+        Some(s) => s.to_string(),
+        None => payload.downcast_ref::<String>().cloned().unwrap_or_default(),
+    };
+
+    format!("Panic: surtr panicked: {}", message)
+}
+
+/// Read a C String passed in by the caller.
+///
+/// # Errors
+///
+/// An error description if the pointer is null or the string is not UTF-8 encoded.
+///
+/// # Safety
+///
+/// The pointer must be null or point to a NUL terminated string.
+pub unsafe fn from_c_str<'a>(s: *const c_char, name: &str) -> Result<&'a str, String> {
+    if s.is_null() {
+        return Err(format!("InvalidInput: {} is null", name));
+    }
+
+    unsafe { CStr::from_ptr(s) }
+        .to_str()
+        .map_err(|e| format!("InvalidInput: {} is not UTF-8 encoded: {}", name, e))
+}
+
+/// Convert an error description into a C String. NUL bytes are removed, so this cannot fail.
+pub fn error_c_str(e: String) -> *mut c_char {
+    CString::new(e.replace('\0', "")).unwrap_or_default().into_raw()
+}
+
+/// Set a named option, rejecting names which are not in `SurtrOptions::OPTION_NAMES`.
+///
+/// # Returns
+///
+/// SURTR_OK, or SURTR_INVALID_INPUT if the name is null, not UTF-8 encoded or not a known option.
+///
+/// # Safety
+///
+/// The name must be null or point to a NUL terminated string.
+pub unsafe fn set_option(options: &mut SurtrOptions, name: *const c_char, value: bool) -> i32 {
+    match unsafe { from_c_str(name, "name") } {
+        Ok(n) if SurtrOptions::OPTION_NAMES.contains(&n) => {
+            options.set(n, value);
+            SURTR_OK
+        }
+        _ => SURTR_INVALID_INPUT,
+    }
+}
+
+/// Copy the options once for many URLs, with the defaults of `surtr::surt` set,
+/// so `surtr::surt_with` does not copy them again for each URL.
+pub fn batch_options(options: Option<&SurtrOptions>) -> SurtrOptions {
+    options.cloned().unwrap_or_default().with_surt_defaults()
+}
+
+/// Generate a SURT, catching any panic. The options are borrowed, not copied.
+///
+/// # Safety
+///
+/// The URL must be null or point to a NUL terminated string.
+pub unsafe fn surt(url: *const c_char, options: Option<&SurtrOptions>) -> SurtrResult {
+    catch_unwind(AssertUnwindSafe(|| {
+        let input = match unsafe { from_c_str(url, "url") } {
+            Ok(s) => s,
+            Err(e) => return SurtrResult::from_error(SURTR_INVALID_INPUT, e),
+        };
+        let result = match options {
+            Some(o) => surtr::surt_with(input, o),
+            None => surtr::surt(input, None),
+        };
+
+        match result {
+            Ok(s) => SurtrResult::from_string(s),
+            Err(e) => SurtrResult::from_error(error_code(&e), e.to_string()),
+        }
+    }))
+    .unwrap_or_else(|payload| SurtrResult::from_error(SURTR_PANIC, panic_message(payload)))
+}
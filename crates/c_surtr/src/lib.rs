@@ -0,0 +1,382 @@
+//! C Bindings for Surtr - A Rust based Sort-friendly URI Reordering Transform. ([SURT])
+//!
+//! The crate builds a static and a shared library, `libc_surtr`, with the header `include/c_surtr.h`
+//! generated by cbindgen. A pkg-config file is installed alongside them by `make install-c-surtr`.
+//!
+//! [SURT]: http://crawler.archive.org/articles/user_manual/glossary.html#surt
+//!
+//! # Errors
+//!
+//! Every exported function catches panics, so a bug in Surtr is reported as an error rather than aborting the caller.
+//! Errors are returned with a stable numeric code, one of the `SURTR_*` constants, alongside their description.
+//!
+//! # Ownership
+//!
+//! Options are created with `surtr_options_new` or `surtr_options_profile`, and must be freed with `surtr_options_free`.
+//! The strings within a `SurtrResult` are owned by the library, and must be freed with `surtr_result_free`.
+//! Strings passed into the library are only borrowed for the duration of the call.
+//!
+//! # Rust
+//!
+//! The crate is also built as an rlib, so `go_surtr` can share the plumbing in the `ffi` module.
+
+
+pub mod ffi;
+
+use std::ffi::{CString, c_char};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use surtr::{HostGranularity, SurtrOptions};
+
+use ffi::{batch_options, error_c_str, from_c_str, surt};
+
+/// No error occurred.
+pub const SURTR_OK: i32 = 0;
+/// The URL could not be parsed. Maps to `SurtrError::UrlParseError`.
+pub const SURTR_URL_PARSE_ERROR: i32 = 1;
+/// The URL was expected to contain a scheme. Maps to `SurtrError::NoSchemeFoundError`.
+pub const SURTR_NO_SCHEME_FOUND_ERROR: i32 = 2;
+/// The URL could not be canonicalized. Maps to `SurtrError::CanonicalizerError`.
+pub const SURTR_CANONICALIZER_ERROR: i32 = 3;
+/// A pointer was null, a string was not UTF-8 encoded, or an option or value was not recognised.
+pub const SURTR_INVALID_INPUT: i32 = 4;
+/// Surtr panicked. This is always a bug in Surtr.
+pub const SURTR_PANIC: i32 = 5;
+
+/// An opaque set of options.
+///
+/// Created with `surtr_options_new` or `surtr_options_profile`, and freed with `surtr_options_free`.
+/// A handle may be shared between threads, as long as it is not modified or freed while in use.
+pub struct SurtrOptionsHandle(SurtrOptions);
+
+/// The result of generating a SURT.
+#[repr(C)]
+pub struct SurtrResult {
+    /// SURTR_OK, or the error code.
+    pub code: i32,
+    /// The SURT. Null if the code is not SURTR_OK.
+    pub output: *mut c_char,
+    /// The error description. Null if the code is SURTR_OK.
+    pub error: *mut c_char,
+}
+
+impl SurtrResult {
+    /// Create a result from a successful SURT. Outputs containing a NUL byte cannot be returned to C.
+    pub fn from_string(s: String) -> Self {
+        match CString::new(s) {
+            Ok(output) => Self {
+                code: SURTR_OK,
+                output: output.into_raw(),
+                error: ::std::ptr::null_mut(),
+            },
+            Err(_) => Self::from_error(SURTR_INVALID_INPUT, "InvalidInput: output contains a NUL byte".to_string()),
+        }
+    }
+
+    /// Create a result from an error code and description. NUL bytes are removed from the description.
+    pub fn from_error(code: i32, e: String) -> Self {
+        Self {
+            code,
+            output: ::std::ptr::null_mut(),
+            error: error_c_str(e),
+        }
+    }
+}
+
+// Internal function to modify a set of options, catching any panic.
+//
+// # Safety
+//
+// The options must be null or valid, and not in use by another thread.
+unsafe fn with_options(options: *mut SurtrOptionsHandle, f: impl FnOnce(&mut SurtrOptions) -> i32) -> i32 {
+    let options = match unsafe { options.as_mut() } {
+        Some(o) => o,
+        None => return SURTR_INVALID_INPUT,
+    };
+
+    catch_unwind(AssertUnwindSafe(|| f(&mut options.0))).unwrap_or(SURTR_PANIC)
+}
+
+/// Get the version of the library.
+///
+/// # Returns
+///
+/// A static, NUL terminated string. It must not be freed.
+#[unsafe(no_mangle)]
+pub extern "C" fn surtr_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char
+}
+
+/// Create a set of options with the default values, which produce the same keys as IA's Python `surt`.
+///
+/// # Returns
+///
+/// A handle to the options, or null if they could not be created. Free it with `surtr_options_free`.
+#[unsafe(no_mangle)]
+pub extern "C" fn surtr_options_new() -> *mut SurtrOptionsHandle {
+    catch_unwind(|| Box::into_raw(Box::new(SurtrOptionsHandle(SurtrOptions::default())))).unwrap_or(::std::ptr::null_mut())
+}
+
+/// Create a set of options from a named profile: `default`, `heritrix` or `canonical`.
+///
+/// # Arguments
+///
+/// * `name` - The name of the profile.
+///
+/// # Returns
+///
+/// A handle to the options, or null if the profile is not known. Free it with `surtr_options_free`.
+///
+/// # Safety
+///
+/// The name must be null or point to a NUL terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn surtr_options_profile(name: *const c_char) -> *mut SurtrOptionsHandle {
+    catch_unwind(AssertUnwindSafe(|| {
+        let options = unsafe { from_c_str(name, "name") }.ok().and_then(|n| SurtrOptions::profile(n).ok());
+
+        match options {
+            Some(o) => Box::into_raw(Box::new(SurtrOptionsHandle(o))),
+            None => ::std::ptr::null_mut(),
+        }
+    }))
+    .unwrap_or(::std::ptr::null_mut())
+}
+
+/// Free a set of options. Null handles are ignored.
+///
+/// # Safety
+///
+/// The handle must have been created by this library, and must not be used after this call.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn surtr_options_free(options: *mut SurtrOptionsHandle) {
+    if options.is_null() {
+        return;
+    }
+
+    let _ = catch_unwind(AssertUnwindSafe(|| unsafe {
+        drop(Box::from_raw(options));
+    }));
+}
+
+/// Set an option, such as `with_scheme` or `trailing_comma`.
+///
+/// # Arguments
+///
+/// * `options` - The options to modify.
+/// * `name` - The name of the option.
+/// * `value` - The value of the option.
+///
+/// # Returns
+///
+/// SURTR_OK, or SURTR_INVALID_INPUT if a pointer is null or the option is not known.
+///
+/// # Safety
+///
+/// The handle must be null or valid, and the name must be null or point to a NUL terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn surtr_options_set(options: *mut SurtrOptionsHandle, name: *const c_char, value: bool) -> i32 {
+    unsafe { with_options(options, |o| ffi::set_option(o, name, value)) }
+}
+
+/// Set how much of the host is kept: `full`, `suffix`, `registered_domain` or `registered_domain+N`.
+///
+/// # Returns
+///
+/// SURTR_OK, or SURTR_INVALID_INPUT if a pointer is null or the granularity is not valid.
+///
+/// # Safety
+///
+/// The handle must be null or valid, and the granularity must be null or point to a NUL terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn surtr_options_set_host_granularity(options: *mut SurtrOptionsHandle, granularity: *const c_char) -> i32 {
+    unsafe {
+        with_options(options, |o| {
+            match from_c_str(granularity, "granularity").map(|g| g.parse::<HostGranularity>()) {
+                Ok(Ok(g)) => {
+                    o.set_host_granularity(g);
+                    SURTR_OK
+                }
+                _ => SURTR_INVALID_INPUT,
+            }
+        })
+    }
+}
+
+/// Set the number of path segments kept. A negative depth keeps the full path, which is the default.
+///
+/// # Returns
+///
+/// SURTR_OK, or SURTR_INVALID_INPUT if the handle is null.
+///
+/// # Safety
+///
+/// The handle must be null or valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn surtr_options_set_path_depth(options: *mut SurtrOptionsHandle, depth: i64) -> i32 {
+    unsafe {
+        with_options(options, |o| {
+            o.set_path_depth(usize::try_from(depth).ok());
+            SURTR_OK
+        })
+    }
+}
+
+/// Generate a SURT from a URL.
+///
+/// # Arguments
+///
+/// * `url` - The URL to be transformed.
+/// * `options` - The options to be used. Null uses the default options.
+///
+/// # Returns
+///
+/// A SurtrResult, which must be freed with `surtr_result_free`.
+///
+/// # Safety
+///
+/// The URL must be null or point to a NUL terminated string. The handle must be null or valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn surtr_surt(url: *const c_char, options: *const SurtrOptionsHandle) -> SurtrResult {
+    unsafe { surt(url, options.as_ref().map(|o| &o.0)) }
+}
+
+/// Generate a SURT from each URL in an array, writing the results into an array provided by the caller.
+///
+/// # Arguments
+///
+/// * `urls` - An array of `len` URLs. A null URL produces a SURTR_INVALID_INPUT result.
+/// * `len` - The number of URLs.
+/// * `options` - The options to be used. Null uses the default options.
+/// * `results` - An array with room for `len` results. Each result must be freed with `surtr_result_free`.
+///
+/// # Returns
+///
+/// The number of URLs which produced an error.
+///
+/// # Safety
+///
+/// Both arrays must hold `len` elements, and every non-null URL must point to a NUL terminated string.
+/// The handle must be null or valid. Nothing is written if either array is null.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn surtr_surt_many(
+    urls: *const *const c_char,
+    len: usize,
+    options: *const SurtrOptionsHandle,
+    results: *mut SurtrResult,
+) -> usize {
+    if urls.is_null() || results.is_null() {
+        return len;
+    }
+
+    let options = batch_options(unsafe { options.as_ref() }.map(|o| &o.0));
+    let urls = unsafe { std::slice::from_raw_parts(urls, len) };
+    let mut errors = 0;
+
+    for (i, url) in urls.iter().enumerate() {
+        let result = unsafe { surt(*url, Some(&options)) };
+        if result.code != SURTR_OK {
+            errors += 1;
+        }
+
+        unsafe { results.add(i).write(result) };
+    }
+
+    errors
+}
+
+/// Free the strings within a SurtrResult, and set them to null. Freeing a result twice has no effect.
+///
+/// # Safety
+///
+/// The result must be null, or have been returned by this library.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn surtr_result_free(result: *mut SurtrResult) {
+    let result = match unsafe { result.as_mut() } {
+        Some(r) => r,
+        None => return,
+    };
+
+    let _ = catch_unwind(AssertUnwindSafe(|| {
+        for s in [&mut result.output, &mut result.error] {
+            if !s.is_null() {
+                unsafe { drop(CString::from_raw(*s)) };
+                *s = ::std::ptr::null_mut();
+            }
+        }
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+
+    // Free a result, returning its output or error.
+    fn take(mut result: SurtrResult) -> (i32, String) {
+        let s = match result.code {
+            SURTR_OK => unsafe { CStr::from_ptr(result.output) },
+            _ => unsafe { CStr::from_ptr(result.error) },
+        };
+        let taken = (result.code, s.to_string_lossy().to_string());
+
+        unsafe { surtr_result_free(&mut result) };
+        assert!(result.output.is_null() && result.error.is_null());
+        taken
+    }
+
+    #[test]
+    fn test_surtr_surt() {
+        let url = c"http://www.example.com/b?z=1&a=2";
+
+        assert_eq!(take(unsafe { surtr_surt(url.as_ptr(), ::std::ptr::null()) }), (SURTR_OK, "com,example)/b?a=2&z=1".to_string()));
+        assert_eq!(take(unsafe { surtr_surt(::std::ptr::null(), ::std::ptr::null()) }).0, SURTR_INVALID_INPUT);
+        assert_eq!(take(unsafe { surtr_surt(c"http://%ff/".as_ptr(), ::std::ptr::null()) }).0, SURTR_CANONICALIZER_ERROR);
+        assert_eq!(take(unsafe { surtr_surt(c"http://\xff/".as_ptr(), ::std::ptr::null()) }).0, SURTR_INVALID_INPUT);
+
+        let options = unsafe { surtr_options_profile(c"heritrix".as_ptr()) };
+        assert!(!options.is_null());
+        unsafe {
+            assert_eq!(surtr_options_set(options, c"trailing_comma".as_ptr(), false), SURTR_OK);
+            assert_eq!(surtr_options_set(options, c"not_an_option".as_ptr(), false), SURTR_INVALID_INPUT);
+            assert_eq!(surtr_options_set_host_granularity(options, c"registered_domain".as_ptr()), SURTR_OK);
+            assert_eq!(surtr_options_set_host_granularity(options, c"most".as_ptr()), SURTR_INVALID_INPUT);
+            assert_eq!(surtr_options_set_path_depth(options, 1), SURTR_OK);
+        }
+        assert_eq!(take(unsafe { surtr_surt(url.as_ptr(), options) }).1, "http://(com,example)/b");
+
+        unsafe {
+            assert_eq!(surtr_options_set_path_depth(options, -1), SURTR_OK);
+            assert_eq!(surtr_options_set(::std::ptr::null_mut(), c"surt".as_ptr(), true), SURTR_INVALID_INPUT);
+            surtr_options_free(options);
+            surtr_options_free(::std::ptr::null_mut());
+            assert!(surtr_options_profile(c"unknown".as_ptr()).is_null());
+        }
+    }
+
+    #[test]
+    fn test_surtr_surt_many() {
+        let urls = [c"http://www.example.com/".as_ptr(), ::std::ptr::null(), c"dns:example.com".as_ptr()];
+        let mut results: Vec<SurtrResult> = Vec::with_capacity(urls.len());
+
+        let options = surtr_options_new();
+        let errors = unsafe { surtr_surt_many(urls.as_ptr(), urls.len(), options, results.as_mut_ptr()) };
+        unsafe {
+            results.set_len(urls.len());
+            surtr_options_free(options);
+        }
+
+        assert_eq!(errors, 1);
+        let taken: Vec<(i32, String)> = results.into_iter().map(take).collect();
+        assert_eq!(taken[0], (SURTR_OK, "com,example)/".to_string()));
+        assert_eq!(taken[1].0, SURTR_INVALID_INPUT);
+        assert_eq!(taken[2], (SURTR_OK, "dns:example.com".to_string()));
+
+        assert_eq!(unsafe { surtr_surt_many(::std::ptr::null(), 2, ::std::ptr::null(), ::std::ptr::null_mut()) }, 2);
+    }
+
+    #[test]
+    fn test_surtr_version() {
+        let version = unsafe { CStr::from_ptr(surtr_version()) };
+        assert_eq!(version.to_str().unwrap(), env!("CARGO_PKG_VERSION"));
+    }
+}
@@ -0,0 +1,68 @@
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// The target directory holding libc_surtr, found from the location of this test binary.
+fn profile_dir() -> PathBuf {
+    let exe = env::current_exe().expect("test binary has no path");
+    exe.parent().and_then(Path::parent).expect("test binary is not in target/<profile>/deps").to_path_buf()
+}
+
+// Compile tests/harness.c against libc_surtr with the C compiler, and run it.
+fn run_harness(name: &str, link_args: &[&str]) {
+    let crate_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let out_dir = profile_dir();
+    let harness = out_dir.join(name);
+
+    let cc = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let compiled = Command::new(&cc)
+        .arg(crate_dir.join("tests").join("harness.c"))
+        .arg("-I")
+        .arg(crate_dir.join("include"))
+        .arg("-o")
+        .arg(&harness)
+        .args(link_args)
+        .status();
+
+    match compiled {
+        Ok(status) => assert!(status.success(), "failed to compile the harness with {}", cc),
+        Err(e) => {
+            eprintln!("skipping the C harness, {} is not available: {}", cc, e);
+            return;
+        }
+    }
+
+    let output = Command::new(&harness).output().expect("failed to run the harness");
+    assert!(
+        output.status.success(),
+        "harness failed:\n{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn test_c_harness() {
+    // The static and shared libraries are not built by `cargo test`, so build them first.
+    let mut build = Command::new(env!("CARGO"));
+    build.args(["build", "-p", "c_surtr"]);
+    if !cfg!(debug_assertions) {
+        build.arg("--release");
+    }
+    assert!(build.status().expect("failed to run cargo").success());
+
+    let out_dir = profile_dir();
+    let lib_dir = out_dir.to_str().expect("target directory is not UTF-8");
+    let static_lib = out_dir.join("libc_surtr.a");
+
+    run_harness(
+        "c_surtr_harness_static",
+        &[static_lib.to_str().unwrap(), "-lpthread", "-ldl", "-lm"],
+    );
+    if cfg!(target_os = "linux") {
+        run_harness(
+            "c_surtr_harness_shared",
+            &["-L", lib_dir, "-lc_surtr", &format!("-Wl,-rpath,{}", lib_dir)],
+        );
+    }
+}
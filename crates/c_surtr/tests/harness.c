@@ -0,0 +1,78 @@
+// A small C program driving libc_surtr through its public header.
+// Built and run by tests/c_harness.rs, or by `make test-c-surtr`.
+#include <stdio.h>
+#include <string.h>
+
+#include "c_surtr.h"
+
+static int failures = 0;
+
+#define CHECK(cond)                                                   \
+    do {                                                              \
+        if (!(cond)) {                                                \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, #cond); \
+            failures++;                                               \
+        }                                                             \
+    } while (0)
+
+static void check_surt(const char *url, const SurtrOptionsHandle *options, const char *expected) {
+    SurtrResult result = surtr_surt(url, options);
+
+    CHECK(result.code == SURTR_OK);
+    CHECK(result.error == NULL);
+    if (result.output == NULL || strcmp(result.output, expected) != 0) {
+        fprintf(stderr, "surt(%s): expected %s, got %s\n", url, expected, result.output ? result.output : "(null)");
+        failures++;
+    }
+
+    surtr_result_free(&result);
+    CHECK(result.output == NULL);
+}
+
+int main(void) {
+    CHECK(strlen(surtr_version()) > 0);
+
+    check_surt("http://www.archive.org/goo/?a=2&b&a=1", NULL, "org,archive)/goo?a=1&a=2&b");
+
+    SurtrOptionsHandle *options = surtr_options_new();
+    CHECK(options != NULL);
+    CHECK(surtr_options_set(options, "with_scheme", true) == SURTR_OK);
+    CHECK(surtr_options_set(options, "trailing_comma", true) == SURTR_OK);
+    CHECK(surtr_options_set(options, "no_such_option", true) == SURTR_INVALID_INPUT);
+    check_surt("https://www.example.com/", options, "https://(com,example,)/");
+
+    CHECK(surtr_options_set_host_granularity(options, "suffix") == SURTR_OK);
+    CHECK(surtr_options_set_path_depth(options, 0) == SURTR_OK);
+    check_surt("http://news.bbc.co.uk/a/b", options, "http://(uk,co,)/");
+    surtr_options_free(options);
+
+    SurtrOptionsHandle *heritrix = surtr_options_profile("heritrix");
+    CHECK(heritrix != NULL);
+    check_surt("http://www.example.com/", heritrix, "http://(com,example,www,)/");
+    CHECK(surtr_options_profile("no_such_profile") == NULL);
+
+    const char *urls[] = {"http://www.example.com/", NULL, "http://%ff/", "dns:example.com"};
+    SurtrResult results[4];
+    CHECK(surtr_surt_many(urls, 4, heritrix, results) == 2);
+    CHECK(results[0].code == SURTR_OK);
+    CHECK(results[1].code == SURTR_INVALID_INPUT && results[1].error != NULL);
+    CHECK(results[2].code == SURTR_CANONICALIZER_ERROR && results[2].output == NULL);
+    CHECK(results[3].code == SURTR_OK && strcmp(results[3].output, "dns:example.com") == 0);
+    for (int i = 0; i < 4; i++) {
+        surtr_result_free(&results[i]);
+        surtr_result_free(&results[i]);
+    }
+    surtr_options_free(heritrix);
+
+    SurtrResult invalid = surtr_surt("http://\xff/", NULL);
+    CHECK(invalid.code == SURTR_INVALID_INPUT);
+    surtr_result_free(&invalid);
+
+    if (failures > 0) {
+        fprintf(stderr, "%d checks failed\n", failures);
+        return 1;
+    }
+
+    printf("ok\n");
+    return 0;
+}
@@ -2,19 +2,53 @@
 package go_surtr
 
 /*
+#cgo CFLAGS: -I${SRCDIR}/../c_surtr/include
 #cgo LDFLAGS: -L${SRCDIR} -ldl
 #cgo darwin,arm64 LDFLAGS: -lgo_surtr_darwin_arm64
 #cgo linux,arm64 LDFLAGS: -lgo_surtr_linux_arm64
 #cgo linux,amd64 LDFLAGS: -lgo_surtr_linux_amd64
 typedef struct SurtrOptions SurtrOptions;
+#include "c_surtr.h"
 #include "go_surtr.h"
 #include <stdlib.h>
 */
 import "C"
 import (
+	"errors"
+	"strings"
 	"unsafe"
 )
 
+// ErrorCode is the stable numeric code of an error returned by Surtr.
+type ErrorCode int
+
+const (
+	// CodeOK is returned when no error occurred.
+	CodeOK ErrorCode = C.SURTR_OK
+	// CodeURLParse is returned when the URL could not be parsed.
+	CodeURLParse ErrorCode = C.SURTR_URL_PARSE_ERROR
+	// CodeNoSchemeFound is returned when the URL was expected to contain a scheme.
+	CodeNoSchemeFound ErrorCode = C.SURTR_NO_SCHEME_FOUND_ERROR
+	// CodeCanonicalizer is returned when the URL could not be canonicalized.
+	CodeCanonicalizer ErrorCode = C.SURTR_CANONICALIZER_ERROR
+	// CodeInvalidInput is returned when a string is empty, not UTF-8 encoded or contains a NUL byte,
+	// or when an option name is not known.
+	CodeInvalidInput ErrorCode = C.SURTR_INVALID_INPUT
+	// CodePanic is returned when Surtr panicked. This is always a bug in Surtr.
+	CodePanic ErrorCode = C.SURTR_PANIC
+)
+
+// Sentinel errors for each kind of error which can be produced by Surtr.
+// Use errors.Is to check the kind of a SurtError.
+var (
+	ErrURLParse       = errors.New("UrlParseError")
+	ErrNoSchemeFound  = errors.New("NoSchemeFoundError")
+	ErrCanonicalizer  = errors.New("CanonicalizerError")
+	ErrInvalidInput   = errors.New("InvalidInput")
+	ErrPanic          = errors.New("Panic")
+	errUnknownErrCode = errors.New("unknown surtr error")
+)
+
 // SurtError is a Go Error wrapper around any error which can be produed by Surtr.
 // These errors are found in 3 main types. They are exposed as a String and
 // are prefixed with one of the following Surtr Error names:
@@ -22,10 +56,14 @@ import (
 // 2. NoSchemeFoundError
 // 3. CanonicalizerError
 //
-// These errors are mapped into a Go Error.
+// Invalid input and panics within Surtr are also returned as a SurtError.
+// Each error carries a stable ErrorCode, and wraps the matching sentinel error,
+// so errors.Is(err, ErrURLParse) can be used instead of checking the prefix.
 type SurtError struct {
 	// The raw error string returned from Surtr.
 	s string
+	// The error code returned from Surtr.
+	code ErrorCode
 }
 
 // Error satisfies the Go Error Interface and returns the raw error string
@@ -34,15 +72,42 @@ func (e SurtError) Error() string {
 	return e.s
 }
 
+// Code returns the stable numeric code of the error.
+func (e SurtError) Code() ErrorCode {
+	return e.code
+}
+
+// Unwrap returns the sentinel error matching the error code, for use with errors.Is.
+func (e SurtError) Unwrap() error {
+	switch e.code {
+	case CodeURLParse:
+		return ErrURLParse
+	case CodeNoSchemeFound:
+		return ErrNoSchemeFound
+	case CodeCanonicalizer:
+		return ErrCanonicalizer
+	case CodeInvalidInput:
+		return ErrInvalidInput
+	case CodePanic:
+		return ErrPanic
+	default:
+		return errUnknownErrCode
+	}
+}
+
 // checkString is a helper function to check if the URL is empty.
 // If the URL is empty, an error is returned.
+// URLs containing a NUL byte are also rejected, as they cannot be passed to C.
 //
 // This is used to prevent unnecessary calls out of Go and into C.
 // This also serves to maintain compatiability in the interface with IA's Python
 // Surt library.
 func checkString(url string) (string, error) {
 	if url == "" {
-		return "", SurtError{s: "URL is empty"}
+		return "", SurtError{s: "URL is empty", code: CodeInvalidInput}
+	}
+	if strings.IndexByte(url, 0) >= 0 {
+		return "", SurtError{s: "InvalidInput: URL contains a NUL byte", code: CodeInvalidInput}
 	}
 
 	return url, nil
@@ -51,17 +116,30 @@ func checkString(url string) (string, error) {
 // setupOptions is a helper function to correctly create a SurtrOptions struct within the Surtr library.
 // This function returns a Pointer to the Struct in Rust.
 // Please ensure that the Struct is destroyed correctly, by calling defer on C.destroy_options(<pointer>)
-func setupOptions(options map[string]bool) *C.SurtrOptions {
+// The Struct is destroyed before an error is returned.
+func setupOptions(options map[string]bool) (*C.SurtrOptions, error) {
 	option_struct := C.init_options()
+	if option_struct == nil {
+		return nil, SurtError{s: "Panic: failed to create options", code: CodePanic}
+	}
 
 	for key, value := range options {
+		if strings.IndexByte(key, 0) >= 0 {
+			C.destroy_options(option_struct)
+			return nil, SurtError{s: "InvalidInput: option name contains a NUL byte", code: CodeInvalidInput}
+		}
+
 		key_cstr := C.CString(key)
-		defer C.free(unsafe.Pointer(key_cstr))
+		code := ErrorCode(C.set_option(option_struct, key_cstr, C.bool(value)))
+		C.free(unsafe.Pointer(key_cstr))
 
-		C.set_option(option_struct, key_cstr, C.bool(value))
+		if code != CodeOK {
+			C.destroy_options(option_struct)
+			return nil, SurtError{s: "InvalidInput: invalid option " + key, code: code}
+		}
 	}
 
-	return option_struct
+	return option_struct, nil
 }
 
 // GenerateSurtFromURL is the main function for generating a Surt from a URL.
@@ -78,17 +156,185 @@ func GenerateSurtFromURL(url string, options ...map[string]bool) (string, error)
 	var res C.Results
 
 	if len(options) > 0 {
-		options_struct := setupOptions(options[0])
+		options_struct, err := setupOptions(options[0])
+		if err != nil {
+			return "", err
+		}
 		defer C.destroy_options(options_struct)
 
 		res = C.generate_surt_with_options(url_cstr, options_struct)
 	} else {
 		res = C.generate_surt(url_cstr)
 	}
+	defer C.free_results(res)
+
+	if res.error != nil {
+		return "", SurtError{s: C.GoString(res.error), code: ErrorCode(res.code)}
+	}
+
+	return C.GoString(res.output), nil
+}
+
+// Surter generates SURTs using one set of options, which is created once and reused across calls.
+// This avoids rebuilding the options for every URL, as GenerateSurtFromURL does.
+//
+// A Surter is safe for concurrent use, but must not be used after Close is called.
+type Surter struct {
+	options *C.SurtrOptions
+}
+
+// NewSurter creates a Surter. Optionally include a Mapping of the supported Surtr Options.
+// Call Close to free the options once the Surter is no longer needed.
+func NewSurter(options ...map[string]bool) (*Surter, error) {
+	var opts map[string]bool
+	if len(options) > 0 {
+		opts = options[0]
+	}
+
+	options_struct, err := setupOptions(opts)
+	if err != nil {
+		return nil, err
+	}
+
+	return &Surter{options: options_struct}, nil
+}
+
+// Close frees the options held by the Surter. Calling Close more than once has no effect.
+func (s *Surter) Close() {
+	if s.options != nil {
+		C.destroy_options(s.options)
+		s.options = nil
+	}
+}
+
+// Surt generates a Surt from a URL using the options of the Surter.
+func (s *Surter) Surt(url string) (string, error) {
+	url, err := checkString(url)
+	if err != nil {
+		return "", err
+	}
+
+	url_cstr := C.CString(url)
+	defer C.free(unsafe.Pointer(url_cstr))
+
+	res := C.generate_surt_with_options(url_cstr, s.options)
+	defer C.free_results(res)
 
 	if res.error != nil {
-		return "", SurtError{s: C.GoString(res.error)}
+		return "", SurtError{s: C.GoString(res.error), code: ErrorCode(res.code)}
 	}
 
 	return C.GoString(res.output), nil
 }
+
+// SurtMany generates a Surt from each URL using the options of the Surter, crossing into Rust once.
+// The results and errors are returned in the same order as the URLs. For each URL, either the
+// result is set, or the error is not nil.
+func (s *Surter) SurtMany(urls []string) ([]string, []error) {
+	results := make([]string, len(urls))
+	errs := make([]error, len(urls))
+	if len(urls) == 0 {
+		return results, errs
+	}
+
+	// URLs which cannot be passed to C are sent as null pointers, and their error is kept from Go.
+	url_array := (**C.char)(C.malloc(C.size_t(len(urls)) * C.size_t(unsafe.Sizeof(uintptr(0)))))
+	defer C.free(unsafe.Pointer(url_array))
+
+	url_cstrs := unsafe.Slice(url_array, len(urls))
+	defer func() {
+		for _, url_cstr := range url_cstrs {
+			C.free(unsafe.Pointer(url_cstr))
+		}
+	}()
+
+	for i, url := range urls {
+		url_cstrs[i] = nil
+
+		if _, err := checkString(url); err != nil {
+			errs[i] = err
+			continue
+		}
+
+		url_cstrs[i] = C.CString(url)
+	}
+
+	batch := C.generate_surts(url_array, C.uintptr_t(len(urls)), s.options)
+	defer C.free_batch_results(batch)
+
+	for i, res := range unsafe.Slice(batch.results, int(batch.len)) {
+		if errs[i] != nil {
+			continue
+		}
+
+		if res.error != nil {
+			errs[i] = SurtError{s: C.GoString(res.error), code: ErrorCode(res.code)}
+		} else {
+			results[i] = C.GoString(res.output)
+		}
+	}
+
+	return results, errs
+}
+
+// HostKind describes the kind of host found by GetHostParts.
+type HostKind int
+
+const (
+	// HostKindNone is returned when the URL has no host, such as a mailto: URL.
+	HostKindNone HostKind = iota
+	// HostKindDomain is returned when the host ends in a known public suffix.
+	HostKindDomain
+	// HostKindIPAddress is returned when the host is an IPv4 or IPv6 address.
+	HostKindIPAddress
+	// HostKindUnknownSuffix is returned when the host does not end in a known public suffix.
+	HostKindUnknownSuffix
+)
+
+// HostParts contains the parts of a host, as identified by the Public Suffix List.
+// Missing parts are returned as empty strings.
+type HostParts struct {
+	// Kind is the kind of host which was found.
+	Kind HostKind
+	// Subdomain is the labels below the registered domain. "www.images" for "www.images.amazon.co.uk".
+	Subdomain string
+	// RegisteredDomain is the registered domain. "amazon.co.uk" for "www.images.amazon.co.uk".
+	RegisteredDomain string
+	// Suffix is the public suffix. "co.uk" for "www.images.amazon.co.uk".
+	Suffix string
+}
+
+// goStringOrEmpty is a helper function to convert a possibly null C String into a Go String.
+func goStringOrEmpty(s *C.char) string {
+	if s == nil {
+		return ""
+	}
+
+	return C.GoString(s)
+}
+
+// GetHostParts splits the host of a URL into its subdomain, registered domain and public suffix.
+// A bare host may be passed instead of a URL. The host is canonicalized before it is split.
+func GetHostParts(url string) (HostParts, error) {
+	url, err := checkString(url)
+	if err != nil {
+		return HostParts{}, err
+	}
+
+	url_cstr := C.CString(url)
+	defer C.free(unsafe.Pointer(url_cstr))
+
+	res := C.generate_host_parts(url_cstr)
+	defer C.free_host_parts_results(res)
+
+	if res.error != nil {
+		return HostParts{}, SurtError{s: C.GoString(res.error), code: ErrorCode(res.code)}
+	}
+
+	return HostParts{
+		Kind:             HostKind(res.kind),
+		Subdomain:        goStringOrEmpty(res.subdomain),
+		RegisteredDomain: goStringOrEmpty(res.registered_domain),
+		Suffix:           goStringOrEmpty(res.suffix),
+	}, nil
+}
@@ -15,14 +15,63 @@ typedef struct Results {
    * A C Pointer to the error description.
    */
   const char *error;
+  /**
+   * The error code. SURTR_OK if the output is set.
+   */
+  int32_t code;
 } Results;
 
+/**
+ * A CStyle Struct to pass an array of Results back to Go.
+ */
+typedef struct BatchResults {
+  /**
+   * A C Pointer to the first Results struct. Null if there are no results.
+   */
+  struct Results *results;
+  /**
+   * The number of Results structs.
+   */
+  uintptr_t len;
+} BatchResults;
+
+/**
+ * A CStyle Struct to pass the parts of a host back to Go.
+ */
+typedef struct HostPartsResults {
+  /**
+   * The kind of host which was found.
+   * 0 - The URL has no host. 1 - A domain. 2 - An IP Address. 3 - A domain with an unknown suffix.
+   */
+  uint8_t kind;
+  /**
+   * A C Pointer to the subdomain. Null if there is no subdomain.
+   */
+  const char *subdomain;
+  /**
+   * A C Pointer to the registered domain. Null if there is no registered domain.
+   */
+  const char *registered_domain;
+  /**
+   * A C Pointer to the public suffix. Null if there is no known suffix.
+   */
+  const char *suffix;
+  /**
+   * A C Pointer to the error description.
+   */
+  const char *error;
+  /**
+   * The error code. SURTR_OK if the parts are set.
+   */
+  int32_t code;
+} HostPartsResults;
+
 /**
  * Initialize the SurtrOptions Struct internally. This passes a Pointer back to the Caller.
  *
  * # Returns
  *
- * A Pointer to the SurtrOptions struct.
+ * A Pointer to the SurtrOptions struct, or a null pointer if it could not be created.
  *
  * # Safety
  *
@@ -37,7 +86,7 @@ SurtrOptions *init_options(void);
  *
  * # Arguments
  *
- * * `inst_ref` - A Pointer to the SurtrOptions struct to be destroyed.
+ * * `inst_ref` - A Pointer to the SurtrOptions struct to be destroyed. Null pointers are ignored.
  *
  * # Safety
  *
@@ -55,14 +104,18 @@ void destroy_options(SurtrOptions *inst_ref);
  * * `name` - A Pointer to the c_char containing the name of the option to be set.
  * * `value` - A bool containing the value of the option to be set.
  *
+ * # Returns
+ *
+ * SURTR_OK, or SURTR_INVALID_INPUT if either pointer is null, or the name is not UTF-8 encoded or not a known option.
+ *
  * # Safety
  *
  * This function is unsafe because it takes a pointer to a SurtrOptions struct and a pointer to a c_char and a bool.
  * The caller is responsible for ensuring that the pointer to the SurtrOptions struct is valid and that the pointer to the c_char is valid.
  */
-void set_option(SurtrOptions *inst_ref,
-                const char *name,
-                bool value);
+int32_t set_option(SurtrOptions *inst_ref,
+                   const char *name,
+                   bool value);
 
 /**
  * Generate a SURT from a URL.
@@ -101,3 +154,90 @@ struct Results generate_surt(const char *url);
  */
 struct Results generate_surt_with_options(const char *url,
                                           SurtrOptions *option_ref);
+
+/**
+ * Generate a SURT from each URL in an array, reusing one set of options.
+ *
+ * This avoids crossing the FFI boundary, and copying the options, once per URL.
+ *
+ * # Arguments
+ *
+ * * `urls` - A Pointer to an array of Pointers to the URLs to be transformed. A null URL produces a SURTR_INVALID_INPUT error.
+ * * `len` - The number of URLs in the array.
+ * * `option_ref` - A Pointer to the SurtrOptions struct to be used for the transformation. Null uses the default options.
+ *
+ * # Returns
+ *
+ * A BatchResults struct holding one Results struct per URL, in the same order. It must be freed with free_batch_results.
+ *
+ * # Safety
+ *
+ * This function is unsafe because it takes a pointer to an array of c_char pointers and a pointer to a SurtrOptions struct.
+ * The caller is responsible for ensuring that the array holds `len` pointers, and that every non-null pointer is valid.
+ */
+struct BatchResults generate_surts(const char *const *urls,
+                                   uintptr_t len,
+                                   const SurtrOptions *option_ref);
+
+/**
+ * Split the host of a URL into its subdomain, registered domain and public suffix.
+ *
+ * # Arguments
+ *
+ * * `url` - A Pointer to the c_char containing the URL or host to be split.
+ *
+ * # Returns
+ *
+ * A HostPartsResults struct in the successful Output configuration, or an error configuration if the URL is invalid.
+ *
+ * # Safety
+ *
+ * This function is unsafe because it takes a pointer to a c_char and returns a HostPartsResults struct containing pointers.
+ * The caller is responsible for ensuring that the pointer to the c_char is valid.
+ */
+struct HostPartsResults generate_host_parts(const char *url);
+
+/**
+ * Free the strings within a Results struct returned by generate_surt or generate_surt_with_options.
+ *
+ * # Arguments
+ *
+ * * `results` - The Results struct to be freed.
+ *
+ * # Safety
+ *
+ * This function is unsafe because it frees the pointers within the Results struct.
+ * The caller is responsible for ensuring that the Results struct was returned by this library, that it is only freed once,
+ * and that its pointers are not used after this call.
+ */
+void free_results(struct Results results);
+
+/**
+ * Free the strings within a HostPartsResults struct returned by generate_host_parts.
+ *
+ * # Arguments
+ *
+ * * `results` - The HostPartsResults struct to be freed.
+ *
+ * # Safety
+ *
+ * This function is unsafe because it frees the pointers within the HostPartsResults struct.
+ * The caller is responsible for ensuring that the HostPartsResults struct was returned by this library, that it is only freed once,
+ * and that its pointers are not used after this call.
+ */
+void free_host_parts_results(struct HostPartsResults results);
+
+/**
+ * Free a BatchResults struct returned by generate_surts, including the strings within each of its Results structs.
+ *
+ * # Arguments
+ *
+ * * `batch` - The BatchResults struct to be freed.
+ *
+ * # Safety
+ *
+ * This function is unsafe because it frees the pointers within the BatchResults struct.
+ * The caller is responsible for ensuring that the BatchResults struct was returned by this library, that it is only freed once,
+ * and that its pointers are not used after this call. The Results within it must not be freed with free_results.
+ */
+void free_batch_results(struct BatchResults batch);
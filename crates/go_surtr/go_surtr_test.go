@@ -1,6 +1,13 @@
 package go_surtr
 
-import "testing"
+import (
+	"errors"
+	"fmt"
+	"os"
+	"runtime"
+	"strings"
+	"testing"
+)
 
 func TestGenerateSurtFromUrl(t *testing.T) {
 	testCases := []struct {
@@ -388,3 +395,248 @@ func TestGenerateSurtFromURLOptions(t *testing.T) {
 		}
 	}
 }
+
+func TestGetHostParts(t *testing.T) {
+	testCases := []struct {
+		url         string
+		expected    HostParts
+		shouldError bool
+	}{
+		{
+			url:         "",
+			expected:    HostParts{},
+			shouldError: true,
+		},
+		{
+			url: "http://www.images.amazon.co.uk/",
+			expected: HostParts{
+				Kind:             HostKindDomain,
+				Subdomain:        "www.images",
+				RegisteredDomain: "amazon.co.uk",
+				Suffix:           "co.uk",
+			},
+		},
+		{
+			url: "Example.COM",
+			expected: HostParts{
+				Kind:             HostKindDomain,
+				RegisteredDomain: "example.com",
+				Suffix:           "com",
+			},
+		},
+		{
+			url:      "http://192.168.1.254/info/",
+			expected: HostParts{Kind: HostKindIPAddress},
+		},
+		{
+			url:      "http://intranet.corp/",
+			expected: HostParts{Kind: HostKindUnknownSuffix},
+		},
+		{
+			url:      "mailto:foo@example.com",
+			expected: HostParts{Kind: HostKindNone},
+		},
+	}
+
+	for _, testCase := range testCases {
+		result, err := GetHostParts(testCase.url)
+		if err != nil && !testCase.shouldError {
+			t.Errorf("Expected no error for URL: %s, but got: %v", testCase.url, err)
+		}
+		if err == nil && testCase.shouldError {
+			t.Errorf("Expected error for URL: %s, but got no error", testCase.url)
+		}
+		if result != testCase.expected {
+			t.Errorf("Expected result: %+v, but got: %+v", testCase.expected, result)
+		}
+	}
+}
+
+// residentBytes returns the resident set size of the test process, as reported by Linux.
+// C allocations made by the Rust library are not visible to the Go runtime, so they are measured here.
+func residentBytes(t *testing.T) int64 {
+	t.Helper()
+
+	statm, err := os.ReadFile("/proc/self/statm")
+	if err != nil {
+		t.Skipf("resident memory is not available: %v", err)
+	}
+
+	var size, resident int64
+	if _, err := fmt.Sscan(string(statm), &size, &resident); err != nil {
+		t.Fatalf("failed to parse /proc/self/statm: %v", err)
+	}
+
+	return resident * int64(os.Getpagesize())
+}
+
+func TestGenerateSurtFromURLFreesResults(t *testing.T) {
+	// A long URL makes any leaked output obvious: 100,000 calls would leak over 400MB.
+	url := "http://www.example.com/" + strings.Repeat("a", 4096)
+	options := map[string]bool{"with_scheme": true}
+
+	call := func() {
+		if _, err := GenerateSurtFromURL(url); err != nil {
+			t.Fatalf("unexpected error: %v", err)
+		}
+		if _, err := GenerateSurtFromURL(url, options); err != nil {
+			t.Fatalf("unexpected error: %v", err)
+		}
+		if _, err := GenerateSurtFromURL("http://%ff/"); err == nil {
+			t.Fatal("expected an error for a host which is not UTF-8")
+		}
+		if _, err := GetHostParts("www.images.amazon.co.uk"); err != nil {
+			t.Fatalf("unexpected error: %v", err)
+		}
+	}
+
+	// Go allocations per call must not depend on the number of calls.
+	few := testing.AllocsPerRun(100, call)
+	many := testing.AllocsPerRun(10000, call)
+	if many > few {
+		t.Errorf("Go allocations grew from %v to %v per call", few, many)
+	}
+
+	runtime.GC()
+	before := residentBytes(t)
+	for i := 0; i < 100000; i++ {
+		call()
+	}
+	runtime.GC()
+	after := residentBytes(t)
+
+	if growth := after - before; growth > 32<<20 {
+		t.Errorf("resident memory grew by %d bytes over 100000 calls", growth)
+	}
+}
+
+func TestSurtErrorCodes(t *testing.T) {
+	testCases := []struct {
+		url      string
+		options  map[string]bool
+		code     ErrorCode
+		sentinel error
+	}{
+		{
+			url:      "",
+			code:     CodeInvalidInput,
+			sentinel: ErrInvalidInput,
+		},
+		{
+			url:      "http://example.com/\x00",
+			code:     CodeInvalidInput,
+			sentinel: ErrInvalidInput,
+		},
+		{
+			url:      "http://\xff\xfe/",
+			code:     CodeInvalidInput,
+			sentinel: ErrInvalidInput,
+		},
+		{
+			url:      "http://%ff/",
+			code:     CodeCanonicalizer,
+			sentinel: ErrCanonicalizer,
+		},
+		{
+			url:      "http://example.com/",
+			options:  map[string]bool{"with\x00scheme": true},
+			code:     CodeInvalidInput,
+			sentinel: ErrInvalidInput,
+		},		{
+			url:      "http://example.com/",
+			options:  map[string]bool{"not_an_option": true},
+			code:     CodeInvalidInput,
+			sentinel: ErrInvalidInput,
+		},
+	}
+
+	for _, testCase := range testCases {
+		var err error
+		if testCase.options != nil {
+			_, err = GenerateSurtFromURL(testCase.url, testCase.options)
+		} else {
+			_, err = GenerateSurtFromURL(testCase.url)
+		}
+
+		var surtErr SurtError
+		if !errors.As(err, &surtErr) {
+			t.Fatalf("Expected a SurtError for URL: %q, but got: %v", testCase.url, err)
+		}
+		if surtErr.Code() != testCase.code {
+			t.Errorf("Expected code %d for URL: %q, but got: %d", testCase.code, testCase.url, surtErr.Code())
+		}
+		if !errors.Is(err, testCase.sentinel) {
+			t.Errorf("Expected errors.Is(%v, %v) for URL: %q", err, testCase.sentinel, testCase.url)
+		}
+		if errors.Is(err, ErrURLParse) {
+			t.Errorf("Expected errors.Is(%v, ErrURLParse) to be false for URL: %q", err, testCase.url)
+		}
+	}
+
+	if _, err := GetHostParts("\xff"); !errors.Is(err, ErrInvalidInput) {
+		t.Errorf("Expected ErrInvalidInput for a host which is not UTF-8, but got: %v", err)
+	}
+}
+
+func TestSurter(t *testing.T) {
+	surter, err := NewSurter(map[string]bool{"with_scheme": true})
+	if err != nil {
+		t.Fatalf("unexpected error: %v", err)
+	}
+	defer surter.Close()
+
+	urls := []string{
+		"http://www.archive.org/",
+		"",
+		"http://archive.org/goo/?a=2&b&a=1",
+		"http://%ff/",
+		"dns:archive.org",
+		"http://example.com/\x00",
+	}
+	expected := []string{
+		"http://(org,archive)/",
+		"",
+		"http://(org,archive)/goo?a=1&a=2&b",
+		"",
+		"dns:archive.org",
+		"",
+	}
+	expectedErrs := []error{nil, ErrInvalidInput, nil, ErrCanonicalizer, nil, ErrInvalidInput}
+
+	results, errs := surter.SurtMany(urls)
+	if len(results) != len(urls) || len(errs) != len(urls) {
+		t.Fatalf("Expected %d results and errors, but got %d and %d", len(urls), len(results), len(errs))
+	}
+
+	for i, url := range urls {
+		if results[i] != expected[i] {
+			t.Errorf("Expected result: %s for URL: %q, but got: %s", expected[i], url, results[i])
+		}
+		if expectedErrs[i] == nil && errs[i] != nil {
+			t.Errorf("Expected no error for URL: %q, but got: %v", url, errs[i])
+		}
+		if expectedErrs[i] != nil && !errors.Is(errs[i], expectedErrs[i]) {
+			t.Errorf("Expected error %v for URL: %q, but got: %v", expectedErrs[i], url, errs[i])
+		}
+
+		// A single call gives the same result as the batch.
+		result, err := surter.Surt(url)
+		if result != expected[i] || (err == nil) != (expectedErrs[i] == nil) {
+			t.Errorf("Expected Surt(%q) to match SurtMany, but got: %s, %v", url, result, err)
+		}
+	}
+
+	if results, errs := surter.SurtMany(nil); len(results) != 0 || len(errs) != 0 {
+		t.Errorf("Expected no results for no URLs, but got: %v, %v", results, errs)
+	}
+
+	defaults, err := NewSurter()
+	if err != nil {
+		t.Fatalf("unexpected error: %v", err)
+	}
+	if result, err := defaults.Surt("http://www.archive.org/"); err != nil || result != "org,archive)/" {
+		t.Errorf("Expected org,archive)/ with the default options, but got: %s, %v", result, err)
+	}
+	defaults.Close()
+	defaults.Close()
+}
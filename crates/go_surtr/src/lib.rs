@@ -8,16 +8,28 @@
 //! [SURT]: http://crawler.archive.org/articles/user_manual/glossary.html#surt
 //! [IA implementation]: https://github.com/internetarchive/surt
 //! 
+//! # Errors
+//! 
+//! Every exported function catches panics, so a bug in Surtr is reported as an error rather than aborting the caller.
+//! Errors are returned with a stable numeric code, one of the `SURTR_*` constants, alongside their description.
+//! The constants, and the plumbing behind every function, are shared with `c_surtr`, so the Go package
+//! includes `c_surtr.h` for the constants alongside `go_surtr.h`.
+//! 
 //! # Safety
 //! 
 //! This crate is unsafe because it uses C bindings to call the Rust code.
 //! The caller is responsible for ensuring that the pointer to the SurtrOptions struct is valid and that the SurtrOptions struct is not null.
 //! The caller is responsible for ensuring that the pointer to the c_char is valid and that the Results struct is not null.
 //! The caller is responsible for ensuring that the pointer to the c_char is valid and that the Results struct is not null.
+//! The strings within every returned Results and HostPartsResults struct are owned by Rust. The caller must copy them,
+//! then pass the struct to free_results or free_host_parts_results exactly once.
 
 
-use std::ffi::{CStr, CString, c_char};
-use surtr::SurtrOptions;
+use c_surtr::ffi::{batch_options, error_c_str, error_code, from_c_str, panic_message};
+use c_surtr::{SurtrResult, SURTR_INVALID_INPUT, SURTR_OK, SURTR_PANIC};
+use std::ffi::{CString, c_char};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use surtr::{HostParts, SurtrOptions};
 
 /// A CStyle Struct to pass errors back to Go.
 #[repr(C)]
@@ -26,6 +38,8 @@ pub struct Results {
     output: *const c_char,
     /// A C Pointer to the error description.
     error: *const c_char,
+    /// The error code. SURTR_OK if the output is set.
+    code: i32,
 }
 
 impl Results {
@@ -37,54 +51,171 @@ impl Results {
     /// 
     /// # Returns
     /// 
-    /// A Results struct in the successful Output configuration.
+    /// A Results struct in the successful Output configuration, or the Error configuration if the
+    /// output contains a NUL byte.
     pub fn from_string(s: String) -> Self {
+        Self::from(SurtrResult::from_string(s))
+    }
+
+    /// Create a Results struct from an error description.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `code` - The error code.
+    /// * `e` - The error description.
+    /// 
+    /// # Returns
+    /// 
+    /// A Results struct in the Error configuration.
+    pub fn from_error(code: i32, e: String) -> Self {
+        Self::from(SurtrResult::from_error(code, e))
+    }
+}
+
+impl From<SurtrResult> for Results {
+    fn from(result: SurtrResult) -> Self {
         Self {
-            output: CString::new(s).unwrap().into_raw(),
+            output: result.output,
+            error: result.error,
+            code: result.code,
+        }
+    }
+}
+
+/// A CStyle Struct to pass an array of Results back to Go.
+#[repr(C)]
+pub struct BatchResults {
+    /// A C Pointer to the first Results struct. Null if there are no results.
+    results: *mut Results,
+    /// The number of Results structs.
+    len: usize,
+}
+
+impl BatchResults {
+    /// Create a BatchResults struct from a list of Results.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `results` - The Results, in the order of the URLs they were created from.
+    /// 
+    /// # Returns
+    /// 
+    /// A BatchResults struct owning the Results.
+    pub fn from_results(results: Vec<Results>) -> Self {
+        if results.is_empty() {
+            return Self { results: ::std::ptr::null_mut(), len: 0 };
+        }
+
+        let len = results.len();
+        let results = Box::into_raw(results.into_boxed_slice()) as *mut Results;
+
+        Self { results, len }
+    }
+}
+
+/// A CStyle Struct to pass the parts of a host back to Go.
+#[repr(C)]
+pub struct HostPartsResults {
+    /// The kind of host which was found.
+    /// 0 - The URL has no host. 1 - A domain. 2 - An IP Address. 3 - A domain with an unknown suffix.
+    kind: u8,
+    /// A C Pointer to the subdomain. Null if there is no subdomain.
+    subdomain: *const c_char,
+    /// A C Pointer to the registered domain. Null if there is no registered domain.
+    registered_domain: *const c_char,
+    /// A C Pointer to the public suffix. Null if there is no known suffix.
+    suffix: *const c_char,
+    /// A C Pointer to the error description.
+    error: *const c_char,
+    /// The error code. SURTR_OK if the parts are set.
+    code: i32,
+}
+
+impl HostPartsResults {
+    /// Create a HostPartsResults struct from the parts of a host.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `parts` - The parts of the host, or None if the URL has no host.
+    /// 
+    /// # Returns
+    /// 
+    /// A HostPartsResults struct in the successful Output configuration.
+    pub fn from_parts(parts: Option<HostParts>) -> Self {
+        let kind = match &parts {
+            None => 0,
+            Some(HostParts::Domain { .. }) => 1,
+            Some(HostParts::IpAddress(_)) => 2,
+            Some(HostParts::UnknownSuffix(_)) => 3,
+        };
+
+        Self {
+            kind,
+            subdomain: to_c_str(parts.as_ref().and_then(|p| p.subdomain())),
+            registered_domain: to_c_str(parts.as_ref().and_then(|p| p.registered_domain())),
+            suffix: to_c_str(parts.as_ref().and_then(|p| p.suffix())),
             error: ::std::ptr::null(),
+            code: SURTR_OK,
         }
     }
 
-    /// Create a Results struct from an error description.
+    /// Create a HostPartsResults struct from an error description.
     /// 
     /// # Arguments
     /// 
+    /// * `code` - The error code.
     /// * `e` - The error description.
     /// 
     /// # Returns
     /// 
-    /// A Results struct in the Error configuration.
-    pub fn from_error(e: String) -> Self {
+    /// A HostPartsResults struct in the Error configuration.
+    pub fn from_error(code: i32, e: String) -> Self {
         Self {
-            output: ::std::ptr::null(),
-            error: CString::new(e).unwrap().into_raw(),
+            kind: 0,
+            subdomain: ::std::ptr::null(),
+            registered_domain: ::std::ptr::null(),
+            suffix: ::std::ptr::null(),
+            error: error_c_str(e),
+            code,
         }
     }
 }
 
+// Internal function to free a C Pointer created by to_c_str or CString::into_raw. Null pointers are ignored.
+//
+// # Safety
+//
+// The pointer must have been created by CString::into_raw, and must not be used after this call.
+unsafe fn free_c_str(s: *const c_char) {
+    if !s.is_null() {
+        unsafe { drop(CString::from_raw(s as *mut c_char)) };
+    }
+}
+
+// Internal function to convert an optional string into a C Pointer. None, and strings containing a NUL byte,
+// are converted into a null pointer.
+fn to_c_str(s: Option<&str>) -> *const c_char {
+    match s.map(CString::new) {
+        Some(Ok(s)) => s.into_raw(),
+        _ => ::std::ptr::null(),
+    }
+}
 
 // Internal function to call surtr and parse the error into an appropriate Results Object.
+// Panics are caught and returned as errors. The options are borrowed, not copied.
 //
-// # Arguments
-// 
-// * `url` - The URL to be transformed.
-// * `options` - The options to be used for the transformation.
+// # Safety
 //
-// # Returns
-//
-// A Results struct in the successful Output configuration, or an error configuration if the URL is invalid.
-fn surt(url: &str, options: Option<SurtrOptions>) -> Results {
-    match surtr::surt(url, options) {
-        Ok(s) => Results::from_string(s),
-        Err(e) => Results::from_error(e.to_string())
-    }
+// The URL pointer must be null or point to a NUL terminated string.
+unsafe fn surt(url: *const c_char, options: Option<&SurtrOptions>) -> Results {
+    Results::from(unsafe { c_surtr::ffi::surt(url, options) })
 }
 
 /// Initialize the SurtrOptions Struct internally. This passes a Pointer back to the Caller.
 /// 
 /// # Returns
 /// 
-/// A Pointer to the SurtrOptions struct.
+/// A Pointer to the SurtrOptions struct, or a null pointer if it could not be created.
 /// 
 /// # Safety
 /// 
@@ -93,27 +224,28 @@ fn surt(url: &str, options: Option<SurtrOptions>) -> Results {
 /// to prevent memory leaks.
 #[unsafe(no_mangle)]
 pub extern "C" fn init_options() -> *mut SurtrOptions {
-    let new_options = SurtrOptions::default();
-    let boxed_options = Box::new(new_options);
-
-    Box::into_raw(boxed_options)
+    catch_unwind(|| Box::into_raw(Box::new(SurtrOptions::default()))).unwrap_or(::std::ptr::null_mut())
 }
 
 /// Destroy the SurtrOptions Struct internally. This frees the memory allocated for the SurtrOptions struct.
 /// 
 /// # Arguments
 /// 
-/// * `inst_ref` - A Pointer to the SurtrOptions struct to be destroyed.
-///
+/// * `inst_ref` - A Pointer to the SurtrOptions struct to be destroyed. Null pointers are ignored.
+/// 
 /// # Safety
 /// 
 /// This function is unsafe because it takes a pointer to a SurtrOptions struct and returns a pointer to a SurtrOptions struct.
 /// The caller is responsible for ensuring that the pointer to the SurtrOptions struct is valid and that the SurtrOptions struct is not null.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn destroy_options(inst_ref: *mut SurtrOptions) {
-    unsafe {
-        let _ = Box::from_raw(inst_ref);
-    };
+    if inst_ref.is_null() {
+        return;
+    }
+
+    let _ = catch_unwind(AssertUnwindSafe(|| unsafe {
+        drop(Box::from_raw(inst_ref));
+    }));
 }
 
 /// Set an option within the SurtrOptions struct.
@@ -123,17 +255,23 @@ pub unsafe extern "C" fn destroy_options(inst_ref: *mut SurtrOptions) {
 /// * `inst_ref` - A Pointer to the SurtrOptions struct to be modified.
 /// * `name` - A Pointer to the c_char containing the name of the option to be set.
 /// * `value` - A bool containing the value of the option to be set.
-///
+/// 
+/// # Returns
+/// 
+/// SURTR_OK, or SURTR_INVALID_INPUT if either pointer is null, or the name is not UTF-8 encoded or not a known option.
+/// 
 /// # Safety
 /// 
 /// This function is unsafe because it takes a pointer to a SurtrOptions struct and a pointer to a c_char and a bool.
 /// The caller is responsible for ensuring that the pointer to the SurtrOptions struct is valid and that the pointer to the c_char is valid.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn set_option(inst_ref: *mut SurtrOptions, name: *const c_char, value: bool) {
-    let input_str = unsafe {CStr::from_ptr(name).to_str().unwrap() };
+pub unsafe extern "C" fn set_option(inst_ref: *mut SurtrOptions, name: *const c_char, value: bool) -> i32 {
+    if inst_ref.is_null() {
+        return SURTR_INVALID_INPUT;
+    }
 
-    let options_instance = unsafe { &mut *inst_ref };
-    options_instance.set(input_str, value);
+    catch_unwind(AssertUnwindSafe(|| unsafe { c_surtr::ffi::set_option(&mut *inst_ref, name, value) }))
+        .unwrap_or(SURTR_PANIC)
 }
 
 /// Generate a SURT from a URL.
@@ -141,7 +279,7 @@ pub unsafe extern "C" fn set_option(inst_ref: *mut SurtrOptions, name: *const c_
 /// # Arguments
 /// 
 /// * `url` - A Pointer to the c_char containing the URL to be transformed.
-///
+/// 
 /// # Returns
 /// 
 /// A Results struct in the successful Output configuration, or an error configuration if the URL is invalid.
@@ -152,10 +290,7 @@ pub unsafe extern "C" fn set_option(inst_ref: *mut SurtrOptions, name: *const c_
 /// The caller is responsible for ensuring that the pointer to the c_char is valid and that the Results struct is not null.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn generate_surt(url: *const c_char) -> Results {
-    let input_cstr = unsafe { CStr::from_ptr(url) };
-    let input = input_cstr.to_str().unwrap().to_string();
-
-    surt(&input, None)
+    unsafe { surt(url, None) }
 }
 
 /// Generate a SURT from a URL with custom options.
@@ -164,7 +299,7 @@ pub unsafe extern "C" fn generate_surt(url: *const c_char) -> Results {
 /// 
 /// * `url` - A Pointer to the c_char containing the URL to be transformed.
 /// * `option_ref` - A Pointer to the SurtrOptions struct to be used for the transformation.
-///
+/// 
 /// # Returns
 /// 
 /// A Results struct in the successful Output configuration, or an error configuration if the URL is invalid.
@@ -175,10 +310,136 @@ pub unsafe extern "C" fn generate_surt(url: *const c_char) -> Results {
 /// The caller is responsible for ensuring that the pointer to the SurtrOptions struct is valid and that the Results struct is not null.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn generate_surt_with_options(url: *const c_char, option_ref: *mut SurtrOptions) -> Results {
-    let input_cstr = unsafe { CStr::from_ptr(url) };
-    let input = input_cstr.to_str().unwrap().to_string();
+    if option_ref.is_null() {
+        return Results::from_error(SURTR_INVALID_INPUT, "InvalidInput: options is null".to_string());
+    }
+
+    unsafe { surt(url, option_ref.as_ref()) }
+}
+
+/// Generate a SURT from each URL in an array, reusing one set of options.
+/// 
+/// This avoids crossing the FFI boundary, and copying the options, once per URL.
+/// 
+/// # Arguments
+/// 
+/// * `urls` - A Pointer to an array of Pointers to the URLs to be transformed. A null URL produces a SURTR_INVALID_INPUT error.
+/// * `len` - The number of URLs in the array.
+/// * `option_ref` - A Pointer to the SurtrOptions struct to be used for the transformation. Null uses the default options.
+///
+/// # Returns
+/// 
+/// A BatchResults struct holding one Results struct per URL, in the same order. It must be freed with free_batch_results.
+/// 
+/// # Safety
+/// 
+/// This function is unsafe because it takes a pointer to an array of c_char pointers and a pointer to a SurtrOptions struct.
+/// The caller is responsible for ensuring that the array holds `len` pointers, and that every non-null pointer is valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn generate_surts(urls: *const *const c_char, len: usize, option_ref: *const SurtrOptions) -> BatchResults {
+    if urls.is_null() || len == 0 {
+        return BatchResults::from_results(vec![]);
+    }
+
+    let options = batch_options(unsafe { option_ref.as_ref() });
+
+    let urls = unsafe { std::slice::from_raw_parts(urls, len) };
+    let results = urls.iter().map(|url| unsafe { surt(*url, Some(&options)) }).collect();
+
+    BatchResults::from_results(results)
+}
+
+/// Split the host of a URL into its subdomain, registered domain and public suffix.
+/// 
+/// # Arguments
+/// 
+/// * `url` - A Pointer to the c_char containing the URL or host to be split.
+/// 
+/// # Returns
+/// 
+/// A HostPartsResults struct in the successful Output configuration, or an error configuration if the URL is invalid.
+/// 
+/// # Safety
+/// 
+/// This function is unsafe because it takes a pointer to a c_char and returns a HostPartsResults struct containing pointers.
+/// The caller is responsible for ensuring that the pointer to the c_char is valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn generate_host_parts(url: *const c_char) -> HostPartsResults {
+    catch_unwind(AssertUnwindSafe(|| {
+        let input = match unsafe { from_c_str(url, "url") } {
+            Ok(s) => s,
+            Err(e) => return HostPartsResults::from_error(SURTR_INVALID_INPUT, e),
+        };
+
+        match surtr::host_parts(input) {
+            Ok(parts) => HostPartsResults::from_parts(parts),
+            Err(e) => HostPartsResults::from_error(error_code(&e), e.to_string()),
+        }
+    }))
+    .unwrap_or_else(|payload| HostPartsResults::from_error(SURTR_PANIC, panic_message(payload)))
+}
+
+/// Free the strings within a Results struct returned by generate_surt or generate_surt_with_options.
+/// 
+/// # Arguments
+/// 
+/// * `results` - The Results struct to be freed.
+/// 
+/// # Safety
+/// 
+/// This function is unsafe because it frees the pointers within the Results struct.
+/// The caller is responsible for ensuring that the Results struct was returned by this library, that it is only freed once,
+/// and that its pointers are not used after this call.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn free_results(results: Results) {
+    let _ = catch_unwind(|| unsafe {
+        free_c_str(results.output);
+        free_c_str(results.error);
+    });
+}
+
+/// Free the strings within a HostPartsResults struct returned by generate_host_parts.
+/// 
+/// # Arguments
+/// 
+/// * `results` - The HostPartsResults struct to be freed.
+/// 
+/// # Safety
+/// 
+/// This function is unsafe because it frees the pointers within the HostPartsResults struct.
+/// The caller is responsible for ensuring that the HostPartsResults struct was returned by this library, that it is only freed once,
+/// and that its pointers are not used after this call.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn free_host_parts_results(results: HostPartsResults) {
+    let _ = catch_unwind(|| unsafe {
+        free_c_str(results.subdomain);
+        free_c_str(results.registered_domain);
+        free_c_str(results.suffix);
+        free_c_str(results.error);
+    });
+}
 
-    let options = unsafe { (*option_ref).clone() };
-    
-    surt(&input, Some(options.clone()))
+/// Free a BatchResults struct returned by generate_surts, including the strings within each of its Results structs.
+/// 
+/// # Arguments
+/// 
+/// * `batch` - The BatchResults struct to be freed.
+/// 
+/// # Safety
+/// 
+/// This function is unsafe because it frees the pointers within the BatchResults struct.
+/// The caller is responsible for ensuring that the BatchResults struct was returned by this library, that it is only freed once,
+/// and that its pointers are not used after this call. The Results within it must not be freed with free_results.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn free_batch_results(batch: BatchResults) {
+    if batch.results.is_null() {
+        return;
+    }
+
+    let _ = catch_unwind(|| unsafe {
+        let results = Box::from_raw(std::ptr::slice_from_raw_parts_mut(batch.results, batch.len));
+        for result in results.into_vec() {
+            free_results(result);
+        }
+    });
 }
@@ -1,4 +1,4 @@
-from .py_surtr import surt
+from .py_surtr import surt, surt_many, host_parts, HandyUrl
 from .py_surtr import (
     CanonicalizerError,
     NoSchemeFoundError,
@@ -8,6 +8,9 @@ from .py_surtr import (
 
 __all__ = [
     "surt",
+    "surt_many",
+    "host_parts",
+    "HandyUrl",
     "CanonicalizerError",
     "NoSchemeFoundError",
     "SurtrException",
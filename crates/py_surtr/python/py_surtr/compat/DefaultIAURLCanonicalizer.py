@@ -0,0 +1,12 @@
+"""The canonicalize function of `surt.DefaultIAURLCanonicalizer`, backed by surtr."""
+
+
+def canonicalize(url, **options):
+    """Canonicalize a handyurl with GoogleURLCanonicalizer, then IAURLCanonicalizer. This is what surt() uses by default.
+
+    The handyurl is changed in place and returned. The options are the same as those of surt().
+    """
+    return url._canonicalize("default", **options)
+
+
+canonicalize._surtr_canonicalizer = "default"
@@ -0,0 +1,12 @@
+"""The canonicalize function of `surt.GoogleURLCanonicalizer`, backed by surtr."""
+
+
+def canonicalize(url, **options):
+    """Canonicalize a handyurl as Google's Safe Browsing API does.
+
+    The handyurl is changed in place and returned. The options are the same as those of surt().
+    """
+    return url._canonicalize("google", **options)
+
+
+canonicalize._surtr_canonicalizer = "google"
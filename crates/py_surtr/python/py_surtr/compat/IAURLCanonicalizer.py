@@ -0,0 +1,12 @@
+"""The canonicalize function of `surt.IAURLCanonicalizer`, backed by surtr."""
+
+
+def canonicalize(url, **options):
+    """Canonicalize a handyurl with the Internet Archive's rules, such as removing www and sorting the query.
+
+    The handyurl is changed in place and returned. The options are the same as those of surt().
+    """
+    return url._canonicalize("ia", **options)
+
+
+canonicalize._surtr_canonicalizer = "ia"
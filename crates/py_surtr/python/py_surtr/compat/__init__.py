@@ -0,0 +1,14 @@
+"""A drop-in replacement for the Internet Archive's `surt` package, backed by surtr.
+
+Swap `import surt` for `from py_surtr import compat as surt` to keep existing call sites.
+"""
+from . import DefaultIAURLCanonicalizer, GoogleURLCanonicalizer, IAURLCanonicalizer, handyurl
+from .surt import surt
+
+__all__ = [
+    "surt",
+    "handyurl",
+    "DefaultIAURLCanonicalizer",
+    "GoogleURLCanonicalizer",
+    "IAURLCanonicalizer",
+]
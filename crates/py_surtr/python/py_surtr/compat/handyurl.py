@@ -0,0 +1,124 @@
+"""The parsed form of a URL, compatible with `surt.handyurl`."""
+from ..py_surtr import HandyUrl, host_parts
+
+_FIELDS = (
+    "scheme",
+    "auth_user",
+    "auth_pass",
+    "host",
+    "port",
+    "path",
+    "query",
+    "hash",
+    "last_delimiter",
+)
+
+# The HandyUrl method run by each canonicalizer module.
+_CANONICALIZE_METHODS = {
+    "google": "canonicalize_google",
+    "ia": "canonicalize_ia",
+    "default": "canonicalize",
+}
+
+
+def _to_bytes(value):
+    if value is None or isinstance(value, bytes):
+        return value
+    return str(value).encode("utf-8")
+
+
+def _to_str(value):
+    if value is None or isinstance(value, str):
+        return value
+    if isinstance(value, bytes):
+        return value.decode("utf-8")
+    return str(value)
+
+
+class handyurl(object):
+    """A URL split into its parts. Every part is bytes, except the port which is an int."""
+
+    DEFAULT_PORT = None
+
+    def __init__(
+        self,
+        scheme=None,
+        auth_user=None,
+        auth_pass=None,
+        host=None,
+        port=None,
+        path=None,
+        query=None,
+        hash=None,
+        last_delimiter=None,
+    ):
+        self.scheme = _to_bytes(scheme)
+        self.auth_user = _to_bytes(auth_user)
+        self.auth_pass = _to_bytes(auth_pass)
+        self.host = _to_bytes(host)
+        self.port = int(port) if port is not None else None
+        self.path = _to_bytes(path)
+        self.query = _to_bytes(query)
+        self.hash = _to_bytes(hash)
+        self.last_delimiter = _to_bytes(last_delimiter)
+
+    @classmethod
+    def parse(cls, url, **options):
+        """Parse a URL (str or bytes) into a handyurl. A scheme of http is assumed if none is given."""
+        return cls._from_handy_url(HandyUrl.parse(url, **options))
+
+    @classmethod
+    def _from_handy_url(cls, url):
+        return cls(**{name: getattr(url, name) for name in _FIELDS})
+
+    def _handy_url(self):
+        return HandyUrl(**{name: _to_str(getattr(self, name)) for name in _FIELDS})
+
+    def _canonicalize(self, canonicalizer, **options):
+        url = getattr(self._handy_url(), _CANONICALIZE_METHODS[canonicalizer])(**options)
+        self.__init__(**{name: getattr(url, name) for name in _FIELDS})
+        return self
+
+    def getURLString(
+        self,
+        surt=False,
+        public_suffix=False,
+        trailing_comma=False,
+        reverse_ipaddr=True,
+        with_scheme=True,
+        **options,
+    ):
+        """Build the URL, or its SURT if surt=True, as bytes."""
+        options.update(
+            surt=surt,
+            public_suffix=public_suffix,
+            trailing_comma=trailing_comma,
+            reverse_ipaddr=reverse_ipaddr,
+            with_scheme=with_scheme,
+        )
+        return self._handy_url().get_url(**options).encode("utf-8")
+
+    def geturl(self):
+        """The URL as a str."""
+        return self.getURLString().decode("utf-8")
+
+    def geturl_bytes(self):
+        """The URL as bytes."""
+        return self.getURLString()
+
+    def getPublicSuffix(self):
+        """The registered domain of the host, such as b"example.co.uk", or the whole host if it has none."""
+        parts = host_parts(self.host) if self.host else None
+        if parts is None or parts["registered_domain"] is None:
+            return self.host
+        return parts["registered_domain"].encode("utf-8")
+
+    def getPublicPrefix(self):
+        """The subdomain of the host, such as b"www", or b"" if it has none."""
+        parts = host_parts(self.host) if self.host else None
+        if parts is None or parts["subdomain"] is None:
+            return b""
+        return parts["subdomain"].encode("utf-8")
+
+    def __repr__(self):
+        return "handyurl(%s)" % ", ".join("%s=%r" % (name, getattr(self, name)) for name in _FIELDS)
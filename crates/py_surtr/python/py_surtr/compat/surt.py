@@ -0,0 +1,50 @@
+"""The surt function, compatible with `surt.surt`."""
+from ..py_surtr import _surt_with
+from . import DefaultIAURLCanonicalizer
+from .handyurl import handyurl
+
+
+def _canonicalizers(canonicalizer):
+    if canonicalizer is None:
+        return [DefaultIAURLCanonicalizer.canonicalize]
+    if isinstance(canonicalizer, (list, tuple)):
+        return [c for item in canonicalizer for c in _canonicalizers(item)]
+    if callable(canonicalizer):
+        return [canonicalizer]
+    if callable(getattr(canonicalizer, "canonicalize", None)):
+        return [canonicalizer.canonicalize]
+
+    raise TypeError("canonicalizer must be a callable, a module with canonicalize or a list of them")
+
+
+def surt(url, canonicalizer=None, **options):
+    """Convert a URL into a SURT, with the same signature as `surt.surt`.
+
+    The canonicalizer is a function taking a handyurl and the options, a module with a
+    `canonicalize` function, or a list of them which are run in order. It defaults to
+    DefaultIAURLCanonicalizer. When every canonicalizer is one of the ones in this package, the
+    whole conversion runs in Rust.
+
+    A bytes URL gives a bytes SURT.
+    """
+    if isinstance(url, bytes):
+        return surt(url.decode("utf-8"), canonicalizer, **options).encode("utf-8")
+
+    if not url:
+        return "-"
+    if url.startswith("filedesc"):
+        return url
+
+    canonicalizers = _canonicalizers(canonicalizer)
+    stages = [getattr(c, "_surtr_canonicalizer", None) for c in canonicalizers]
+    if None not in stages:
+        return _surt_with(url, stages, **options)
+
+    options.setdefault("surt", True)
+    options.setdefault("with_scheme", False)
+
+    hurl = handyurl.parse(url, **options)
+    for c in canonicalizers:
+        hurl = c(hurl, **options)
+
+    return hurl.getURLString(**options).decode("utf-8")
@@ -1,6 +1,63 @@
+from typing import Any, Iterable, Literal, TypedDict
+
 class SurtrException(Exception): ...
 class UrlParseError(SurtrException): ...
 class NoSchemeFoundError(SurtrException): ...
 class CanonicalizerError(SurtrException): ...
 
-def surt(url: str | bytes, **kwargs: bool) -> str | bytes: ...
+class HostParts(TypedDict):
+    kind: Literal["domain", "ip_address", "unknown_suffix"]
+    subdomain: str | None
+    registered_domain: str | None
+    suffix: str | None
+
+def surt(url: str | bytes, **kwargs: bool | str | int | None) -> str | bytes: ...
+def surt_many(
+    urls: Iterable[str | bytes | None],
+    errors: Literal["raise", "return", "sentinel"] = "raise",
+    sentinel: Any = None,
+    threads: int = 1,
+    **kwargs: bool | str | int | None,
+) -> list[str | bytes | Any]: ...
+def host_parts(url: str | bytes) -> HostParts | None: ...
+
+class HandyUrl:
+    scheme: str | None
+    auth_user: str | None
+    auth_pass: str | None
+    host: str | None
+    port: str | None
+    path: str | None
+    query: str | None
+    hash: str | None
+    last_delimiter: str | None
+    @property
+    def replay_timestamp(self) -> str | None: ...
+    @property
+    def replay_modifier(self) -> str | None: ...
+    def __init__(
+        self,
+        scheme: str | None = None,
+        auth_user: str | None = None,
+        auth_pass: str | None = None,
+        host: str | None = None,
+        port: str | None = None,
+        path: str | None = None,
+        query: str | None = None,
+        hash: str | None = None,
+        last_delimiter: str | None = None,
+    ) -> None: ...
+    @staticmethod
+    def parse(url: str | bytes, **kwargs: bool | str | int | None) -> HandyUrl: ...
+    def canonicalize(self, **kwargs: bool | str | int | None) -> HandyUrl: ...
+    def canonicalize_google(self, **kwargs: bool | str | int | None) -> HandyUrl: ...
+    def canonicalize_ia(self, **kwargs: bool | str | int | None) -> HandyUrl: ...
+    def get_url(self, **kwargs: bool | str | int | None) -> str: ...
+    def get_host_parts(self) -> HostParts | None: ...
+
+# Internal function backing py_surtr.compat.
+def _surt_with(
+    url: str,
+    canonicalizers: list[Literal["google", "ia", "default"]],
+    **kwargs: bool | str | int | None,
+) -> str: ...
@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use surtr::{HandyUrl, SurtrOptions};
+
+use crate::{build_options, decode_error, host_parts_dict, to_py_err, UrlInput};
+
+// options is an internal function which builds the SurtrOptions for a method from its **kwargs.
+fn options(kwargs: Option<&Bound<'_, PyDict>>) -> PyResult<SurtrOptions> {
+    match kwargs {
+        None => Ok(SurtrOptions::default()),
+        Some(d) => build_options(d),
+    }
+}
+
+/// HandyUrl - A URL parsed into its parts, matching the Rust `surtr::HandyUrl`.
+/// 
+/// Every part is a string, or None if it is not present in the URL. The parts can be read and
+/// changed as attributes. The canonicalize methods return a new HandyUrl, leaving this one unchanged.
+/// 
+/// Args:
+/// 
+/// - scheme, auth_user, auth_pass, host, port, path, query, hash, last_delimiter (str | None) - The parts of the URL.
+/// 
+/// Example:
+/// 
+/// ```python
+/// url = HandyUrl.parse("http://www.Example.com/a/?b=2&a=1")
+/// url.host # "www.Example.com"
+/// url.canonicalize_google().canonicalize_ia().get_url() # "http://example.com/a?a=1&b=2"
+/// ```
+#[pyclass(name = "HandyUrl", module = "py_surtr", eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PyHandyUrl {
+    /// http, ftp, dns -> The protocol being used in the URL.
+    #[pyo3(get, set)]
+    scheme: Option<String>,
+    /// The Basic Authentication username.
+    #[pyo3(get, set)]
+    auth_user: Option<String>,
+    /// The Basic Authentication password.
+    #[pyo3(get, set)]
+    auth_pass: Option<String>,
+    /// The domain name or IP address of the server.
+    #[pyo3(get, set)]
+    host: Option<String>,
+    /// The port number of the server.
+    #[pyo3(get, set)]
+    port: Option<String>,
+    /// The path of the resource being requested.
+    #[pyo3(get, set)]
+    path: Option<String>,
+    /// The query parameters, without the leading `?`.
+    #[pyo3(get, set)]
+    query: Option<String>,
+    /// The fragment, without the leading `#`.
+    #[pyo3(get, set)]
+    hash: Option<String>,
+    /// "?" if the URL ended with an empty query.
+    #[pyo3(get, set)]
+    last_delimiter: Option<String>,
+    /// The capture timestamp of an unwrapped archive replay URL.
+    #[pyo3(get)]
+    replay_timestamp: Option<String>,
+    /// The replay modifier of an unwrapped archive replay URL, such as `id_`.
+    #[pyo3(get)]
+    replay_modifier: Option<String>,
+}
+
+impl From<HandyUrl> for PyHandyUrl {
+    fn from(url: HandyUrl) -> Self {
+        Self {
+            scheme: url.scheme,
+            auth_user: url.auth_user,
+            auth_pass: url.auth_pass,
+            host: url.host,
+            port: url.port,
+            path: url.path,
+            query: url.query,
+            hash: url.hash,
+            last_delimiter: url.last_delimiter,
+            replay_timestamp: url.replay_timestamp,
+            replay_modifier: url.replay_modifier,
+        }
+    }
+}
+
+impl From<PyHandyUrl> for HandyUrl {
+    fn from(url: PyHandyUrl) -> Self {
+        Self {
+            scheme: url.scheme,
+            auth_user: url.auth_user,
+            auth_pass: url.auth_pass,
+            host: url.host,
+            port: url.port,
+            path: url.path,
+            query: url.query,
+            hash: url.hash,
+            last_delimiter: url.last_delimiter,
+            replay_timestamp: url.replay_timestamp,
+            replay_modifier: url.replay_modifier,
+        }
+    }
+}
+
+impl PyHandyUrl {
+    // Run a canonicalizer over a copy of this URL.
+    fn canonicalize_with(
+        &self,
+        kwargs: Option<&Bound<'_, PyDict>>,
+        canonicalizer: fn(HandyUrl, &SurtrOptions) -> Result<HandyUrl, surtr::SurtrError>,
+    ) -> PyResult<Self> {
+        let opts = options(kwargs)?;
+
+        canonicalizer(self.clone().into(), &opts).map(Self::from).map_err(to_py_err)
+    }
+}
+
+#[pymethods]
+impl PyHandyUrl {
+    #[new]
+    #[pyo3(signature = (
+        scheme=None, auth_user=None, auth_pass=None, host=None, port=None,
+        path=None, query=None, hash=None, last_delimiter=None,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        scheme: Option<String>,
+        auth_user: Option<String>,
+        auth_pass: Option<String>,
+        host: Option<String>,
+        port: Option<String>,
+        path: Option<String>,
+        query: Option<String>,
+        hash: Option<String>,
+        last_delimiter: Option<String>,
+    ) -> Self {
+        Self {
+            scheme,
+            auth_user,
+            auth_pass,
+            host,
+            port,
+            path,
+            query,
+            hash,
+            last_delimiter,
+            replay_timestamp: None,
+            replay_modifier: None,
+        }
+    }
+
+    /// parse - Parse a URL into a HandyUrl. A scheme of http is assumed if none is given.
+    /// 
+    /// Args:
+    /// 
+    /// - url (str | bytes) - The URL to parse.
+    /// - **kwargs - The same options as `surt`. Only the parsing options, such as `auth_exclude`, are used.
+    /// 
+    /// Raises:
+    /// 
+    /// - UrlParseError - If the URL is invalid.
+    /// - UnicodeDecodeError - If a bytes URL is not UTF-8 encoded.
+    #[staticmethod]
+    #[pyo3(signature = (url, **kwargs))]
+    fn parse(py: Python<'_>, url: UrlInput, kwargs: Option<&Bound<'_, PyDict>>) -> PyResult<Self> {
+        let opts = options(kwargs)?;
+        let in_url = match url {
+            UrlInput::String(s) => s,
+            UrlInput::Bytes(b) => String::from_utf8(b).map_err(|e| decode_error(py, e))?,
+        };
+
+        HandyUrl::parse(&in_url, &opts).map(Self::from).map_err(to_py_err)
+    }
+
+    /// canonicalize - Canonicalize the URL with both stages used by `surt`: Google, then IA.
+    /// 
+    /// Raises:
+    /// 
+    /// - CanonicalizerError - If a part of the URL is not UTF-8 encoded once unescaped.
+    #[pyo3(signature = (**kwargs))]
+    fn canonicalize(&self, kwargs: Option<&Bound<'_, PyDict>>) -> PyResult<Self> {
+        self.canonicalize_with(kwargs, HandyUrl::canonicalize)
+    }
+
+    /// canonicalize_google - Canonicalize the URL as Google's Safe Browsing API does.
+    /// 
+    /// The hash is removed, the host and path are unescaped and normalized, and the rest
+    /// of the URL is minimally escaped.
+    /// 
+    /// Raises:
+    /// 
+    /// - CanonicalizerError - If a part of the URL is not UTF-8 encoded once unescaped.
+    #[pyo3(signature = (**kwargs))]
+    fn canonicalize_google(&self, kwargs: Option<&Bound<'_, PyDict>>) -> PyResult<Self> {
+        self.canonicalize_with(kwargs, HandyUrl::canonicalize_google)
+    }
+
+    /// canonicalize_ia - Canonicalize the URL as the Internet Archive does, controlled by the
+    /// canonicalization options. For example, `www` is removed from the host and the query is sorted.
+    #[pyo3(signature = (**kwargs))]
+    fn canonicalize_ia(&self, kwargs: Option<&Bound<'_, PyDict>>) -> PyResult<Self> {
+        self.canonicalize_with(kwargs, HandyUrl::canonicalize_ia)
+    }
+
+    /// get_url - Build the URL, or its SURT, from its parts.
+    /// 
+    /// Args:
+    /// 
+    /// - **kwargs - The same options as `surt`. Unlike `surt`, a URL is built unless `surt=True` is passed,
+    ///   and `with_scheme` defaults to True.
+    /// 
+    /// Raises:
+    /// 
+    /// - NoSchemeFoundError - If a scheme is needed, but the URL has none.
+    #[pyo3(signature = (**kwargs))]
+    fn get_url(&self, kwargs: Option<&Bound<'_, PyDict>>) -> PyResult<String> {
+        let opts = options(kwargs)?;
+
+        HandyUrl::from(self.clone()).get_url(&opts).map_err(to_py_err)
+    }
+
+    /// get_host_parts - Split the host into its subdomain, registered domain and public suffix.
+    /// 
+    /// Returns:
+    /// 
+    /// None if the URL has no host. Otherwise the same dictionary as `host_parts`.
+    fn get_host_parts(&self) -> Option<HashMap<&'static str, Option<String>>> {
+        HandyUrl::from(self.clone()).get_host_parts().map(|p| host_parts_dict(&p))
+    }
+
+    fn __repr__(&self) -> String {
+        let fields = [
+            ("scheme", &self.scheme),
+            ("auth_user", &self.auth_user),
+            ("auth_pass", &self.auth_pass),
+            ("host", &self.host),
+            ("port", &self.port),
+            ("path", &self.path),
+            ("query", &self.query),
+            ("hash", &self.hash),
+            ("last_delimiter", &self.last_delimiter),
+        ];
+
+        let parts: Vec<String> = fields
+            .iter()
+            .filter_map(|(name, value)| value.as_ref().map(|v| format!("{}={:?}", name, v)))
+            .collect();
+
+        format!("HandyUrl({})", parts.join(", "))
+    }
+}
@@ -1,7 +1,12 @@
+use std::collections::HashMap;
+use std::string::FromUtf8Error;
+
 use pyo3::{create_exception, prelude::*};
-use pyo3::exceptions::PyException;
+use pyo3::exceptions::{PyException, PyUnicodeDecodeError, PyValueError};
 use pyo3::types::PyDict;
-use surtr::SurtrError;
+use surtr::{HandyUrl, HostParts, SurtrError, SurtrOptions};
+
+mod handy_url;
 
 create_exception!(py_surtr, SurtrException, PyException);
 create_exception!(py_surtr, UrlParseError, SurtrException);
@@ -31,11 +36,22 @@ pub enum UrlOutput {
 
 // build_options is an internal function which converts a Python **kwargs dictionary
 // into the SurtrOptions struct which is needed for Surtr.
-fn build_options(dict: &Bound<'_, PyDict>) -> PyResult<surtr::SurtrOptions> {
+pub(crate) fn build_options(dict: &Bound<'_, PyDict>) -> PyResult<surtr::SurtrOptions> {
     let mut opts = surtr::SurtrOptions::default();
 
     for item in dict.items() {
         let key = item.get_item(0)?.to_string();
+
+        if key == "host_granularity" {
+            let granularity: String = item.get_item(1)?.extract()?;
+            opts.set_host_granularity(granularity.parse().map_err(PyValueError::new_err)?);
+            continue;
+        }
+        if key == "path_depth" {
+            opts.set_path_depth(item.get_item(1)?.extract()?);
+            continue;
+        }
+
         let value = matches!(item.get_item(1)?.to_string().as_str(), "True");
         opts.set(&key, value);
     }
@@ -53,6 +69,8 @@ fn build_options(dict: &Bound<'_, PyDict>) -> PyResult<surtr::SurtrOptions> {
 /// 
 /// - url (str | bytes) - The URL to transform. String or Bytes format.
 /// - **kwargs - A set of named boolean options. View the readme for a complete list.
+///   `host_granularity` takes a string instead: "full", "suffix", "registered_domain" or "registered_domain+N".
+///   `path_depth` takes the number of path segments to keep, or None.
 /// 
 /// Returns:
 /// 
@@ -63,6 +81,7 @@ fn build_options(dict: &Bound<'_, PyDict>) -> PyResult<surtr::SurtrOptions> {
 /// - UrlParseError - If the URL is invalid.
 /// - NoSchemeFoundError - If the parsing expected a Scheme, but couldn't find one.
 /// - CanonicalizerError - If there is an issue during canonicalization.
+/// - ValueError - If `host_granularity` is not a valid granularity.
 #[pyfunction]
 #[pyo3(signature = (url=None, **kwargs))]
 pub fn surt(
@@ -95,11 +114,281 @@ pub fn surt(
             "string" => Ok(UrlOutput::String(s)),
             _ => Ok(UrlOutput::Bytes(s.as_bytes().to_vec())),
         },
-        Err(e) => match e {
-            SurtrError::CanonicalizerError(s) => Err(CanonicalizerError::new_err(s.to_string())),
-            SurtrError::NoSchemeFoundError => Err(NoSchemeFoundError::new_err(e.to_string())),
-            SurtrError::UrlParseError(s) => Err(UrlParseError::new_err(s.to_string()))
-        },
+        Err(e) => Err(to_py_err(e)),
+    }
+}
+
+/// ErrorMode is how surt_many handles URLs which cannot be converted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ErrorMode {
+    Raise,
+    Return,
+    Sentinel,
+}
+
+impl std::str::FromStr for ErrorMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "raise" => Ok(Self::Raise),
+            "return" => Ok(Self::Return),
+            "sentinel" => Ok(Self::Sentinel),
+            _ => Err(format!("invalid errors '{}'. Expected raise, return or sentinel", s)),
+        }
+    }
+}
+
+// surt_all is an internal function which converts every URL, splitting the work between threads.
+// None entries are URLs which failed before conversion, and are left as None.
+// The options are copied once, with the defaults of surtr::surt set, and shared by every URL.
+fn surt_all(
+    urls: &[Option<String>],
+    options: &Option<surtr::SurtrOptions>,
+    threads: usize,
+) -> Vec<Option<Result<String, SurtrError>>> {
+    let options = &options.clone().unwrap_or_default().with_surt_defaults();
+
+    let convert = |url: &Option<String>| -> Option<Result<String, SurtrError>> {
+        url.as_ref().map(|u| match u.is_empty() {
+            true => Ok("-".to_string()),
+            false => surtr::surt_with(u, options),
+        })
+    };
+
+    if threads <= 1 || urls.len() < 2 {
+        return urls.iter().map(convert).collect();
+    }
+
+    let chunk_size = urls.len().div_ceil(threads);
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = urls
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || chunk.iter().map(convert).collect::<Vec<_>>()))
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().expect("surt_many worker thread panicked"))
+            .collect()
+    })
+}
+
+/// surt_many - Convert many URLs into SURTs in a single call.
+/// 
+/// The URLs are converted in Rust with the GIL released, so other Python threads keep running.
+/// The results are returned in the same order as the URLs, with the same type as each URL.
+/// 
+/// Args:
+/// 
+/// - urls (Iterable[str | bytes | None]) - The URLs to transform. None and empty URLs become "-", as with `surt`.
+/// - errors (str) - How URLs which cannot be converted are handled:
+///   "raise" (the default) raises the error of the first one, "return" puts the exception in the list,
+///   and "sentinel" puts `sentinel` in the list.
+/// - sentinel (Any) - The value used for failed URLs when errors="sentinel". Defaults to None.
+/// - threads (int) - The number of threads to convert the URLs with. 0 uses one per CPU. Defaults to 1.
+/// - **kwargs - The same options as `surt`.
+/// 
+/// Returns:
+/// 
+/// A list with one SURT, exception or sentinel per URL.
+/// 
+/// Raises:
+/// 
+/// - UrlParseError, NoSchemeFoundError or CanonicalizerError - If a URL fails and errors="raise".
+/// - UnicodeDecodeError - If a bytes URL is not UTF-8 encoded and errors="raise".
+/// - TypeError - If a URL is not str, bytes or None.
+/// - ValueError - If `errors` or an option is not valid.
+#[pyfunction]
+#[pyo3(signature = (urls, errors="raise", sentinel=None, threads=1, **kwargs))]
+pub fn surt_many(
+    py: Python<'_>,
+    urls: &Bound<'_, PyAny>,
+    errors: &str,
+    sentinel: Option<PyObject>,
+    threads: usize,
+    kwargs: Option<&Bound<'_, PyDict>>,
+) -> PyResult<Vec<PyObject>> {
+    let mode: ErrorMode = errors.parse().map_err(PyValueError::new_err)?;
+    let opts: Option<surtr::SurtrOptions> = match kwargs {
+        None => None,
+        Some(d) => Some(build_options(d)?),
+    };
+    let threads = match threads {
+        0 => std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+        n => n,
+    };
+
+    // Read every URL while holding the GIL. Bytes which are not UTF-8 fail here, before conversion.
+    let mut in_urls: Vec<Option<String>> = Vec::new();
+    let mut is_bytes: Vec<bool> = Vec::new();
+    let mut failures: Vec<Option<PyErr>> = Vec::new();
+
+    for item in urls.try_iter()? {
+        let (url, bytes) = match item?.extract::<Option<UrlInput>>()? {
+            None => (Ok(String::new()), false),
+            Some(UrlInput::String(s)) => (Ok(s), false),
+            Some(UrlInput::Bytes(b)) => (String::from_utf8(b).map_err(|e| decode_error(py, e)), true),
+        };
+
+        match url {
+            Ok(u) => {
+                in_urls.push(Some(u));
+                failures.push(None);
+            }
+            Err(e) => {
+                in_urls.push(None);
+                failures.push(Some(e));
+            }
+        }
+        is_bytes.push(bytes);
+    }
+
+    let results = py.allow_threads(|| surt_all(&in_urls, &opts, threads));
+
+    let mut output = Vec::with_capacity(results.len());
+    for ((result, failure), bytes) in results.into_iter().zip(failures).zip(is_bytes) {
+        let err = match (result, failure) {
+            (Some(Ok(s)), _) => {
+                output.push(match bytes {
+                    true => UrlOutput::Bytes(s.into_bytes()),
+                    false => UrlOutput::String(s),
+                }.into_pyobject(py)?.unbind());
+                continue;
+            }
+            (Some(Err(e)), _) => to_py_err(e),
+            (None, Some(e)) => e,
+            (None, None) => unreachable!("every URL is either converted or failed"),
+        };
+
+        match mode {
+            ErrorMode::Raise => return Err(err),
+            ErrorMode::Return => output.push(err.into_value(py).into_any()),
+            ErrorMode::Sentinel => output.push(match &sentinel {
+                Some(s) => s.clone_ref(py),
+                None => py.None(),
+            }),
+        }
+    }
+
+    Ok(output)
+}
+
+/// host_parts - Split the host of a URL into its subdomain, registered domain and public suffix.
+/// 
+/// The host is canonicalized before it is split. A bare host may be passed instead of a URL.
+/// 
+/// Args:
+/// 
+/// - url (str | bytes) - The URL or host to split. String or Bytes format.
+/// 
+/// Returns:
+/// 
+/// None if the URL has no host. Otherwise a dictionary with the keys:
+/// 
+/// - kind - One of "domain", "ip_address" or "unknown_suffix".
+/// - subdomain - The labels below the registered domain, or None.
+/// - registered_domain - The registered domain, or None for IP addresses and unknown suffixes.
+/// - suffix - The public suffix, or None for IP addresses and unknown suffixes.
+/// 
+/// Raises:
+/// 
+/// - UrlParseError - If the URL is invalid.
+/// - CanonicalizerError - If there is an issue during canonicalization.
+#[pyfunction]
+pub fn host_parts(url: UrlInput) -> PyResult<Option<HashMap<&'static str, Option<String>>>> {
+    let in_url: String = match url {
+        UrlInput::String(s) => s,
+        UrlInput::Bytes(b) => String::from_utf8(b)?,
+    };
+
+    let parts = match surtr::host_parts(&in_url) {
+        Ok(Some(p)) => p,
+        Ok(None) => return Ok(None),
+        Err(e) => return Err(to_py_err(e)),
+    };
+
+    Ok(Some(host_parts_dict(&parts)))
+}
+
+// host_parts_dict is an internal function which converts HostParts into the dictionary returned to Python.
+pub(crate) fn host_parts_dict(parts: &HostParts) -> HashMap<&'static str, Option<String>> {
+    let kind = match parts {
+        HostParts::Domain { .. } => "domain",
+        HostParts::IpAddress(_) => "ip_address",
+        HostParts::UnknownSuffix(_) => "unknown_suffix",
+    };
+
+    HashMap::from([
+        ("kind", Some(kind.to_string())),
+        ("subdomain", parts.subdomain().map(String::from)),
+        ("registered_domain", parts.registered_domain().map(String::from)),
+        ("suffix", parts.suffix().map(String::from)),
+    ])
+}
+
+// canonicalize_stage is an internal function which runs one named canonicalizer over a HandyUrl.
+fn canonicalize_stage(url: HandyUrl, stage: &str, options: &SurtrOptions) -> PyResult<HandyUrl> {
+    let result = match stage {
+        "google" => url.canonicalize_google(options),
+        "ia" => url.canonicalize_ia(options),
+        "default" => url.canonicalize(options),
+        _ => {
+            return Err(PyValueError::new_err(format!(
+                "invalid canonicalizer '{}'. Expected google, ia or default",
+                stage
+            )));
+        }
+    };
+
+    result.map_err(to_py_err)
+}
+
+// surt_with backs `surt` in the compat layer when every canonicalizer is one of the built in ones,
+// so the whole conversion runs in Rust. The canonicalizers are run in order.
+#[pyfunction(name = "_surt_with")]
+#[pyo3(signature = (url, canonicalizers, **kwargs))]
+fn surt_with(url: &str, canonicalizers: Vec<String>, kwargs: Option<&Bound<'_, PyDict>>) -> PyResult<String> {
+    let mut opts = match kwargs {
+        None => SurtrOptions::default(),
+        Some(d) => build_options(d)?,
+    };
+    if opts.get("surt").is_none() {
+        opts.set("surt", true);
+    }
+    if opts.get("with_scheme").is_none() {
+        opts.set("with_scheme", false);
+    }
+
+    if url.is_empty() {
+        return Ok("-".to_string());
+    }
+    if url.starts_with("filedesc") {
+        return Ok(url.to_string());
+    }
+
+    let mut hurl = HandyUrl::parse(url, &opts).map_err(to_py_err)?;
+    for canonicalizer in &canonicalizers {
+        hurl = canonicalize_stage(hurl, canonicalizer, &opts)?;
+    }
+
+    hurl.get_url(&opts).map_err(to_py_err)
+}
+
+// decode_error is an internal function which builds a UnicodeDecodeError for bytes which are not UTF-8.
+pub(crate) fn decode_error(py: Python<'_>, e: FromUtf8Error) -> PyErr {
+    match PyUnicodeDecodeError::new_utf8(py, e.as_bytes(), e.utf8_error()) {
+        Ok(err) => PyErr::from_value(err.into_any()),
+        Err(err) => err,
+    }
+}
+
+// to_py_err is an internal function which maps a SurtrError onto the matching Python exception.
+pub(crate) fn to_py_err(e: SurtrError) -> PyErr {
+    match e {
+        SurtrError::CanonicalizerError(s) => CanonicalizerError::new_err(s),
+        SurtrError::NoSchemeFoundError => NoSchemeFoundError::new_err(e.to_string()),
+        SurtrError::UrlParseError(s) => UrlParseError::new_err(s),
     }
 }
 
@@ -115,5 +404,13 @@ pub fn py_surtr(py: Python<'_>, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add("CanonicalizerError", py.get_type::<CanonicalizerError>())?;
 
     // Add Functions
-    m.add_function(wrap_pyfunction!(surt, m)?)
+    m.add_function(wrap_pyfunction!(surt, m)?)?;
+    m.add_function(wrap_pyfunction!(surt_many, m)?)?;
+    m.add_function(wrap_pyfunction!(host_parts, m)?)?;
+
+    // Add Classes
+    m.add_class::<handy_url::PyHandyUrl>()?;
+
+    // Internal function backing py_surtr.compat
+    m.add_function(wrap_pyfunction!(surt_with, m)?)
 }
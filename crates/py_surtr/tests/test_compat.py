@@ -0,0 +1,99 @@
+import pytest
+from py_surtr import compat as surt
+from py_surtr.compat import (
+    DefaultIAURLCanonicalizer,
+    GoogleURLCanonicalizer,
+    IAURLCanonicalizer,
+)
+from py_surtr.compat.handyurl import handyurl
+
+
+def test_handyurl_parse():
+    # These tests are from the surt package's test_surt.py
+    hurl = handyurl.parse("http://www.archive.org/index.html#item")
+    assert hurl.scheme == b"http"
+    assert hurl.host == b"www.archive.org"
+    assert hurl.path == b"/index.html"
+    assert hurl.hash == b"item"
+    assert hurl.geturl() == "http://www.archive.org/index.html#item"
+    assert hurl.geturl_bytes() == b"http://www.archive.org/index.html#item"
+
+    assert handyurl.parse("http://www.archive.org:8080/").port == 8080
+    assert handyurl.parse("archive.org").geturl() == "http://archive.org"
+    assert handyurl.parse(b"http://archive.org/?").geturl() == "http://archive.org/?"
+
+    hurl = handyurl.parse("http://www.archive.org/goo/")
+    assert hurl.getURLString(surt=True) == b"http://(org,archive,www)/goo/"
+    assert hurl.getURLString(surt=True, with_scheme=False) == b"org,archive,www)/goo/"
+    assert hurl.getURLString(surt=True, with_scheme=False, trailing_comma=True) == b"org,archive,www,)/goo/"
+
+
+def test_handyurl_public_suffix():
+    hurl = handyurl.parse("http://www.news.bbc.co.uk/")
+    assert hurl.getPublicSuffix() == b"bbc.co.uk"
+    assert hurl.getPublicPrefix() == b"www.news"
+
+    hurl = handyurl.parse("http://127.0.0.1/")
+    assert hurl.getPublicSuffix() == b"127.0.0.1"
+    assert hurl.getPublicPrefix() == b""
+
+
+def test_canonicalizers():
+    hurl = GoogleURLCanonicalizer.canonicalize(handyurl.parse("http://www.Example.com:80/a/../b/?z=1&a=2#top"))
+    assert hurl.geturl() == "http://www.example.com:80/b/?z=1&a=2"
+
+    hurl = IAURLCanonicalizer.canonicalize(handyurl.parse("http://www.Example.com/b/?z=1&a=2"))
+    assert hurl.geturl() == "http://example.com/b?a=2&z=1"
+
+    hurl = DefaultIAURLCanonicalizer.canonicalize(handyurl.parse("http://www.Example.com:80/a/../b/?z=1&a=2#top"))
+    assert hurl.geturl() == "http://example.com/b?a=2&z=1"
+
+    # The options are passed through to the canonicalizer.
+    hurl = IAURLCanonicalizer.canonicalize(
+        handyurl.parse("http://example.com/b/"), path_strip_trailing_slash_unless_empty=False
+    )
+    assert hurl.geturl() == "http://example.com/b/"
+
+    # The handyurl is changed in place.
+    hurl = handyurl.parse("http://www.example.com/")
+    assert IAURLCanonicalizer.canonicalize(hurl) is hurl
+    assert hurl.host == b"example.com"
+
+
+def test_surt():
+    # These tests are from the surt package's test_surt.py
+    assert surt.surt(None) == "-"
+    assert surt.surt("") == "-"
+    assert surt.surt("filedesc:foo.arc.gz") == "filedesc:foo.arc.gz"
+    assert surt.surt("dns:archive.org") == "dns:archive.org"
+    assert surt.surt("http://www.archive.org/") == "org,archive)/"
+    assert surt.surt(b"http://archive.org/goo/?a=2&b&a=1") == b"org,archive)/goo?a=1&a=2&b"
+    assert surt.surt("http://archive.org/goo/?a=2&b&a=1", trailing_comma=True) == "org,archive,)/goo?a=1&a=2&b"
+    assert surt.surt("http://www.archive.org/", with_scheme=True) == "http://(org,archive)/"
+
+    url = "http://www.Archive.org/Goo/?a=2&b&a=1"
+    assert surt.surt(url, canonicalizer=surt.GoogleURLCanonicalizer) == "org,archive,www)/Goo/?a=2&b&a=1"
+    assert surt.surt(url, canonicalizer=GoogleURLCanonicalizer.canonicalize) == "org,archive,www)/Goo/?a=2&b&a=1"
+    assert surt.surt(url, canonicalizer=surt.IAURLCanonicalizer) == "org,archive)/goo?a=1&a=2&b"
+    assert surt.surt(url, canonicalizer=surt.DefaultIAURLCanonicalizer) == surt.surt(url)
+    assert (
+        surt.surt(url, canonicalizer=[GoogleURLCanonicalizer.canonicalize, IAURLCanonicalizer.canonicalize])
+        == surt.surt(url)
+    )
+
+
+def test_surt_custom_canonicalizer():
+    def strip_query(hurl, **options):
+        hurl.query = None
+        return hurl
+
+    url = "http://www.archive.org/goo/?a=2&b&a=1"
+    assert surt.surt(url, canonicalizer=strip_query) == "org,archive,www)/goo/"
+    assert surt.surt(url, canonicalizer=[DefaultIAURLCanonicalizer.canonicalize, strip_query]) == "org,archive)/goo"
+    assert (
+        surt.surt(url, canonicalizer=[strip_query, IAURLCanonicalizer.canonicalize], with_scheme=True)
+        == "http://(org,archive)/goo"
+    )
+
+    with pytest.raises(TypeError):
+        surt.surt(url, canonicalizer=1)
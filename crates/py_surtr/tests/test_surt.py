@@ -264,3 +264,139 @@ def test_surt_return_type(burl):
     uurl = burl.decode("ascii")
     u = surt.surt(uurl)
     assert type(u) is type(uurl)
+
+
+@pytest.mark.parametrize(
+    "granularity,out",
+    [
+        ("full", "uk,co,bbc,news,a)/"),
+        ("registered_domain+1", "uk,co,bbc,news)/"),
+        ("registered_domain", "uk,co,bbc)/"),
+        ("suffix", "uk,co)/"),
+    ],
+)
+def test_surt_host_granularity(granularity, out):
+    assert (
+        surt.surt("http://a.news.bbc.co.uk/", host_granularity=granularity)
+        == out
+    )
+
+
+def test_surt_host_granularity_invalid():
+    with pytest.raises(ValueError):
+        surt.surt("http://a.news.bbc.co.uk/", host_granularity="subdomain")
+
+
+@pytest.mark.parametrize(
+    "opts,out",
+    [
+        (dict(path_depth=None), "org,archive)/details/foo/bar?a=b"),
+        (dict(path_depth=0), "org,archive)/"),
+        (dict(path_depth=1), "org,archive)/details"),
+        (dict(path_depth=3), "org,archive)/details/foo/bar"),
+        (
+            dict(path_depth=3, path_depth_keep_query=True),
+            "org,archive)/details/foo/bar?a=b",
+        ),
+    ],
+)
+def test_surt_path_depth(opts, out):
+    assert surt.surt("http://archive.org/details/foo/bar?a=b", **opts) == out
+
+
+def test_surt_many():
+    urls = [
+        "http://www.archive.org/",
+        b"http://archive.org/goo/?b&a",
+        "",
+        None,
+        "dns:archive.org",
+    ]
+    expected = ["org,archive)/", b"org,archive)/goo?a&b", "-", "-", "dns:archive.org"]
+
+    assert surt.surt_many(urls) == expected
+    assert surt.surt_many(iter(urls), threads=2) == expected
+    assert surt.surt_many(urls * 50, threads=0) == expected * 50
+    assert surt.surt_many([]) == []
+
+    assert surt.surt_many(
+        (u for u in ["http://www.example.com/"]), with_scheme=True
+    ) == ["http://(com,example)/"]
+    assert surt.surt_many(["http://archive.org/a/b"], path_depth=1) == [
+        "org,archive)/a"
+    ]
+
+
+def test_surt_many_errors():
+    urls = ["http://www.archive.org/", "http://%ff/", b"http://\xff/"]
+
+    with pytest.raises(surt.CanonicalizerError):
+        surt.surt_many(urls)
+
+    result = surt.surt_many(urls, errors="return", threads=2)
+    assert result[0] == "org,archive)/"
+    assert isinstance(result[1], surt.CanonicalizerError)
+    assert isinstance(result[2], UnicodeDecodeError)
+
+    assert surt.surt_many(urls, errors="sentinel") == ["org,archive)/", None, None]
+    assert surt.surt_many(urls, errors="sentinel", sentinel="-") == [
+        "org,archive)/",
+        "-",
+        "-",
+    ]
+
+    with pytest.raises(ValueError):
+        surt.surt_many(urls, errors="ignore")
+    with pytest.raises(TypeError):
+        surt.surt_many(["http://www.archive.org/", 1])
+    with pytest.raises(TypeError):
+        surt.surt_many(1)
+
+
+@pytest.mark.parametrize(
+    "url,out",
+    [
+        (
+            "http://www.images.amazon.co.uk/",
+            dict(
+                kind="domain",
+                subdomain="www.images",
+                registered_domain="amazon.co.uk",
+                suffix="co.uk",
+            ),
+        ),
+        (
+            "Example.COM",
+            dict(
+                kind="domain",
+                subdomain=None,
+                registered_domain="example.com",
+                suffix="com",
+            ),
+        ),
+        (
+            b"http://192.168.1.254/info/",
+            dict(
+                kind="ip_address",
+                subdomain=None,
+                registered_domain=None,
+                suffix=None,
+            ),
+        ),
+        (
+            "http://intranet.corp/",
+            dict(
+                kind="unknown_suffix",
+                subdomain=None,
+                registered_domain=None,
+                suffix=None,
+            ),
+        ),
+    ],
+)
+def test_host_parts(url, out):
+    assert surt.host_parts(url) == out
+
+
+def test_host_parts_no_host():
+    assert surt.host_parts("mailto:foo@example.com") is None
@@ -0,0 +1,606 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{
+    cdx::{json_text, parse_timestamp},
+    error::SurtrError,
+    options::SurtrOptions,
+    surt_with,
+    transform::{object_members, scan_value, skip_ws},
+};
+
+/// The access granted to a capture, as used by pywb and OpenWayback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Access {
+    /// The capture can be replayed.
+    #[default]
+    Allow,
+    /// The capture is listed, but cannot be replayed.
+    Block,
+    /// The capture is hidden from both lookups and replay.
+    Exclude,
+    /// The capture can be replayed, even if it is embargoed.
+    AllowIgnoreEmbargo,
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::Allow => "allow",
+            Self::Block => "block",
+            Self::Exclude => "exclude",
+            Self::AllowIgnoreEmbargo => "allow_ignore_embargo",
+        };
+
+        write!(f, "{}", s)
+    }
+}
+
+impl FromStr for Access {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "allow" => Ok(Self::Allow),
+            "block" => Ok(Self::Block),
+            "exclude" => Ok(Self::Exclude),
+            "allow_ignore_embargo" => Ok(Self::AllowIgnoreEmbargo),
+            _ => Err(format!(
+                "invalid access '{}'. Expected allow, block, exclude or allow_ignore_embargo",
+                s
+            )),
+        }
+    }
+}
+
+/// A single access rule, applying to every key which starts with its key.
+///
+/// Rules are parsed from pywb ACL lines, `<key> - {json}`, or from bare JSON objects.
+/// The JSON object must have an `access` member, and may have a `user` member, which limits
+/// the rule to that user. Bare JSON objects must have a `url` member, holding the key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AclRule {
+    /// The SURT prefix the rule applies to. URLs are keyed with `surt()` when the rule is added to `AclRules`.
+    pub key: String,
+    /// The access granted by the rule.
+    pub access: Access,
+    /// The user the rule applies to. None if it applies to every user.
+    pub user: Option<String>,
+}
+
+impl FromStr for AclRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (key, json) = match s.find(" - {") {
+            Some(i) if !s.starts_with('{') => (Some(&s[..i]), &s[i + 3..]),
+            _ => (None, s),
+        };
+
+        let bytes = json.as_bytes();
+        if !json.starts_with('{') || scan_value(bytes, 0)? != bytes.len() {
+            return Err("invalid ACL rule. Expected '<key> - {json}' or a JSON object".to_string());
+        }
+
+        let mut url = None;
+        let mut access = None;
+        let mut user = None;
+        for (member, range) in object_members(bytes, 0..bytes.len())? {
+            match member.as_str() {
+                "url" => url = Some(json_text(&json[range])?),
+                "access" => access = Some(json_text(&json[range])?.parse()?),
+                "user" => user = Some(json_text(&json[range])?),
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            key: key
+                .map(str::to_string)
+                .or(url)
+                .ok_or("invalid ACL rule. The rule has no key or url")?,
+            access: access.ok_or("invalid ACL rule. The rule has no access")?,
+            user,
+        })
+    }
+}
+
+/// An embargo on recent, or old, captures.
+///
+/// Captures before `before`, after `after`, newer than `newer_than_days` or older than
+/// `older_than_days` are embargoed, and given the embargo's access. Timestamps shorter than
+/// 14 digits are padded with zeros.
+///
+/// Embargoes are parsed from JSON objects, similar to pywb's embargo configuration:
+///
+/// ```rust
+/// use surtr::{Access, Embargo};
+///
+/// let embargo: Embargo = r#"{"newer": {"days": 365}, "access": "block"}"#.parse().unwrap();
+///
+/// assert_eq!(embargo.newer_than_days, Some(365));
+/// assert_eq!(embargo.access, Access::Block);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Embargo {
+    /// Captures before this timestamp are embargoed.
+    pub before: Option<String>,
+    /// Captures after this timestamp are embargoed.
+    pub after: Option<String>,
+    /// Captures newer than this many days are embargoed.
+    pub newer_than_days: Option<u64>,
+    /// Captures older than this many days are embargoed.
+    pub older_than_days: Option<u64>,
+    /// The access given to embargoed captures. Defaults to exclude.
+    pub access: Access,
+}
+
+impl Default for Embargo {
+    fn default() -> Self {
+        Self {
+            before: None,
+            after: None,
+            newer_than_days: None,
+            older_than_days: None,
+            access: Access::Exclude,
+        }
+    }
+}
+
+impl Embargo {
+    /// Check whether a capture is embargoed at the given time.
+    ///
+    /// # Arguments
+    ///
+    /// * `timestamp` - The timestamp of the capture.
+    /// * `now` - The time to check the embargo at.
+    pub fn applies(&self, timestamp: &str, now: SystemTime) -> bool {
+        let timestamp = pad_timestamp(timestamp);
+        let now = now.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        let days_ago = |days: u64| unix_timestamp(now.saturating_sub(days.saturating_mul(86400)));
+
+        self.before.as_ref().is_some_and(|t| timestamp < pad_timestamp(t))
+            || self.after.as_ref().is_some_and(|t| timestamp > pad_timestamp(t))
+            || self.newer_than_days.is_some_and(|days| timestamp > days_ago(days))
+            || self.older_than_days.is_some_and(|days| timestamp < days_ago(days))
+    }
+}
+
+impl FromStr for Embargo {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = s.trim().as_bytes();
+        if bytes.first() != Some(&b'{') || scan_value(bytes, 0)? != bytes.len() {
+            return Err("invalid embargo. Expected a JSON object".to_string());
+        }
+        let json = s.trim();
+
+        let mut embargo = Self::default();
+        for (member, range) in object_members(bytes, 0..bytes.len())? {
+            match member.as_str() {
+                "before" => embargo.before = Some(parse_timestamp(&json_text(&json[range])?)?),
+                "after" => embargo.after = Some(parse_timestamp(&json_text(&json[range])?)?),
+                "newer" => embargo.newer_than_days = Some(parse_days(json, range)?),
+                "older" => embargo.older_than_days = Some(parse_days(json, range)?),
+                "access" => embargo.access = json_text(&json[range])?.parse()?,
+                _ => return Err(format!("invalid embargo member '{}'. Expected before, after, newer, older or access", member)),
+            }
+        }
+
+        Ok(embargo)
+    }
+}
+
+/// A set of access rules, searched by SURT prefix.
+///
+/// The rule with the longest key which is a prefix of a URL's SURT applies to it. A rule for
+/// the requested user takes priority over a rule for every user with the same key. Keys which
+/// look like URLs, rather than SURTs, are keyed with `surt()` and the options given to `new()`.
+///
+/// An explicit block or exclude rule always applies. Otherwise, captures are checked against the
+/// embargo, unless the rule is `allow_ignore_embargo`.
+///
+/// # Examples
+///
+/// ```rust
+/// use surtr::{Access, AclRules};
+///
+/// let mut rules = AclRules::new(None);
+/// rules.load(
+///     "com,example)/ - {\"access\": \"block\"}\n\
+///      com,example)/public - {\"access\": \"allow\"}\n\
+///      {\"url\": \"http://example.com/public/secret\", \"access\": \"exclude\"}\n"
+///         .as_bytes(),
+/// ).unwrap();
+///
+/// let access = |url| rules.access(url, "20200101000000", None).unwrap();
+///
+/// assert_eq!(access("http://www.example.com/page"), Access::Block);
+/// assert_eq!(access("http://example.com/public/page"), Access::Allow);
+/// assert_eq!(access("http://example.com/public/secret/page"), Access::Exclude);
+/// assert_eq!(access("http://example.org/"), Access::Allow);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct AclRules {
+    rules: BTreeMap<String, Vec<AclRule>>,
+    default_access: Access,
+    embargo: Option<Embargo>,
+    options: SurtrOptions,
+}
+
+impl AclRules {
+    /// Create an empty set of rules.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The options used to key URLs, and any rules given as URLs.
+    pub fn new(options: Option<SurtrOptions>) -> Self {
+        Self {
+            options: options.unwrap_or_default().with_surt_defaults(),
+            ..Default::default()
+        }
+    }
+
+    /// Read rules from an ACL file.
+    ///
+    /// # Errors
+    ///
+    /// `io::Error` - If the file cannot be read, or a rule is invalid.
+    pub fn open<P: AsRef<Path>>(path: P, options: Option<SurtrOptions>) -> io::Result<Self> {
+        let mut rules = Self::new(options);
+        rules.load(BufReader::new(File::open(path)?))?;
+
+        Ok(rules)
+    }
+
+    /// Set the access given to URLs which no rule applies to. Defaults to allow.
+    pub fn with_default_access(mut self, access: Access) -> Self {
+        self.default_access = access;
+        self
+    }
+
+    /// Set the embargo checked for captures which are not blocked or excluded by a rule.
+    pub fn with_embargo(mut self, embargo: Embargo) -> Self {
+        self.embargo = Some(embargo);
+        self
+    }
+
+    /// Add a rule. A rule with the same key and user replaces the existing rule.
+    ///
+    /// # Errors
+    ///
+    /// `SurtrError` - If the key is a URL which cannot be keyed.
+    pub fn add_rule(&mut self, mut rule: AclRule) -> Result<(), SurtrError> {
+        if !is_surt(&rule.key) {
+            rule.key = surt_with(&rule.key, &self.options)?;
+        }
+
+        let rules = self.rules.entry(rule.key.clone()).or_default();
+        rules.retain(|r| r.user != rule.user);
+        rules.push(rule);
+
+        Ok(())
+    }
+
+    /// Read rules, one per line. Blank lines and lines starting with `#` are skipped.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the number of rules read.
+    ///
+    /// # Errors
+    ///
+    /// `io::ErrorKind::InvalidData` - If a rule is invalid. The error names the line.
+    ///
+    /// `io::Error` - If the input cannot be read.
+    pub fn load<R: BufRead>(&mut self, input: R) -> io::Result<usize> {
+        let mut count = 0;
+
+        for (i, line) in input.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid = |e: String| io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", i + 1, e));
+            let rule: AclRule = line.parse().map_err(invalid)?;
+            self.add_rule(rule).map_err(|e| invalid(e.to_string()))?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    /// Returns the number of rules.
+    pub fn len(&self) -> usize {
+        self.rules.values().map(Vec::len).sum()
+    }
+
+    /// Returns true if there are no rules.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Find the rule which applies to a SURT key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The SURT of the URL.
+    /// * `user` - The user requesting the URL, if any.
+    pub fn find_rule(&self, key: &str, user: Option<&str>) -> Option<&AclRule> {
+        (0..=key.len()).rev().filter(|end| key.is_char_boundary(*end)).find_map(|end| {
+            let rules = self.rules.get(&key[..end])?;
+
+            rules
+                .iter()
+                .find(|r| user.is_some() && r.user.as_deref() == user)
+                .or_else(|| rules.iter().find(|r| r.user.is_none()))
+        })
+    }
+
+    /// Decide the access to a capture of a URL.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL of the capture.
+    /// * `timestamp` - The timestamp of the capture, used to check the embargo.
+    /// * `user` - The user requesting the capture, if any.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the access to the capture. `Access::AllowIgnoreEmbargo` is returned as `Access::Allow`.
+    ///
+    /// # Errors
+    ///
+    /// `SurtrError` - If the URL cannot be keyed.
+    pub fn access(&self, url: &str, timestamp: &str, user: Option<&str>) -> Result<Access, SurtrError> {
+        let key = surt_with(url, &self.options)?;
+
+        Ok(self.access_for_key(&key, timestamp, user, SystemTime::now()))
+    }
+
+    /// Decide the access to a capture, by its SURT key, at the given time.
+    pub fn access_for_key(&self, key: &str, timestamp: &str, user: Option<&str>, now: SystemTime) -> Access {
+        let access = self.find_rule(key, user).map_or(self.default_access, |r| r.access);
+
+        match access {
+            Access::Block | Access::Exclude => access,
+            Access::AllowIgnoreEmbargo => Access::Allow,
+            Access::Allow => match &self.embargo {
+                Some(embargo) if embargo.applies(timestamp, now) => embargo.access,
+                _ => Access::Allow,
+            },
+        }
+    }
+}
+
+// SURTs either have a scheme followed by `(`, or no scheme and a host ending in `)`. A host ending
+// in `,`, such as `com,`, is a SURT prefix of every host below it, and cannot be the host of a URL.
+fn is_surt(key: &str) -> bool {
+    if key.contains("://") {
+        return key.contains("://(");
+    }
+
+    let host = key.split('/').next().unwrap_or(key);
+    host.ends_with(')') || host.ends_with(',')
+}
+
+// Parse a relative embargo, such as `{"days": 30}`.
+fn parse_days(json: &str, range: std::ops::Range<usize>) -> Result<u64, String> {
+    let bytes = json.as_bytes();
+    let start = skip_ws(bytes, range.start);
+    if bytes.get(start) != Some(&b'{') {
+        return Err("invalid embargo. Expected an object such as {\"days\": 30}".to_string());
+    }
+
+    let mut days = 0u64;
+    for (unit, value) in object_members(bytes, range)? {
+        let count: u64 = json_text(&json[value.clone()])?
+            .parse()
+            .map_err(|_| format!("invalid embargo {} '{}'. Expected a number", unit, &json[value]))?;
+
+        days += match unit.as_str() {
+            "days" => count,
+            "weeks" => count * 7,
+            "years" => count * 365,
+            _ => return Err(format!("invalid embargo unit '{}'. Expected days, weeks or years", unit)),
+        };
+    }
+
+    Ok(days)
+}
+
+fn pad_timestamp(timestamp: &str) -> String {
+    format!("{:0<14}", timestamp)
+}
+
+// Convert seconds since the epoch to a 14 digit timestamp.
+fn unix_timestamp(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let rem = secs % 86400;
+
+    // Howard Hinnant's civil_from_days.
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}{:02}{:02}{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_access() {
+        for s in ["allow", "block", "exclude", "allow_ignore_embargo"] {
+            assert_eq!(s.parse::<Access>().unwrap().to_string(), s);
+        }
+        assert!("deny".parse::<Access>().is_err());
+    }
+
+    #[test]
+    fn test_acl_rule() {
+        let rule: AclRule = r#"com,example)/private - {"access": "block", "url": "http://example.com/private"}"#
+            .parse()
+            .unwrap();
+        assert_eq!(rule.key, "com,example)/private");
+        assert_eq!(rule.access, Access::Block);
+        assert_eq!(rule.user, None);
+
+        let rule: AclRule = r#"{"url": "com,example)/", "access": "exclude", "user": "staff"}"#.parse().unwrap();
+        assert_eq!(rule.key, "com,example)/");
+        assert_eq!(rule.user.as_deref(), Some("staff"));
+
+        assert!(r#"{"access": "block"}"#.parse::<AclRule>().is_err());
+        assert!(r#"{"url": "com,example)/"}"#.parse::<AclRule>().is_err());
+        assert!(r#"{"url": "com,example)/", "access": "deny"}"#.parse::<AclRule>().is_err());
+        assert!("com,example)/ block".parse::<AclRule>().is_err());
+    }
+
+    #[test]
+    fn test_find_rule() {
+        let mut rules = AclRules::new(None);
+        rules
+            .load(
+                "# Example rules\n\
+                 com, - {\"access\": \"exclude\"}\n\
+                 com,example)/ - {\"access\": \"block\"}\n\
+                 com,example)/ - {\"access\": \"allow\", \"user\": \"staff\"}\n\
+                 \n\
+                 {\"url\": \"https://www.example.com/public/\", \"access\": \"allow\"}\n"
+                    .as_bytes(),
+            )
+            .unwrap();
+        assert_eq!(rules.len(), 4);
+
+        let access = |url: &str, user: Option<&str>| rules.access(url, "20200101000000", user).unwrap();
+        assert_eq!(access("http://example.com/", None), Access::Block);
+        assert_eq!(access("http://example.com/", Some("staff")), Access::Allow);
+        assert_eq!(access("http://example.com/", Some("guest")), Access::Block);
+        assert_eq!(access("http://example.com/public", None), Access::Allow);
+        // Keys are matched as plain prefixes, as pywb does.
+        assert_eq!(access("http://example.com/publication", None), Access::Allow);
+        assert_eq!(access("http://shop.example.com/", None), Access::Exclude);
+        assert_eq!(access("http://example.org/", None), Access::Allow);
+
+        assert_eq!(rules.find_rule("com,example)/public", None).unwrap().key, "com,example)/public");
+        assert!(rules.find_rule("org,example)/", None).is_none());
+
+        let rules = rules.with_default_access(Access::Block);
+        assert_eq!(rules.access("http://example.org/", "20200101000000", None).unwrap(), Access::Block);
+
+        let mut replaced = AclRules::new(None);
+        replaced.load("com,example)/ - {\"access\": \"block\"}\ncom,example)/ - {\"access\": \"allow\"}\n".as_bytes()).unwrap();
+        assert_eq!(replaced.len(), 1);
+        assert_eq!(replaced.find_rule("com,example)/", None).unwrap().access, Access::Allow);
+
+        // A `)` in the path does not make a URL a SURT.
+        let mut paren = AclRules::new(None);
+        paren
+            .add_rule(AclRule {
+                key: "example.com/page_(1)".to_string(),
+                access: Access::Block,
+                user: None,
+            })
+            .unwrap();
+        assert!(paren.find_rule("com,example)/page_(1)", None).is_some());
+
+        // Nor does a `,` in the path of a host without a `.`.
+        paren
+            .add_rule(AclRule {
+                key: "localhost/a,b".to_string(),
+                access: Access::Block,
+                user: None,
+            })
+            .unwrap();
+        assert!(paren.find_rule("localhost/a,b", None).is_none());
+        assert_eq!(paren.access("http://localhost/a,b", "20200101000000", None).unwrap(), Access::Block);
+    }
+
+    #[test]
+    fn test_load_errors() {
+        let err = AclRules::new(None)
+            .load("com,example)/ - {\"access\": \"block\"}\nnot a rule\n".as_bytes())
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().starts_with("line 2:"));
+
+        assert!(AclRules::open("/nonexistent/acl.aclj", None).is_err());
+    }
+
+    #[test]
+    fn test_embargo() {
+        let embargo: Embargo = r#"{"before": "2000", "after": "20300101000000", "access": "block"}"#.parse().unwrap();
+        let now = SystemTime::now();
+
+        assert!(embargo.applies("19991231235959", now));
+        assert!(!embargo.applies("2000", now));
+        assert!(!embargo.applies("20200101000000", now));
+        assert!(embargo.applies("20300101000001", now));
+
+        // 2020-01-31T00:00:00Z
+        let now = UNIX_EPOCH + Duration::from_secs(1580428800);
+        let embargo: Embargo = r#"{"newer": {"days": 30}}"#.parse().unwrap();
+        assert_eq!(embargo.access, Access::Exclude);
+        assert!(embargo.applies("20200102000000", now));
+        assert!(!embargo.applies("20191231000000", now));
+
+        let embargo: Embargo = r#"{"older": {"years": 1, "weeks": 1}}"#.parse().unwrap();
+        assert_eq!(embargo.older_than_days, Some(372));
+        assert!(embargo.applies("2019", now));
+
+        assert!(r#"{"newer": 30}"#.parse::<Embargo>().is_err());
+        assert!(r#"{"newer": {"months": 1}}"#.parse::<Embargo>().is_err());
+        assert!(r#"{"until": "2020"}"#.parse::<Embargo>().is_err());
+        assert!(r#"{"before": "2020-01-01"}"#.parse::<Embargo>().is_err());
+    }
+
+    #[test]
+    fn test_embargo_rules() {
+        let mut rules = AclRules::new(None).with_embargo(r#"{"newer": {"days": 30}}"#.parse().unwrap());
+        rules
+            .load(
+                "com,example)/ - {\"access\": \"allow_ignore_embargo\"}\n\
+                 org,example)/ - {\"access\": \"block\"}\n"
+                    .as_bytes(),
+            )
+            .unwrap();
+
+        let now = UNIX_EPOCH + Duration::from_secs(1580428800);
+        assert_eq!(rules.access_for_key("com,example)/", "20200130000000", None, now), Access::Allow);
+        assert_eq!(rules.access_for_key("org,example)/", "20200130000000", None, now), Access::Block);
+        assert_eq!(rules.access_for_key("net,example)/", "20200130000000", None, now), Access::Exclude);
+        assert_eq!(rules.access_for_key("net,example)/", "20190130000000", None, now), Access::Allow);
+    }
+
+    #[test]
+    fn test_unix_timestamp() {
+        assert_eq!(unix_timestamp(0), "19700101000000");
+        assert_eq!(unix_timestamp(951782400), "20000229000000");
+        assert_eq!(unix_timestamp(1580428800 + 3661), "20200131010101");
+        assert_eq!(unix_timestamp(4107542399), "21000228235959");
+    }
+}
@@ -1,11 +1,19 @@
 use crate::{
     canonicalizers::google, canonicalizers::ia, error::SurtrError, handy_url::HandyUrl,
-    options::SurtrOptions,
+    nested::NestedUrlMode, options::SurtrOptions,
 };
 
 pub fn canonicalize(url_input: HandyUrl, options: &SurtrOptions) -> Result<HandyUrl, SurtrError> {
     let mut url = url_input;
 
+    // Nested URLs are canonicalized before the query is unescaped, while their parameters can still be split.
+    if let Some(nested) = options.get_nested_urls()
+        && nested.mode() == NestedUrlMode::Canonicalize
+        && let Some(query) = &url.query
+    {
+        url.query = Some(nested.canonicalize_query(query, options));
+    }
+
     url = google::canonicalize(url, options)?;
     url = ia::canonicalize(url, options)?;
 
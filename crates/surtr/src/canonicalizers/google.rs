@@ -1,3 +1,5 @@
+#![allow(clippy::collapsible_if)]
+
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::str::FromStr;
 
@@ -210,6 +212,31 @@ fn normalize_path(input: String) -> String {
     output
 }
 
+// Truncate a path, as produced by normalize_path, to the first `depth` segments.
+//
+// # Arguments
+//
+// * `path` - The normalized path to truncate. Expected to start with `/`.
+// * `depth` - The number of path segments to keep.
+//
+// # Returns
+//
+// The truncated path, or None if the path has `depth` segments or fewer and is unchanged.
+pub fn truncate_path(path: &str, depth: usize) -> Option<String> {
+    let segments: Vec<&str> = path
+        .trim_start_matches('/')
+        .trim_end_matches('/')
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .collect();
+
+    if segments.len() <= depth {
+        return None;
+    }
+
+    Some(format!("/{}", segments[..depth].join("/")))
+}
+
 #[cfg(test)]
 mod tests {
     use crate::options::SurtrOptions;
@@ -561,6 +588,17 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_truncate_path() {
+        assert_eq!(truncate_path("/details/foo/bar", 1), Some("/details".to_string()));
+        assert_eq!(truncate_path("/details/foo/bar", 2), Some("/details/foo".to_string()));
+        assert_eq!(truncate_path("/details/foo/bar", 0), Some("/".to_string()));
+        assert_eq!(truncate_path("/details/foo/bar", 3), None);
+        assert_eq!(truncate_path("/details/foo/", 2), None);
+        assert_eq!(truncate_path("/", 0), None);
+        assert_eq!(truncate_path(&normalize_path("/a/./b/../c/d".to_string()), 2), Some("/a/c".to_string()));
+    }
+
     #[test]
     fn test_attempt_ip_formats() {
         // The tests are copied from GoogleURLCanonicalizerTest.java
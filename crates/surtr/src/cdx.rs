@@ -0,0 +1,941 @@
+use std::fmt;
+use std::io::{self, BufRead};
+use std::str::FromStr;
+
+use crate::{
+    error::SurtrError,
+    options::SurtrOptions,
+    surt, surt_with,
+    transform::{
+        decode_json_string, encode_json_string, json_string, object_members, scan_value, split_line_ending,
+        TransformError,
+    },
+};
+
+// The field letters of the default CDX 11 layout, `CDX N b a m s k r M S V g`.
+const DEFAULT_FIELDS: &str = "NbamskrMSVg";
+
+/// A CDX or CDXJ line with its key recomputed, as produced by `CdxRekeyer`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RekeyedLine {
+    /// The line number in the input.
+    pub line: usize,
+    /// The line with the new key, including its line ending.
+    pub output: Vec<u8>,
+    /// The key before re-keying. None for header and blank lines, which are passed through unchanged.
+    pub old_key: Option<String>,
+    /// The key after re-keying. None for header and blank lines.
+    pub new_key: Option<String>,
+}
+
+impl RekeyedLine {
+    /// Check whether re-keying changed the key of the line.
+    pub fn changed(&self) -> bool {
+        self.old_key != self.new_key
+    }
+}
+
+/// Recompute the keys of CDX and CDXJ lines from their original URLs.
+///
+/// Each line is re-keyed with `surt()`, using the URL from the `a` field of CDX lines, or
+/// the `url` member of the JSON block of CDXJ lines. Only the key is rewritten; every other
+/// byte of the line is kept.
+///
+/// CDX 9 and 11 field lines are supported. If the input starts with a ` CDX` header, its field
+/// letters are used to find the key and URL, otherwise the 11 field layout is assumed. Header
+/// lines are passed through unchanged.
+///
+/// The output is in input order. Changing the options changes the sort order of the keys,
+/// so the output must be re-sorted, with `LC_ALL=C sort`, before it is used as an index.
+///
+/// # Examples
+///
+/// ```rust
+/// use surtr::{CdxRekeyer, SurtrOptions};
+///
+/// let cdx = " CDX N b a m s k r M S V g\n\
+///            com,example)/ 20200101000000 http://www.example.com/ text/html 200 ABC - - 1043 333 a.warc.gz\n";
+///
+/// let options = SurtrOptions::profile("heritrix").unwrap();
+/// let lines: Vec<_> = CdxRekeyer::new(Some(options)).rekey(cdx.as_bytes()).map(|l| l.unwrap()).collect();
+///
+/// assert!(!lines[0].changed());
+/// assert!(lines[1].changed());
+/// assert_eq!(lines[1].new_key.as_deref(), Some("http://(com,example,www,)/"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct CdxRekeyer {
+    options: SurtrOptions,
+}
+
+impl Default for CdxRekeyer {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl CdxRekeyer {
+    /// Create a re-keyer which builds keys with the given options.
+    pub fn new(options: Option<SurtrOptions>) -> Self {
+        Self {
+            options: options.unwrap_or_default().with_surt_defaults(),
+        }
+    }
+
+    /// Read CDX or CDXJ lines from `input`, and yield each line with its new key.
+    pub fn rekey<R: BufRead>(self, input: R) -> RekeyLines<R> {
+        RekeyLines {
+            rekeyer: self,
+            input,
+            line: 0,
+            key_field: DEFAULT_FIELDS.find('N').unwrap_or(0),
+            url_field: DEFAULT_FIELDS.find('a').unwrap_or(2),
+            changed: 0,
+            failed: 0,
+            ending: Vec::new(),
+        }
+    }
+
+    /// Re-key a single CDX or CDXJ line, without its line ending.
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - The line to re-key.
+    /// * `key_field` - The index of the key field of CDX lines. Ignored for CDXJ lines.
+    /// * `url_field` - The index of the original URL field of CDX lines. Ignored for CDXJ lines.
+    /// * `line` - The line number, used in errors.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the re-keyed line, the old key and the new key.
+    ///
+    /// # Errors
+    ///
+    /// `TransformError::Field` - If the line has no URL field, or it is empty.
+    /// `TransformError::Surt` - If the URL cannot be converted.
+    pub fn rekey_line(
+        &self,
+        record: &[u8],
+        key_field: usize,
+        url_field: usize,
+        line: usize,
+    ) -> Result<(Vec<u8>, String, String), TransformError> {
+        let field_err = |message: String| TransformError::Field {
+            line,
+            record: record.to_vec(),
+            message,
+        };
+
+        let spans = split_spaces(record);
+
+        // CDXJ lines are `key timestamp {json}`, and the URL is taken from the JSON block.
+        let (key_span, url) = match spans.get(2) {
+            Some(json) if record.get(json.start) == Some(&b'{') => {
+                let url = json_string(&record[json.start..], "/url").map_err(field_err)?;
+                (spans[0].clone(), url)
+            }
+            _ => {
+                let key_span = spans
+                    .get(key_field)
+                    .ok_or_else(|| field_err(format!("line has no key field {}", key_field)))?;
+                let url_span = spans
+                    .get(url_field)
+                    .ok_or_else(|| field_err(format!("line has no url field {}", url_field)))?;
+
+                (key_span.clone(), String::from_utf8_lossy(&record[url_span.clone()]).into_owned())
+            }
+        };
+        if url.is_empty() {
+            return Err(field_err("line has an empty url field".to_string()));
+        }
+
+        let new_key = surt_with(&url, &self.options).map_err(|error| TransformError::Surt {
+            line,
+            record: record.to_vec(),
+            error,
+        })?;
+        let old_key = String::from_utf8_lossy(&record[key_span.clone()]).into_owned();
+
+        let mut out = Vec::with_capacity(record.len() + new_key.len());
+        out.extend_from_slice(&record[..key_span.start]);
+        out.extend_from_slice(new_key.as_bytes());
+        out.extend_from_slice(&record[key_span.end..]);
+
+        Ok((out, old_key, new_key))
+    }
+}
+
+/// An iterator over re-keyed lines, created by `CdxRekeyer::rekey`.
+pub struct RekeyLines<R> {
+    rekeyer: CdxRekeyer,
+    input: R,
+    line: usize,
+    key_field: usize,
+    url_field: usize,
+    changed: usize,
+    failed: usize,
+    ending: Vec<u8>,
+}
+
+impl<R> RekeyLines<R> {
+    /// The number of lines read so far.
+    pub fn lines(&self) -> usize {
+        self.line
+    }
+
+    /// The number of lines whose key has changed so far.
+    pub fn changed(&self) -> usize {
+        self.changed
+    }
+
+    /// The number of lines which could not be re-keyed so far.
+    pub fn failed(&self) -> usize {
+        self.failed
+    }
+
+    /// The line ending of the last line read, empty if it had none.
+    ///
+    /// Errors carry the line without its ending, so it can be written back as it was read.
+    pub fn line_ending(&self) -> &[u8] {
+        &self.ending
+    }
+}
+
+impl<R: BufRead> Iterator for RekeyLines<R> {
+    type Item = Result<RekeyedLine, TransformError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut buf = Vec::new();
+        match self.input.read_until(b'\n', &mut buf) {
+            Ok(0) => return None,
+            Ok(_) => self.line += 1,
+            Err(e) => return Some(Err(TransformError::Io(e))),
+        }
+
+        let (record, ending) = split_line_ending(&buf);
+        self.ending = ending.to_vec();
+        let passthrough = |output: Vec<u8>, line: usize| RekeyedLine {
+            line,
+            output,
+            old_key: None,
+            new_key: None,
+        };
+
+        // Header lines describe the field layout of the following lines.
+        if let Some(fields) = header_fields(record) {
+            if let (Some(key), Some(url)) = (fields.find('N'), fields.find('a')) {
+                self.key_field = key;
+                self.url_field = url;
+            }
+            return Some(Ok(passthrough(buf, self.line)));
+        }
+        if record.iter().all(u8::is_ascii_whitespace) {
+            return Some(Ok(passthrough(buf, self.line)));
+        }
+
+        let result = self
+            .rekeyer
+            .rekey_line(record, self.key_field, self.url_field, self.line)
+            .map(|(mut output, old_key, new_key)| {
+                output.extend_from_slice(ending);
+                RekeyedLine {
+                    line: self.line,
+                    output,
+                    old_key: Some(old_key),
+                    new_key: Some(new_key),
+                }
+            });
+
+        match &result {
+            Ok(l) if l.changed() => self.changed += 1,
+            Ok(_) => {}
+            Err(_) => self.failed += 1,
+        }
+
+        Some(result)
+    }
+}
+
+// Get the field letters of a ` CDX N b a ...` header line.
+fn header_fields(record: &[u8]) -> Option<String> {
+    let text = std::str::from_utf8(record).ok()?;
+    let fields = text.trim_start().strip_prefix("CDX ")?;
+
+    Some(fields.split_whitespace().collect())
+}
+
+// Get the byte ranges of the space separated fields of a CDX line. CDXJ JSON blocks are kept whole.
+fn split_spaces(record: &[u8]) -> Vec<std::ops::Range<usize>> {
+    let mut spans = vec![];
+    let mut start = 0;
+
+    for (i, b) in record.iter().enumerate() {
+        if spans.len() == 2 && record.get(start) == Some(&b'{') {
+            break;
+        }
+        if *b == b' ' {
+            spans.push(start..i);
+            start = i + 1;
+        }
+    }
+    spans.push(start..record.len());
+
+    spans
+}
+
+/// Re-key every CDX or CDXJ line in `input`, writing the output to `output`.
+///
+/// Lines which cannot be re-keyed are written unchanged, with their original line ending.
+///
+/// # Returns
+///
+/// A Result containing the number of lines whose key changed, and the number of lines which
+/// could not be re-keyed.
+///
+/// # Errors
+///
+/// Returns an error if reading the input or writing the output fails.
+pub fn rekey_cdx<R: BufRead, W: io::Write>(
+    input: R,
+    output: &mut W,
+    options: Option<SurtrOptions>,
+) -> io::Result<(usize, usize)> {
+    let mut lines = CdxRekeyer::new(options).rekey(input);
+
+    while let Some(result) = lines.next() {
+        match result {
+            Ok(l) => output.write_all(&l.output)?,
+            Err(TransformError::Io(e)) => return Err(e),
+            Err(TransformError::Field { record, .. } | TransformError::Surt { record, .. }) => {
+                output.write_all(&record)?;
+                output.write_all(lines.line_ending())?;
+            }
+        }
+    }
+
+    Ok((lines.changed(), lines.failed()))
+}
+
+/// A CDX line, in the 11 field layout `CDX N b a m s k r M S V g` used by the Wayback Machine.
+///
+/// Fields which are not known are written as `-`. The 9 field layout, `CDX N b a m s k r V g`,
+/// is also accepted by the parser, but records are always written with 11 fields.
+///
+/// # Examples
+///
+/// ```rust
+/// use surtr::CdxRecord;
+///
+/// let mut record = CdxRecord::new("http://www.example.com/", "20200101000000", None).unwrap();
+/// record.status = Some(200);
+/// record.filename = "a.warc.gz".to_string();
+///
+/// assert_eq!(record.to_string(), "com,example)/ 20200101000000 http://www.example.com/ - 200 - - - - - a.warc.gz");
+/// assert_eq!(record.to_string().parse::<CdxRecord>().unwrap(), record);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CdxRecord {
+    /// The SURT of the original URL. `N`
+    pub urlkey: String,
+    /// The capture timestamp, as up to 14 digits. `b`
+    pub timestamp: String,
+    /// The original URL. `a`
+    pub original: String,
+    /// The mime type. `m`
+    pub mime: String,
+    /// The HTTP status code. `s`
+    pub status: Option<u16>,
+    /// The payload digest. `k`
+    pub digest: String,
+    /// The redirect URL. `r`
+    pub redirect: String,
+    /// The meta tags, such as robots `noindex`. `M`
+    pub meta: String,
+    /// The compressed length of the record. `S`
+    pub length: Option<u64>,
+    /// The offset of the record in the file. `V`
+    pub offset: Option<u64>,
+    /// The name of the WARC or ARC file. `g`
+    pub filename: String,
+}
+
+impl CdxRecord {
+    /// The header line for the layout written by `CdxRecord`.
+    pub const HEADER: &'static str = " CDX N b a m s k r M S V g";
+
+    /// Create a record for a URL, keyed with `surt()`. All other fields are set to `-`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the URL cannot be converted into a SURT.
+    pub fn new(original: &str, timestamp: &str, options: Option<SurtrOptions>) -> Result<Self, SurtrError> {
+        Ok(Self {
+            urlkey: surt(original, options)?,
+            timestamp: timestamp.to_string(),
+            original: original.to_string(),
+            mime: "-".to_string(),
+            status: None,
+            digest: "-".to_string(),
+            redirect: "-".to_string(),
+            meta: "-".to_string(),
+            length: None,
+            offset: None,
+            filename: "-".to_string(),
+        })
+    }
+
+    /// Recompute the key from the original URL.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the URL cannot be converted into a SURT.
+    pub fn rekey(&mut self, options: Option<SurtrOptions>) -> Result<(), SurtrError> {
+        self.urlkey = surt(&self.original, options)?;
+        Ok(())
+    }
+}
+
+impl FromStr for CdxRecord {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.trim_end_matches(['\n', '\r']).split(' ').collect();
+
+        let (meta, length, offset, filename) = match fields.len() {
+            11 => (fields[7], parse_number(fields[8], "length")?, parse_number(fields[9], "offset")?, fields[10]),
+            9 => ("-", None, parse_number(fields[7], "offset")?, fields[8]),
+            n => return Err(format!("invalid CDX line. Expected 9 or 11 fields, found {}", n)),
+        };
+
+        Ok(Self {
+            urlkey: fields[0].to_string(),
+            timestamp: parse_timestamp(fields[1])?,
+            original: fields[2].to_string(),
+            mime: fields[3].to_string(),
+            status: parse_number(fields[4], "status")?,
+            digest: fields[5].to_string(),
+            redirect: fields[6].to_string(),
+            meta: meta.to_string(),
+            length,
+            offset,
+            filename: filename.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for CdxRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {} {} {} {} {} {} {} {}",
+            self.urlkey,
+            self.timestamp,
+            self.original,
+            dash(&self.mime),
+            dash_number(self.status),
+            dash(&self.digest),
+            dash(&self.redirect),
+            dash(&self.meta),
+            dash_number(self.length),
+            dash_number(self.offset),
+            dash(&self.filename),
+        )
+    }
+}
+
+/// A CDXJ line, `urlkey timestamp {json}`, as used by pywb and OutbackCDX.
+///
+/// The common members of the JSON block are parsed into fields. Numbers are accepted as JSON
+/// numbers or strings, and written as strings, as pywb does. Any other members are kept in
+/// `extra`, as raw JSON, so they are written back unchanged.
+///
+/// # Examples
+///
+/// ```rust
+/// use surtr::CdxjRecord;
+///
+/// let line = r#"com,example)/ 20200101000000 {"url": "http://www.example.com/", "status": 200, "source": "a.cdx"}"#;
+/// let record: CdxjRecord = line.parse().unwrap();
+///
+/// assert_eq!(record.original, "http://www.example.com/");
+/// assert_eq!(record.status, Some(200));
+/// assert_eq!(record.extra, vec![("source".to_string(), "\"a.cdx\"".to_string())]);
+/// assert_eq!(
+///     record.to_string(),
+///     r#"com,example)/ 20200101000000 {"url": "http://www.example.com/", "status": "200", "source": "a.cdx"}"#
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CdxjRecord {
+    /// The SURT of the original URL.
+    pub urlkey: String,
+    /// The capture timestamp, as up to 14 digits.
+    pub timestamp: String,
+    /// The original URL. `url`
+    pub original: String,
+    /// The mime type. `mime`
+    pub mime: Option<String>,
+    /// The HTTP status code. `status`
+    pub status: Option<u16>,
+    /// The payload digest. `digest`
+    pub digest: Option<String>,
+    /// The compressed length of the record. `length`
+    pub length: Option<u64>,
+    /// The offset of the record in the file. `offset`
+    pub offset: Option<u64>,
+    /// The name of the WARC or ARC file. `filename`
+    pub filename: Option<String>,
+    /// The other members of the JSON block, as raw JSON values, in the order they were read.
+    pub extra: Vec<(String, String)>,
+}
+
+impl CdxjRecord {
+    /// Create a record for a URL, keyed with `surt()`. All other fields are empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the URL cannot be converted into a SURT.
+    pub fn new(original: &str, timestamp: &str, options: Option<SurtrOptions>) -> Result<Self, SurtrError> {
+        Ok(Self {
+            urlkey: surt(original, options)?,
+            timestamp: timestamp.to_string(),
+            original: original.to_string(),
+            ..Default::default()
+        })
+    }
+
+    /// Recompute the key from the original URL.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the URL cannot be converted into a SURT.
+    pub fn rekey(&mut self, options: Option<SurtrOptions>) -> Result<(), SurtrError> {
+        self.urlkey = surt(&self.original, options)?;
+        Ok(())
+    }
+}
+
+impl FromStr for CdxjRecord {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.trim_end_matches(['\n', '\r']).splitn(3, ' ');
+
+        let (urlkey, timestamp, json) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(k), Some(t), Some(j)) if j.starts_with('{') => (k, t, j.trim_end()),
+            _ => return Err("invalid CDXJ line. Expected 'urlkey timestamp {json}'".to_string()),
+        };
+
+        let bytes = json.as_bytes();
+        if scan_value(bytes, 0)? != bytes.len() {
+            return Err("invalid CDXJ line. Unexpected data after the JSON block".to_string());
+        }
+
+        let mut record = Self {
+            urlkey: urlkey.to_string(),
+            timestamp: parse_timestamp(timestamp)?,
+            ..Default::default()
+        };
+        let mut has_url = false;
+
+        for (key, range) in object_members(bytes, 0..bytes.len())? {
+            let raw = &json[range];
+            match key.as_str() {
+                "url" => {
+                    record.original = json_text(raw)?;
+                    has_url = true;
+                }
+                "mime" => record.mime = Some(json_text(raw)?),
+                "status" => record.status = parse_number(&json_text(raw)?, "status")?,
+                "digest" => record.digest = Some(json_text(raw)?),
+                "length" => record.length = parse_number(&json_text(raw)?, "length")?,
+                "offset" => record.offset = parse_number(&json_text(raw)?, "offset")?,
+                "filename" => record.filename = Some(json_text(raw)?),
+                _ => record.extra.push((key, raw.to_string())),
+            }
+        }
+
+        if !has_url {
+            return Err("invalid CDXJ line. The JSON block has no url".to_string());
+        }
+
+        Ok(record)
+    }
+}
+
+impl fmt::Display for CdxjRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut members = vec![("url", encode_json_string(&self.original))];
+
+        let strings = [("mime", &self.mime), ("digest", &self.digest), ("filename", &self.filename)];
+        let numbers = [("status", self.status.map(u64::from)), ("length", self.length), ("offset", self.offset)];
+
+        members.extend(strings.iter().filter_map(|(k, v)| v.as_ref().map(|v| (*k, encode_json_string(v)))));
+        members.extend(numbers.iter().filter_map(|(k, v)| v.map(|v| (*k, format!("\"{}\"", v)))));
+
+        // Written in the order pywb uses, with any extra members last.
+        let order = ["url", "mime", "status", "digest", "length", "offset", "filename"];
+        members.sort_by_key(|(k, _)| order.iter().position(|o| o == k));
+
+        let json: Vec<String> = members
+            .into_iter()
+            .map(|(k, v)| format!("\"{}\": {}", k, v))
+            .chain(self.extra.iter().map(|(k, v)| format!("{}: {}", encode_json_string(k), v)))
+            .collect();
+
+        write!(f, "{} {} {{{}}}", self.urlkey, self.timestamp, json.join(", "))
+    }
+}
+
+impl From<&CdxRecord> for CdxjRecord {
+    fn from(record: &CdxRecord) -> Self {
+        let optional = |s: &str| match s {
+            "-" | "" => None,
+            s => Some(s.to_string()),
+        };
+
+        let mut extra = vec![];
+        if let Some(redirect) = optional(&record.redirect) {
+            extra.push(("redirect".to_string(), encode_json_string(&redirect)));
+        }
+        if let Some(meta) = optional(&record.meta) {
+            extra.push(("meta".to_string(), encode_json_string(&meta)));
+        }
+
+        Self {
+            urlkey: record.urlkey.clone(),
+            timestamp: record.timestamp.clone(),
+            original: record.original.clone(),
+            mime: optional(&record.mime),
+            status: record.status,
+            digest: optional(&record.digest),
+            length: record.length,
+            offset: record.offset,
+            filename: optional(&record.filename),
+            extra,
+        }
+    }
+}
+
+/// Write CDX or CDXJ records to an output, one per line.
+///
+/// A ` CDX` header is written before the first CDX record. CDXJ has no header.
+/// The writer does not sort the records; they must be written in key order, or sorted afterwards.
+///
+/// # Examples
+///
+/// ```rust
+/// use surtr::{CdxRecord, CdxWriter};
+///
+/// let mut writer = CdxWriter::new(Vec::new());
+/// writer.write_cdx(&CdxRecord::new("http://www.example.com/", "20200101000000", None).unwrap()).unwrap();
+///
+/// assert_eq!(
+///     String::from_utf8(writer.into_inner()).unwrap(),
+///     " CDX N b a m s k r M S V g\ncom,example)/ 20200101000000 http://www.example.com/ - - - - - - - -\n"
+/// );
+/// ```
+pub struct CdxWriter<W> {
+    output: W,
+    header_written: bool,
+}
+
+impl<W: io::Write> CdxWriter<W> {
+    /// Create a writer, which writes a header before the first CDX record.
+    pub fn new(output: W) -> Self {
+        Self {
+            output,
+            header_written: false,
+        }
+    }
+
+    /// Create a writer which never writes a header, for appending to existing files.
+    pub fn without_header(output: W) -> Self {
+        Self {
+            output,
+            header_written: true,
+        }
+    }
+
+    /// Write a CDX record.
+    pub fn write_cdx(&mut self, record: &CdxRecord) -> io::Result<()> {
+        if !self.header_written {
+            writeln!(self.output, "{}", CdxRecord::HEADER)?;
+            self.header_written = true;
+        }
+
+        writeln!(self.output, "{}", record)
+    }
+
+    /// Write a CDXJ record.
+    pub fn write_cdxj(&mut self, record: &CdxjRecord) -> io::Result<()> {
+        writeln!(self.output, "{}", record)
+    }
+
+    /// Flush the output.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.output.flush()
+    }
+
+    /// Consume the writer and return the output.
+    pub fn into_inner(self) -> W {
+        self.output
+    }
+}
+
+// Parse a number field, where `-` means the field is not known.
+fn parse_number<T: FromStr>(value: &str, name: &str) -> Result<Option<T>, String> {
+    match value {
+        "-" | "" => Ok(None),
+        v => v
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("invalid CDX {} '{}'. Expected a number or -", name, v)),
+    }
+}
+
+pub(crate) fn parse_timestamp(value: &str) -> Result<String, String> {
+    match !value.is_empty() && value.len() <= 14 && value.bytes().all(|b| b.is_ascii_digit()) {
+        true => Ok(value.to_string()),
+        false => Err(format!("invalid CDX timestamp '{}'. Expected up to 14 digits", value)),
+    }
+}
+
+// Get a JSON string, or the text of any other JSON value, such as a number.
+pub(crate) fn json_text(raw: &str) -> Result<String, String> {
+    match raw.starts_with('"') {
+        true => decode_json_string(raw.as_bytes()),
+        false => Ok(raw.to_string()),
+    }
+}
+
+fn dash(value: &str) -> &str {
+    match value {
+        "" => "-",
+        v => v,
+    }
+}
+
+fn dash_number<T: fmt::Display>(value: Option<T>) -> String {
+    match value {
+        Some(v) => v.to_string(),
+        None => "-".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rekey(input: &str, options: Option<SurtrOptions>) -> Vec<Result<RekeyedLine, String>> {
+        CdxRekeyer::new(options)
+            .rekey(input.as_bytes())
+            .map(|r| r.map_err(|e| e.to_string()))
+            .collect()
+    }
+
+    fn output(lines: &[Result<RekeyedLine, String>]) -> String {
+        lines
+            .iter()
+            .map(|l| String::from_utf8(l.as_ref().unwrap().output.clone()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_rekey_cdx11() {
+        let input = " CDX N b a m s k r M S V g\n\
+            com,example,www)/ 20200101000000 http://www.example.com/ text/html 200 ABC - - 1043 333 a.warc.gz\n\
+            org,archive)/goo?a&b 20200101000000 http://archive.org/goo/?b&a text/html 200 ABC - - 1043 333 a.warc.gz\n";
+
+        let lines = rekey(input, None);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].as_ref().unwrap().old_key, None);
+        assert!(lines[1].as_ref().unwrap().changed());
+        assert!(!lines[2].as_ref().unwrap().changed());
+        assert_eq!(
+            output(&lines),
+            " CDX N b a m s k r M S V g\n\
+            com,example)/ 20200101000000 http://www.example.com/ text/html 200 ABC - - 1043 333 a.warc.gz\n\
+            org,archive)/goo?a&b 20200101000000 http://archive.org/goo/?b&a text/html 200 ABC - - 1043 333 a.warc.gz\n"
+        );
+    }
+
+    #[test]
+    fn test_rekey_cdx9_header() {
+        let input = " CDX a b N m s k r V g\r\n\
+            http://www.example.com/ 20200101000000 old text/html 200 ABC - 333 a.warc.gz\r\n";
+
+        let lines = rekey(input, None);
+        assert_eq!(
+            output(&lines),
+            " CDX a b N m s k r V g\r\n\
+            http://www.example.com/ 20200101000000 com,example)/ text/html 200 ABC - 333 a.warc.gz\r\n"
+        );
+    }
+
+    #[test]
+    fn test_rekey_cdxj() {
+        let input = "com,example)/ 20200101000000 {\"url\": \"http://www.example.com/\", \"note\": \"a b c\"}\n";
+
+        let lines = rekey(input, Some(SurtrOptions::profile("heritrix").unwrap()));
+        let line = lines[0].as_ref().unwrap();
+        assert_eq!(line.old_key.as_deref(), Some("com,example)/"));
+        assert_eq!(line.new_key.as_deref(), Some("http://(com,example,www,)/"));
+        assert_eq!(
+            output(&lines),
+            "http://(com,example,www,)/ 20200101000000 {\"url\": \"http://www.example.com/\", \"note\": \"a b c\"}\n"
+        );
+    }
+
+    const CDX11: &str = "com,example)/ 20200101000000 http://www.example.com/ text/html 200 \
+        SHA1:ABC http://example.com/a noindex 1043 333 a.warc.gz";
+
+    #[test]
+    fn test_cdx_record_parse() {
+        let record: CdxRecord = CDX11.parse().unwrap();
+        assert_eq!(
+            record,
+            CdxRecord {
+                urlkey: "com,example)/".to_string(),
+                timestamp: "20200101000000".to_string(),
+                original: "http://www.example.com/".to_string(),
+                mime: "text/html".to_string(),
+                status: Some(200),
+                digest: "SHA1:ABC".to_string(),
+                redirect: "http://example.com/a".to_string(),
+                meta: "noindex".to_string(),
+                length: Some(1043),
+                offset: Some(333),
+                filename: "a.warc.gz".to_string(),
+            }
+        );
+        assert_eq!(record.to_string(), CDX11);
+
+        let cdx9: CdxRecord = "com,example)/ 2020 http://example.com/ warc/revisit - ABC - 333 a.warc.gz\n"
+            .parse()
+            .unwrap();
+        assert_eq!(cdx9.status, None);
+        assert_eq!(cdx9.length, None);
+        assert_eq!(cdx9.offset, Some(333));
+        assert_eq!(
+            cdx9.to_string(),
+            "com,example)/ 2020 http://example.com/ warc/revisit - ABC - - - 333 a.warc.gz"
+        );
+        assert_eq!(cdx9.to_string().parse::<CdxRecord>().unwrap(), cdx9);
+    }
+
+    #[test]
+    fn test_cdx_record_invalid() {
+        assert!("com,example)/ 2020 http://example.com/".parse::<CdxRecord>().is_err());
+        assert!(CDX11.replace(" 200 ", " ok ").parse::<CdxRecord>().is_err());
+        assert!(CDX11.replace(" 1043 ", " -1 ").parse::<CdxRecord>().is_err());
+        assert!(CDX11.replace("20200101000000", "2020-01-01").parse::<CdxRecord>().is_err());
+        assert!(CDX11.replace("20200101000000", "202001010000001").parse::<CdxRecord>().is_err());
+    }
+
+    #[test]
+    fn test_cdx_record_new() {
+        let mut record = CdxRecord::new("http://www.example.com/", "20200101000000", None).unwrap();
+        assert_eq!(record.urlkey, "com,example)/");
+
+        record.rekey(Some(SurtrOptions::profile("heritrix").unwrap())).unwrap();
+        assert_eq!(record.urlkey, "http://(com,example,www,)/");
+
+        assert!(CdxRecord::new("http://example.com/%ff", "2020", None).is_err());
+    }
+
+    #[test]
+    fn test_cdxj_record() {
+        let line = "com,example)/ 20200101000000 {\"url\": \"http://www.example.com/\", \"mime\": \"text/html\", \
+            \"status\": \"200\", \"digest\": \"ABC\", \"length\": \"1043\", \"offset\": \"333\", \
+            \"filename\": \"a.warc.gz\", \"source\": {\"coll\": [1, 2]}}";
+
+        let record: CdxjRecord = line.parse().unwrap();
+        assert_eq!(record.original, "http://www.example.com/");
+        assert_eq!(record.mime.as_deref(), Some("text/html"));
+        assert_eq!(record.status, Some(200));
+        assert_eq!(record.length, Some(1043));
+        assert_eq!(record.offset, Some(333));
+        assert_eq!(record.filename.as_deref(), Some("a.warc.gz"));
+        assert_eq!(record.extra, vec![("source".to_string(), "{\"coll\": [1, 2]}".to_string())]);
+        assert_eq!(record.to_string(), line);
+
+        let numbers: CdxjRecord = "a)/ 2020 {\"offset\": 10, \"url\": \"http://a/\\u00e9\"}".parse().unwrap();
+        assert_eq!(numbers.offset, Some(10));
+        assert_eq!(numbers.original, "http://a/\u{e9}");
+        assert_eq!(numbers.to_string(), "a)/ 2020 {\"url\": \"http://a/\u{e9}\", \"offset\": \"10\"}");
+    }
+
+    #[test]
+    fn test_cdxj_record_invalid() {
+        assert!("a)/ 2020".parse::<CdxjRecord>().is_err());
+        assert!("a)/ 2020 {\"mime\": \"text/html\"}".parse::<CdxjRecord>().is_err());
+        assert!("a)/ 2020 {\"url\": \"http://a/\"".parse::<CdxjRecord>().is_err());
+        assert!("a)/ 2020 {\"url\": \"http://a/\"} x".parse::<CdxjRecord>().is_err());
+        assert!("a)/ 2020 {\"url\": \"http://a/\", \"status\": \"ok\"}".parse::<CdxjRecord>().is_err());
+    }
+
+    #[test]
+    fn test_cdx_to_cdxj() {
+        let record: CdxRecord = CDX11.parse().unwrap();
+        let cdxj = CdxjRecord::from(&record);
+
+        assert_eq!(
+            cdxj.to_string(),
+            "com,example)/ 20200101000000 {\"url\": \"http://www.example.com/\", \"mime\": \"text/html\", \
+            \"status\": \"200\", \"digest\": \"SHA1:ABC\", \"length\": \"1043\", \"offset\": \"333\", \
+            \"filename\": \"a.warc.gz\", \"redirect\": \"http://example.com/a\", \"meta\": \"noindex\"}"
+        );
+        assert_eq!(cdxj.to_string().parse::<CdxjRecord>().unwrap(), cdxj);
+    }
+
+    #[test]
+    fn test_cdx_writer() {
+        let record: CdxRecord = CDX11.parse().unwrap();
+
+        let mut writer = CdxWriter::new(Vec::new());
+        writer.write_cdx(&record).unwrap();
+        writer.write_cdx(&record).unwrap();
+        assert_eq!(
+            String::from_utf8(writer.into_inner()).unwrap(),
+            format!("{}\n{}\n{}\n", CdxRecord::HEADER, CDX11, CDX11)
+        );
+
+        let mut writer = CdxWriter::without_header(Vec::new());
+        writer.write_cdxj(&CdxjRecord::from(&record)).unwrap();
+        let out = String::from_utf8(writer.into_inner()).unwrap();
+        assert_eq!(out.trim_end().parse::<CdxjRecord>().unwrap(), CdxjRecord::from(&record));
+    }
+
+    #[test]
+    fn test_rekey_errors() {
+        let input = "com,example)/ 20200101000000\n\
+            com,example)/ 20200101000000 {\"digest\": \"ABC\"}\n\
+            \n\
+            com,example)/ 20200101000000 http://example.com/%ff text/html\n";
+
+        let lines = rekey(input, None);
+        assert!(lines[0].as_ref().unwrap_err().contains("no url field"));
+        assert!(lines[1].as_ref().unwrap_err().contains("FieldError"));
+        assert_eq!(lines[2].as_ref().unwrap().old_key, None);
+        assert!(lines[3].as_ref().unwrap_err().contains("CanonicalizerError"));
+
+        // An empty third field is not a CDXJ block, and has no URL.
+        let empty = rekey("com,example)/ 20200101000000 \ncom,example)/ 20200101000000  text/html\n", None);
+        assert!(empty[0].as_ref().unwrap_err().contains("empty url field"));
+        assert!(empty[1].as_ref().unwrap_err().contains("empty url field"));
+
+        let mut out = Vec::new();
+        assert_eq!(rekey_cdx(input.as_bytes(), &mut out, None).unwrap(), (0, 3));
+        assert_eq!(String::from_utf8(out).unwrap(), input);
+
+        // Failed lines keep their own line ending.
+        let input = "com,example)/ 20200101000000\r\n\
+            com,example)/ 20200101000000 http://www.example.com/ text/html\r\n\
+            com,example)/ 20200101000000";
+
+        let mut out = Vec::new();
+        assert_eq!(rekey_cdx(input.as_bytes(), &mut out, None).unwrap(), (0, 2));
+        assert_eq!(String::from_utf8(out).unwrap(), input);
+    }
+}
@@ -0,0 +1,197 @@
+use std::cell::RefCell;
+use std::net::IpAddr;
+
+use tld_extract::{SuffixList, TLDExtract};
+
+const TLD_SOURCE: tld_extract::Source = tld_extract::Source::Snapshot;
+
+thread_local! {
+    // Building the suffix trie is expensive, so it is built once per thread. `TLDExtract::extract`
+    // requires a mutable reference; one extractor per thread lets threads split hosts in parallel.
+    // Labels are not converted back to unicode, so the parts match the punycode host they came from.
+    static TLD_EXTRACT: RefCell<TLDExtract> = RefCell::new(
+        TLDExtract::new(SuffixList::new(TLD_SOURCE, false, None), false)
+            .expect("TLD Extract failed to compile successfully.")
+    );
+}
+
+/// The parts of a host, as identified by the Public Suffix List.
+///
+/// IP addresses and hosts which do not end in a known public suffix are returned as their own
+/// variants, rather than as a `Domain` with missing parts, so callers have to decide how to
+/// group them.
+///
+/// # Examples
+///
+/// ```rust
+/// use surtr::HostParts;
+///
+/// let parts = HostParts::parse("www.images.amazon.co.uk");
+///
+/// assert_eq!(parts.suffix(), Some("co.uk"));
+/// assert_eq!(parts.registered_domain(), Some("amazon.co.uk"));
+/// assert_eq!(parts.subdomain(), Some("www.images"));
+///
+/// assert_eq!(HostParts::parse("192.168.1.254"), HostParts::IpAddress("192.168.1.254".to_string()));
+/// assert_eq!(HostParts::parse("localhost"), HostParts::UnknownSuffix("localhost".to_string()));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HostParts {
+    /// The host is an IPv4 or IPv6 address. IP addresses have no suffix, registered domain or subdomain.
+    IpAddress(String),
+    /// The host does not end in a suffix from the Public Suffix List. e.g. `localhost` or `intranet.corp`.
+    UnknownSuffix(String),
+    /// The host ends in a suffix from the Public Suffix List.
+    Domain {
+        /// The labels below the registered domain. `www.images` for `www.images.amazon.co.uk`.
+        subdomain: Option<String>,
+        /// The registered domain. `amazon.co.uk` for `www.images.amazon.co.uk`.
+        /// This is None when the host is itself a public suffix, such as `co.uk`.
+        registered_domain: Option<String>,
+        /// The public suffix. `co.uk` for `www.images.amazon.co.uk`.
+        suffix: String,
+    },
+}
+
+impl HostParts {
+    /// Split a host into its parts using the Public Suffix List.
+    ///
+    /// # Arguments
+    ///
+    /// `host` - The host to split. This is expected to be lowercase and ASCII, as produced by canonicalization.
+    ///
+    /// # Returns
+    ///
+    /// A HostParts enum describing the host.
+    pub fn parse(host: &str) -> Self {
+        let trimmed = host.trim_start_matches('[').trim_end_matches(']');
+        if trimmed.parse::<IpAddr>().is_ok() {
+            return Self::IpAddress(trimmed.to_string());
+        }
+
+        // tld_extract rejects labels containing characters such as `_` or `%`, which are common in
+        // crawled hosts. Only the number of suffix labels is taken from the extractor, so those
+        // characters are masked before the lookup and the original labels are returned.
+        let last = host.chars().count().saturating_sub(1);
+        let masked: String = host
+            .chars()
+            .enumerate()
+            .map(|(i, c)| match c {
+                '-' if i != 0 && i != last => c,
+                '.' => c,
+                _ if c.is_ascii_alphanumeric() => c.to_ascii_lowercase(),
+                _ => 'x',
+            })
+            .collect();
+
+        let extracted = TLD_EXTRACT.with(|extract| extract.borrow_mut().extract(&masked));
+
+        let suffix_len = match extracted {
+            Ok(t) => t.suffix.map(|s| s.split('.').count()).unwrap_or(0),
+            Err(_) => 0,
+        };
+        if suffix_len == 0 {
+            return Self::UnknownSuffix(host.to_string());
+        }
+
+        let labels: Vec<&str> = host.split('.').collect();
+        let suffix = labels[labels.len() - suffix_len..].join(".");
+        if labels.len() == suffix_len {
+            return Self::Domain {
+                subdomain: None,
+                registered_domain: None,
+                suffix,
+            };
+        }
+
+        let domain_idx = labels.len() - suffix_len - 1;
+        let subdomain = match domain_idx {
+            0 => None,
+            _ => Some(labels[..domain_idx].join(".")),
+        };
+
+        Self::Domain {
+            subdomain,
+            registered_domain: Some(labels[domain_idx..].join(".")),
+            suffix,
+        }
+    }
+
+    /// The public suffix of the host. None for IP addresses and unknown suffixes.
+    pub fn suffix(&self) -> Option<&str> {
+        match self {
+            Self::Domain { suffix, .. } => Some(suffix),
+            _ => None,
+        }
+    }
+
+    /// The registered domain of the host. None for IP addresses, unknown suffixes and bare suffixes.
+    pub fn registered_domain(&self) -> Option<&str> {
+        match self {
+            Self::Domain { registered_domain, .. } => registered_domain.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// The subdomain labels of the host. None if there are no labels below the registered domain.
+    pub fn subdomain(&self) -> Option<&str> {
+        match self {
+            Self::Domain { subdomain, .. } => subdomain.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_parts_domain() {
+        assert_eq!(
+            HostParts::parse("www.amazon.co.uk"),
+            HostParts::Domain {
+                subdomain: Some("www".to_string()),
+                registered_domain: Some("amazon.co.uk".to_string()),
+                suffix: "co.uk".to_string(),
+            }
+        );
+        assert_eq!(
+            HostParts::parse("fool.com"),
+            HostParts::Domain {
+                subdomain: None,
+                registered_domain: Some("fool.com".to_string()),
+                suffix: "com".to_string(),
+            }
+        );
+        assert_eq!(
+            HostParts::parse("co.uk"),
+            HostParts::Domain {
+                subdomain: None,
+                registered_domain: None,
+                suffix: "co.uk".to_string(),
+            }
+        );
+        assert_eq!(HostParts::parse("xn--bcher-kva.ch").registered_domain(), Some("xn--bcher-kva.ch"));
+        assert_eq!(HostParts::parse("under_score.example.com").subdomain(), Some("under_score"));
+        assert_eq!(HostParts::parse("%20leadingspace.com").registered_domain(), Some("%20leadingspace.com"));
+        assert_eq!(HostParts::parse("xn--e1afmkfd.xn--p1ai").suffix(), Some("xn--p1ai"));
+    }
+
+    #[test]
+    fn test_host_parts_ip_address() {
+        assert_eq!(HostParts::parse("127.0.0.1"), HostParts::IpAddress("127.0.0.1".to_string()));
+        assert_eq!(HostParts::parse("[::1]"), HostParts::IpAddress("::1".to_string()));
+        assert_eq!(HostParts::parse("127.0.0.1").registered_domain(), None);
+    }
+
+    #[test]
+    fn test_host_parts_unknown_suffix() {
+        assert_eq!(HostParts::parse("localhost"), HostParts::UnknownSuffix("localhost".to_string()));
+        assert_eq!(
+            HostParts::parse("www.example.notarealtld"),
+            HostParts::UnknownSuffix("www.example.notarealtld".to_string())
+        );
+        assert_eq!(HostParts::parse("localhost").suffix(), None);
+    }
+}
@@ -0,0 +1,613 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use regex::Regex;
+use yaml_rust2::{Yaml, YamlLoader};
+
+use crate::{
+    cdx::json_text,
+    error::SurtrError,
+    handy_url::HandyUrl,
+    key_range::{key_range, KeyRange, MatchType},
+    options::SurtrOptions,
+    surt,
+    transform::{object_members, scan_value},
+};
+
+/// How a filter compares the value of a field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterKind {
+    /// The value matches a regex.
+    Regex,
+    /// The value contains a string. Written with a `~` prefix.
+    Contains,
+    /// The value equals a string. Written with a `=` prefix.
+    Exact,
+}
+
+/// A filter on a field of a CDX or CDXJ line, in pywb's filter syntax.
+///
+/// Filters are written as `[!][~|=]field:value`. `!` inverts the filter, `~` checks the field
+/// contains the value, `=` checks it equals the value, and otherwise the value is a regex.
+///
+/// # Examples
+///
+/// ```rust
+/// use surtr::{FilterKind, FuzzyFilter};
+///
+/// let filter: FuzzyFilter = "!~urlkey:callback=".parse().unwrap();
+///
+/// assert_eq!(filter.kind, FilterKind::Contains);
+/// assert!(filter.invert);
+/// assert!(filter.matches("com,example)/?a=1"));
+/// assert!(!filter.matches("com,example)/?callback=abc"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct FuzzyFilter {
+    /// The field to compare, such as `urlkey`, `mime` or `status`.
+    pub field: String,
+    /// The value compared against the field.
+    pub value: String,
+    /// How the field is compared.
+    pub kind: FilterKind,
+    /// Whether lines which do not match are kept instead.
+    pub invert: bool,
+    regex: Option<Regex>,
+}
+
+impl FuzzyFilter {
+    /// Check whether the value of the field passes the filter.
+    pub fn matches(&self, value: &str) -> bool {
+        let matched = match self.kind {
+            FilterKind::Contains => value.contains(&self.value),
+            FilterKind::Exact => value == self.value,
+            FilterKind::Regex => self.regex.as_ref().is_some_and(|r| r.is_match(value)),
+        };
+
+        matched != self.invert
+    }
+}
+
+impl PartialEq for FuzzyFilter {
+    fn eq(&self, other: &Self) -> bool {
+        self.field == other.field && self.value == other.value && self.kind == other.kind && self.invert == other.invert
+    }
+}
+
+impl fmt::Display for FuzzyFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            FilterKind::Regex => "",
+            FilterKind::Contains => "~",
+            FilterKind::Exact => "=",
+        };
+
+        write!(f, "{}{}{}:{}", if self.invert { "!" } else { "" }, kind, self.field, self.value)
+    }
+}
+
+impl FromStr for FuzzyFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (invert, rest) = match s.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, s),
+        };
+        let (kind, rest) = match rest.chars().next() {
+            Some('~') => (FilterKind::Contains, &rest[1..]),
+            Some('=') => (FilterKind::Exact, &rest[1..]),
+            _ => (FilterKind::Regex, rest),
+        };
+
+        let (field, value) = match rest.split_once(':') {
+            Some((field, value)) if !field.is_empty() => (field, value),
+            _ => return Err(format!("invalid filter '{}'. Expected [!][~|=]field:value", s)),
+        };
+        let regex = match kind {
+            FilterKind::Regex => Some(Regex::new(value).map_err(|e| format!("invalid filter regex '{}': {}", value, e))?),
+            _ => None,
+        };
+
+        Ok(Self {
+            field: field.to_string(),
+            value: value.to_string(),
+            kind,
+            invert,
+            regex,
+        })
+    }
+}
+
+/// A fuzzy matching rule, as defined in pywb's `rules.yaml`.
+///
+/// A rule applies to keys starting with one of its prefixes, which match its regex. The groups
+/// captured by the regex are substituted into the filter templates, as `{0}`, `{1}` and so on,
+/// and the URL is cut after the first `replace_after` string to build a broader lookup.
+#[derive(Debug, Clone)]
+pub struct FuzzyRule {
+    /// The SURT prefixes the rule applies to. An empty prefix applies to every key.
+    pub url_prefixes: Vec<String>,
+    regex: Regex,
+    replace_after: String,
+    filters: Vec<String>,
+    match_type: MatchType,
+    find_all: bool,
+}
+
+impl FuzzyRule {
+    /// The filter used when a rule has none, requiring the key to contain the first group.
+    pub const DEFAULT_FILTER: &'static str = "~urlkey:{0}";
+
+    /// Create a rule with the defaults used by pywb. The URL is cut after the first `?`,
+    /// looked up as a prefix, and filtered to keys containing the first group of the regex.
+    ///
+    /// # Errors
+    ///
+    /// An error message if the regex is invalid.
+    pub fn new(url_prefixes: Vec<String>, regex: &str) -> Result<Self, String> {
+        Ok(Self {
+            url_prefixes,
+            regex: Regex::new(regex).map_err(|e| format!("invalid fuzzy regex '{}': {}", regex, e))?,
+            replace_after: "?".to_string(),
+            filters: vec![Self::DEFAULT_FILTER.to_string()],
+            match_type: MatchType::Prefix,
+            find_all: false,
+        })
+    }
+
+    /// Set the string the URL is cut after. An empty string keeps the whole URL.
+    pub fn with_replace_after(mut self, replace_after: &str) -> Self {
+        self.replace_after = replace_after.to_string();
+        self
+    }
+
+    /// Set the filter templates.
+    ///
+    /// # Errors
+    ///
+    /// An error message if a template is not a valid filter.
+    pub fn with_filters(mut self, filters: Vec<String>) -> Result<Self, String> {
+        // Templates are checked with empty groups, as the placeholders are not valid regex.
+        let placeholder = Regex::new(r"\{\d+\}").unwrap();
+        for filter in &filters {
+            placeholder.replace_all(filter, "").parse::<FuzzyFilter>()?;
+        }
+
+        self.filters = filters;
+        Ok(self)
+    }
+
+    /// Set the match type of the broader lookup.
+    pub fn with_match_type(mut self, match_type: MatchType) -> Self {
+        self.match_type = match_type;
+        self
+    }
+
+    /// Use the groups of every match of the regex, rather than only the first.
+    pub fn with_find_all(mut self, find_all: bool) -> Self {
+        self.find_all = find_all;
+        self
+    }
+
+    // Returns the groups captured from the key, or None if the regex does not match.
+    fn groups(&self, key: &str) -> Option<Vec<String>> {
+        let group_strings = |c: regex::Captures| -> Vec<String> {
+            c.iter().skip(1).map(|g| g.map_or(String::new(), |g| g.as_str().to_string())).collect()
+        };
+
+        if self.find_all {
+            let groups: Vec<String> = self.regex.captures_iter(key).flat_map(group_strings).collect();
+            let matched = self.regex.is_match(key);
+            return matched.then_some(groups);
+        }
+
+        self.regex.captures(key).map(group_strings)
+    }
+
+    // Parses a rule from pywb's rules.yaml. Rules without a fuzzy_lookup are skipped.
+    fn from_yaml(rule: &Yaml) -> Result<Option<Self>, String> {
+        let fuzzy = &rule["fuzzy_lookup"];
+        if fuzzy.is_badvalue() || fuzzy.is_null() {
+            return Ok(None);
+        }
+
+        let url_prefixes = match &rule["url_prefix"] {
+            Yaml::Array(prefixes) => prefixes.iter().map(yaml_string).collect::<Result<_, _>>()?,
+            Yaml::BadValue | Yaml::Null => vec![String::new()],
+            prefix => vec![yaml_string(prefix)?],
+        };
+
+        if let Yaml::String(regex) = fuzzy {
+            return Self::new(url_prefixes, regex).map(Some);
+        }
+
+        let mut fuzzy_rule = Self::new(url_prefixes, &optional_string(&fuzzy["match"])?.unwrap_or_default())?;
+        if let Some(replace) = optional_string(&fuzzy["replace"])? {
+            fuzzy_rule = fuzzy_rule.with_replace_after(&replace);
+        }
+        match &fuzzy["filter"] {
+            Yaml::Array(filters) => {
+                fuzzy_rule = fuzzy_rule.with_filters(filters.iter().map(yaml_string).collect::<Result<_, _>>()?)?;
+            }
+            Yaml::BadValue | Yaml::Null => {}
+            filter => fuzzy_rule = fuzzy_rule.with_filters(vec![yaml_string(filter)?])?,
+        }
+        if let Some(match_type) = optional_string(&fuzzy["type"])? {
+            fuzzy_rule = fuzzy_rule.with_match_type(match_type.parse()?);
+        }
+        if let Some(find_all) = fuzzy["find_all"].as_bool() {
+            fuzzy_rule = fuzzy_rule.with_find_all(find_all);
+        }
+
+        Ok(Some(fuzzy_rule))
+    }
+}
+
+/// The broader lookup built by a fuzzy rule, for when an exact lookup finds nothing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuzzyMatch {
+    /// The URL to look up.
+    pub url: String,
+    /// How the URL should be matched.
+    pub match_type: MatchType,
+    /// The key range to scan.
+    pub range: KeyRange,
+    /// The filters a line must pass to be a fuzzy match.
+    pub filters: Vec<FuzzyFilter>,
+}
+
+impl FuzzyMatch {
+    /// Check whether a CDX or CDXJ line from the range passes every filter.
+    ///
+    /// The `urlkey` and `timestamp` fields are read from every line. Other fields are read
+    /// from the JSON block of CDXJ lines, or by position from CDX lines, where `original`
+    /// (or `url`), `mime`, `status` and `digest` are supported. Lines without a field fail
+    /// any filter on it.
+    pub fn matches(&self, line: &str) -> bool {
+        self.filters.iter().all(|filter| match line_field(line, &filter.field) {
+            Some(value) => filter.matches(&value),
+            None => false,
+        })
+    }
+}
+
+/// A list of fuzzy matching rules, checked in order.
+///
+/// When an exact lookup of a URL finds nothing, the first rule which applies to the URL's key
+/// builds a broader lookup, and filters for the lines which are near matches. Keys are built with
+/// `surt()`, and the options given to `new()`, so they match the index being searched.
+///
+/// # Examples
+///
+/// ```rust
+/// use surtr::{FuzzyRules, HandyUrl, MatchType, SurtrOptions};
+///
+/// let rules = FuzzyRules::from_yaml(
+///     "rules:\n\
+///      \x20 - url_prefix: 'com,example)/api'\n\
+///      \x20   fuzzy_lookup: '[?&](id=[^&]+)'\n",
+///     None,
+/// ).unwrap();
+///
+/// let url = HandyUrl::parse("http://example.com/api?_=1600000000&id=42", &SurtrOptions::default()).unwrap();
+/// let fuzzy = rules.fuzzy_match(&url).unwrap().unwrap();
+///
+/// assert_eq!(fuzzy.url, "http://example.com/api?");
+/// assert_eq!(fuzzy.match_type, MatchType::Prefix);
+/// assert_eq!(fuzzy.filters[0].to_string(), "~urlkey:id=42");
+/// assert!(fuzzy.matches("com,example)/api?_=1500000000&id=42 20200101000000 http://example.com/api?id=42&_=1500000000"));
+/// assert!(!fuzzy.matches("com,example)/api?id=7 20200101000000 http://example.com/api?id=7"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FuzzyRules {
+    rules: Vec<FuzzyRule>,
+    options: Option<SurtrOptions>,
+}
+
+impl FuzzyRules {
+    /// Create an empty list of rules.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The options used to key URLs.
+    pub fn new(options: Option<SurtrOptions>) -> Self {
+        Self { rules: vec![], options }
+    }
+
+    /// Parse rules from a pywb `rules.yaml` document.
+    ///
+    /// The document is either a list of rules, or a mapping with a `rules` list. Each rule has a
+    /// `url_prefix`, which is a SURT prefix or a list of them, and a `fuzzy_lookup`, which is a regex
+    /// or a mapping with `match`, `replace`, `filter`, `type` and `find_all` members. Rules without
+    /// a `fuzzy_lookup`, such as rewriting rules, are skipped.
+    ///
+    /// # Errors
+    ///
+    /// An error message if the document is not valid YAML, or a rule is invalid.
+    pub fn from_yaml(yaml: &str, options: Option<SurtrOptions>) -> Result<Self, String> {
+        let docs = YamlLoader::load_from_str(yaml).map_err(|e| format!("invalid rules yaml: {}", e))?;
+        let mut rules = Self::new(options);
+
+        for doc in &docs {
+            let list = match doc {
+                Yaml::Array(list) => list,
+                _ => match &doc["rules"] {
+                    Yaml::Array(list) => list,
+                    _ => return Err("invalid rules yaml. Expected a list of rules, or a mapping with a rules list".to_string()),
+                },
+            };
+
+            for (i, rule) in list.iter().enumerate() {
+                if let Some(rule) = FuzzyRule::from_yaml(rule).map_err(|e| format!("rule {}: {}", i + 1, e))? {
+                    rules.add_rule(rule);
+                }
+            }
+        }
+
+        Ok(rules)
+    }
+
+    /// Read rules from a pywb `rules.yaml` file.
+    ///
+    /// # Errors
+    ///
+    /// `io::Error` - If the file cannot be read, or the rules are invalid.
+    pub fn open<P: AsRef<Path>>(path: P, options: Option<SurtrOptions>) -> io::Result<Self> {
+        let yaml = fs::read_to_string(path)?;
+
+        Self::from_yaml(&yaml, options).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Add a rule after the existing rules.
+    pub fn add_rule(&mut self, rule: FuzzyRule) {
+        self.rules.push(rule);
+    }
+
+    /// Returns the number of rules.
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Returns true if there are no rules.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Build the fuzzy lookup for a URL.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The parsed URL, before canonicalization.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the fuzzy lookup, or None if no rule applies to the URL.
+    ///
+    /// # Errors
+    ///
+    /// `SurtrError` - If the URL cannot be keyed.
+    pub fn fuzzy_match(&self, url: &HandyUrl) -> Result<Option<FuzzyMatch>, SurtrError> {
+        let mut url_options = SurtrOptions::default();
+        url_options.set("surt", false);
+        url_options.set("with_scheme", true);
+
+        let url = url.get_url(&url_options)?;
+        let key = surt(&url, self.options.clone())?;
+
+        let matched = self.rules.iter().find_map(|rule| {
+            if !rule.url_prefixes.iter().any(|p| key.starts_with(p.as_str())) {
+                return None;
+            }
+            rule.groups(&key).map(|groups| (rule, groups))
+        });
+        let (rule, groups) = match matched {
+            Some(m) => m,
+            None => return Ok(None),
+        };
+
+        let mut filters = vec![];
+        for template in &rule.filters {
+            let filter = fill_template(template, &groups).parse().map_err(SurtrError::UrlParseError)?;
+            filters.push(filter);
+        }
+
+        // The URL is cut within its path and query, so the scheme and host are always kept.
+        let path_start = match url.find("://") {
+            Some(i) => url[i + 3..].find('/').map_or(url.len(), |j| i + 3 + j),
+            None => 0,
+        };
+        let fuzzy_url = match url[path_start..].find(rule.replace_after.as_str()) {
+            Some(i) if !rule.replace_after.is_empty() => url[..path_start + i + rule.replace_after.len()].to_string(),
+            _ => url,
+        };
+
+        Ok(Some(FuzzyMatch {
+            range: key_range(&fuzzy_url, rule.match_type, self.options.clone())?,
+            url: fuzzy_url,
+            match_type: rule.match_type,
+            filters,
+        }))
+    }
+}
+
+// Substitutes `{0}`, `{1}` and so on with the groups. Groups are escaped for regex filters.
+fn fill_template(template: &str, groups: &[String]) -> String {
+    let is_regex = !template.trim_start_matches('!').starts_with(['~', '=']);
+    let mut filled = template.to_string();
+
+    for (i, group) in groups.iter().enumerate() {
+        let group = match is_regex {
+            true => regex::escape(group),
+            false => group.clone(),
+        };
+        filled = filled.replace(&format!("{{{}}}", i), &group);
+    }
+
+    filled
+}
+
+// Reads a named field from a CDX or CDXJ line.
+fn line_field(line: &str, field: &str) -> Option<String> {
+    let mut parts = line.trim_end_matches(['\r', '\n']).splitn(3, ' ');
+    let urlkey = parts.next()?;
+    let timestamp = parts.next();
+    let rest = parts.next().unwrap_or("");
+
+    match field {
+        "urlkey" => return Some(urlkey.to_string()),
+        "timestamp" => return timestamp.map(str::to_string),
+        _ => {}
+    }
+
+    // CDXJ
+    if rest.starts_with('{') {
+        let bytes = rest.as_bytes();
+        let end = scan_value(bytes, 0).ok()?;
+        let name = if field == "original" { "url" } else { field };
+
+        return object_members(bytes, 0..end)
+            .ok()?
+            .into_iter()
+            .find(|(key, _)| key == name)
+            .and_then(|(_, range)| json_text(&rest[range]).ok());
+    }
+
+    // CDX, with the fields of `CdxRecord::HEADER`.
+    let position = match field {
+        "original" | "url" => 0,
+        "mime" => 1,
+        "status" => 2,
+        "digest" => 3,
+        _ => return None,
+    };
+    rest.split(' ').nth(position).map(str::to_string)
+}
+
+fn yaml_string(value: &Yaml) -> Result<String, String> {
+    match value {
+        Yaml::String(s) => Ok(s.clone()),
+        Yaml::Integer(i) => Ok(i.to_string()),
+        _ => Err(format!("expected a string, found {:?}", value)),
+    }
+}
+
+fn optional_string(value: &Yaml) -> Result<Option<String>, String> {
+    match value {
+        Yaml::BadValue | Yaml::Null => Ok(None),
+        v => yaml_string(v).map(Some),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULES: &str = r#"
+rules:
+    # Rewriting rules are skipped.
+    - url_prefix: 'com,example)/static'
+      rewrite:
+          js_regexs: []
+
+    - url_prefix: ['com,example)/api', 'com,example,api)/']
+      fuzzy_lookup: '[?&](id=[^&]+)'
+
+    - url_prefix: 'com,example)/search'
+      fuzzy_lookup:
+          match: '[?&](page=[^&]+).*[?&](q=[^&]+)'
+          filter: ['~urlkey:{0}', '~urlkey:{1}', '!mime:^warc/revisit$']
+
+    - url_prefix: 'com,example)/tags'
+      fuzzy_lookup:
+          match: '(tag=[^&]+)'
+          find_all: true
+          filter: ['~urlkey:{0}', '~urlkey:{1}']
+
+    - url_prefix: 'org,example)/'
+      fuzzy_lookup:
+          match: '/([\w.]+)\.js'
+          replace: '/'
+          type: 'host'
+          filter: 'urlkey:{0}'
+"#;
+
+    fn parse(url: &str) -> HandyUrl {
+        HandyUrl::parse(url, &SurtrOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn test_filter() {
+        for s in ["urlkey:^com,", "~urlkey:id=1", "=status:200", "!~mime:html", "!digest:^A"] {
+            assert_eq!(s.parse::<FuzzyFilter>().unwrap().to_string(), s);
+        }
+
+        let filter: FuzzyFilter = "=status:200".parse().unwrap();
+        assert!(filter.matches("200"));
+        assert!(!filter.matches("2000"));
+
+        let filter: FuzzyFilter = "urlkey:^com,ex".parse().unwrap();
+        assert!(filter.matches("com,example)/"));
+        assert!(!filter.matches("org,com,ex)/"));
+
+        assert!("urlkey".parse::<FuzzyFilter>().is_err());
+        assert!(":value".parse::<FuzzyFilter>().is_err());
+        assert!("urlkey:(".parse::<FuzzyFilter>().is_err());
+    }
+
+    #[test]
+    fn test_from_yaml() {
+        let rules = FuzzyRules::from_yaml(RULES, None).unwrap();
+        assert_eq!(rules.len(), 4);
+
+        let list = FuzzyRules::from_yaml("- url_prefix: com,\n  fuzzy_lookup: '(a)'\n", None).unwrap();
+        assert_eq!(list.len(), 1);
+        assert_eq!(list.rules[0].url_prefixes, ["com,"]);
+
+        assert!(FuzzyRules::from_yaml("rules: 1", None).is_err());
+        assert!(FuzzyRules::from_yaml("rules: [", None).is_err());
+        assert!(FuzzyRules::from_yaml("- fuzzy_lookup: '('", None).unwrap_err().starts_with("rule 1:"));
+        assert!(FuzzyRules::from_yaml("- fuzzy_lookup: {match: a, type: fuzzy}", None).is_err());
+        assert!(FuzzyRules::from_yaml("- fuzzy_lookup: {match: a, filter: [bad]}", None).is_err());
+    }
+
+    #[test]
+    fn test_fuzzy_match() {
+        let rules = FuzzyRules::from_yaml(RULES, None).unwrap();
+
+        let fuzzy = rules.fuzzy_match(&parse("http://www.example.com/api?id=42&callback=jq123")).unwrap().unwrap();
+        assert_eq!(fuzzy.url, "http://www.example.com/api?");
+        assert_eq!(fuzzy.range, key_range("http://www.example.com/api?", MatchType::Prefix, None).unwrap());
+        assert_eq!(fuzzy.filters, ["~urlkey:id=42".parse::<FuzzyFilter>().unwrap()]);
+
+        let fuzzy = rules.fuzzy_match(&parse("http://api.example.com/v1?id=7")).unwrap().unwrap();
+        assert_eq!(fuzzy.filters[0].value, "id=7");
+
+        // Keys have sorted queries, so groups are captured in key order.
+        let fuzzy = rules.fuzzy_match(&parse("http://example.com/search?page=2&q=rust&_=99")).unwrap().unwrap();
+        let filters: Vec<String> = fuzzy.filters.iter().map(|f| f.to_string()).collect();
+        assert_eq!(filters, ["~urlkey:page=2", "~urlkey:q=rust", "!mime:^warc/revisit$"]);
+        assert!(fuzzy.matches("com,example)/search?page=2&q=rust 20200101000000 http://example.com/search?q=rust&page=2 text/html 200 AAA - - 10 0 a.warc"));
+        assert!(!fuzzy.matches("com,example)/search?page=2&q=rust 20200101000000 http://example.com/search?q=rust&page=2 warc/revisit 200 AAA - - 10 0 a.warc"));
+        assert!(fuzzy.matches(r#"com,example)/search?page=2&q=rust 20200101000000 {"url": "http://example.com/search?q=rust&page=2", "mime": "text/html"}"#));
+        assert!(!fuzzy.matches("com,example)/search?page=2&q=rust 20200101000000"));
+
+        let fuzzy = rules.fuzzy_match(&parse("http://example.com/tags?tag=a&tag=b")).unwrap().unwrap();
+        let filters: Vec<String> = fuzzy.filters.iter().map(|f| f.to_string()).collect();
+        assert_eq!(filters, ["~urlkey:tag=a", "~urlkey:tag=b"]);
+
+        // Regex filters have their groups escaped.
+        let fuzzy = rules.fuzzy_match(&parse("http://example.org/assets/app.min.js?v=1.2")).unwrap().unwrap();
+        assert_eq!(fuzzy.url, "http://example.org/");
+        assert_eq!(fuzzy.match_type, MatchType::Host);
+        assert_eq!(fuzzy.filters[0].to_string(), "urlkey:app\\.min");
+        assert!(fuzzy.matches("org,example)/assets/app.min.js?v=1.1 20200101000000"));
+        assert!(!fuzzy.matches("org,example)/assets/appxmin.js 20200101000000"));
+
+        assert!(rules.fuzzy_match(&parse("http://example.com/api")).unwrap().is_none());
+        assert!(rules.fuzzy_match(&parse("http://example.net/api?id=1")).unwrap().is_none());
+    }
+}
@@ -2,17 +2,17 @@ use std::fmt::Display;
 
 use lazy_static::lazy_static;
 use regex::Regex;
-use tld_extract::{SuffixList, TLDExtract};
 
 use crate::{
+    canonicalizers::{self, google::truncate_path},
+    domain::HostParts,
     error::SurtrError,
-    options::SurtrOptions,
+    nested::NestedUrlMode,
+    options::{HostGranularity, SurtrOptions},
     regex_transformer::host_to_surt,
     url_split::SplitResult,
 };
 
-const TLD_SOURCE: tld_extract::Source = tld_extract::Source::Snapshot;
-
 lazy_static! {
     // These Regexes expect here, because they should always compile. The system doesn't work without them compiling
     // so we should panic if they cannot compile.
@@ -54,7 +54,7 @@ lazy_static! {
 /// // canonicalization is being performed.
 /// assert_eq!(handy_url.get_url(&options), Ok("com,example,www)/".to_string()));
 /// ```
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct HandyUrl {
     /// http, ftp, dns -> The protocol being used in the URL. Typically before the `://`.
     pub scheme: Option<String>,
@@ -82,6 +82,10 @@ pub struct HandyUrl {
     pub hash: Option<String>,
     /// Internal option to determine if a blank operator was present. If it was, we should add it to the SURT for accuracy.
     pub last_delimiter: Option<String>,
+    /// The capture timestamp of an unwrapped archive replay URL. See `SurtrOptions::set_replay_unwrap`.
+    pub replay_timestamp: Option<String>,
+    /// The replay modifier of an unwrapped archive replay URL, such as `id_`.
+    pub replay_modifier: Option<String>,
 }
 
 impl HandyUrl {
@@ -134,6 +138,24 @@ impl HandyUrl {
         let mut url: String = raw_url.trim().to_string();
         url = RE_SPACES.replace_all(&url, "").to_string();
 
+        // Unwrap archive replay URLs, so the original URL is parsed.
+        let (mut replay_timestamp, mut replay_modifier) = (None, None);
+        if let Some(matcher) = options.get_replay_unwrap()
+            && let Some(parts) = matcher.unwrap_url(&url)
+        {
+            url = parts.url;
+            replay_timestamp = Some(parts.timestamp);
+            replay_modifier = parts.modifier;
+        }
+
+        // Key the URL nested in a query parameter, rather than the URL holding it.
+        if let Some(nested) = options.get_nested_urls()
+            && nested.mode() == NestedUrlMode::Extract
+            && let Some(nested_url) = nested.nested_url(&url)
+        {
+            url = nested_url;
+        }
+
         url = HandyUrl::add_default_scheme_if_needed(&url);
 
         url = RE_MULTIPLE_PROTOCOLS
@@ -162,39 +184,112 @@ impl HandyUrl {
             query: split_url.query,
             hash: split_url.fragment,
             last_delimiter,
+            replay_timestamp,
+            replay_modifier,
         })
     }
 
-    // Use the public TLD Sources to identify the registered domain of a given Host.
-    // This is used to discard subdomains from the SURT.
-    fn get_public_suffix(&self) -> Option<String> {
-        let suffix = SuffixList::new(TLD_SOURCE, false, None);
-        let mut extract = TLDExtract::new(suffix, true).expect("TLD Extract failed to compile successfully.");
+    /// Split the host of the URL into its subdomain, registered domain and public suffix.
+    /// 
+    /// # Returns
+    /// 
+    /// None if the URL has no host. Otherwise a `HostParts` enum, which also identifies IP addresses
+    /// and hosts with unknown suffixes.
+    /// 
+    /// # Examples
+    /// 
+    /// ```rust
+    /// use surtr::{HandyUrl, HostParts, SurtrOptions};
+    /// 
+    /// let handy_url = HandyUrl::parse("http://news.bbc.co.uk/", &SurtrOptions::default()).unwrap();
+    /// 
+    /// assert_eq!(
+    ///     handy_url.get_host_parts(),
+    ///     Some(HostParts::Domain {
+    ///         subdomain: Some("news".to_string()),
+    ///         registered_domain: Some("bbc.co.uk".to_string()),
+    ///         suffix: "co.uk".to_string(),
+    ///     })
+    /// );
+    /// ```
+    pub fn get_host_parts(&self) -> Option<HostParts> {
+        self.host.as_deref().map(HostParts::parse)
+    }
 
-        if let Some(host) = &self.host {
-            return match extract.extract(host) {
-                Ok(t) => t.registered_domain,
-                Err(_) => None,
-            };
-        }
+    /// Use the public TLD Sources to identify the public suffix of the host. e.g. `co.uk`
+    /// 
+    /// Returns None if there is no host, the host is an IP address or the suffix is unknown.
+    pub fn get_public_suffix(&self) -> Option<String> {
+        self.get_host_parts()?.suffix().map(String::from)
+    }
 
-        None
+    /// Use the public TLD Sources to identify the registered domain of the host. e.g. `amazon.co.uk`
+    /// 
+    /// This is used to discard subdomains from the SURT.
+    /// Returns None if there is no host, the host is an IP address or the suffix is unknown.
+    pub fn get_registered_domain(&self) -> Option<String> {
+        self.get_host_parts()?.registered_domain().map(String::from)
     }
 
-    // Use the public TLD Sources to identify the subdomain of a given Host.
-    // This exists as a compatibility with IA's version. This method is unused in Surtr.
-    fn _get_public_prefix(&self) -> Option<String> {
-        let suffix = SuffixList::new(TLD_SOURCE, false, None);
-        let mut extract = TLDExtract::new(suffix, true).expect("TLD Extract failed to compile successfully.");
+    /// Use the public TLD Sources to identify the subdomain of the host. e.g. `www.images`
+    /// 
+    /// Returns None if there is no host, the host has no subdomain, the host is an IP address or the suffix is unknown.
+    pub fn get_subdomain(&self) -> Option<String> {
+        self.get_host_parts()?.subdomain().map(String::from)
+    }
 
-        if let Some(host) = &self.host {
-            return match extract.extract(host) {
-                Ok(t) => t.subdomain,
-                Err(_) => None,
-            };
-        }
+    /// Truncate the host of the URL to the given granularity.
+    /// 
+    /// IP addresses and hosts with unknown suffixes have no registered domain, so the full host is returned.
+    /// Hosts which are themselves a public suffix are also returned whole.
+    /// 
+    /// # Arguments
+    /// 
+    /// `granularity` - How much of the host to keep.
+    /// 
+    /// # Returns
+    /// 
+    /// None if the URL has no host, otherwise the truncated host.
+    /// 
+    /// # Examples
+    /// 
+    /// ```rust
+    /// use surtr::{HandyUrl, HostGranularity, SurtrOptions};
+    /// 
+    /// let handy_url = HandyUrl::parse("http://a.news.bbc.co.uk/", &SurtrOptions::default()).unwrap();
+    /// 
+    /// assert_eq!(handy_url.get_host_with_granularity(HostGranularity::Full), Some("a.news.bbc.co.uk".to_string()));
+    /// assert_eq!(handy_url.get_host_with_granularity(HostGranularity::RegisteredDomain(1)), Some("news.bbc.co.uk".to_string()));
+    /// assert_eq!(handy_url.get_host_with_granularity(HostGranularity::RegisteredDomain(0)), Some("bbc.co.uk".to_string()));
+    /// assert_eq!(handy_url.get_host_with_granularity(HostGranularity::Suffix), Some("co.uk".to_string()));
+    /// ```
+    pub fn get_host_with_granularity(&self, granularity: HostGranularity) -> Option<String> {
+        let host = self.host.clone()?;
+
+        let labels_below = match granularity {
+            HostGranularity::Full => return Some(host),
+            HostGranularity::RegisteredDomain(n) => n,
+            HostGranularity::Suffix => {
+                return Some(self.get_public_suffix().unwrap_or(host));
+            }
+        };
 
-        None
+        let parts = HostParts::parse(&host);
+        let registered_domain = match parts.registered_domain() {
+            Some(rd) => rd,
+            None => return Some(host),
+        };
+
+        let sub_labels: Vec<&str> = match parts.subdomain() {
+            Some(sub) => sub.split('.').collect(),
+            None => vec![],
+        };
+        let keep_from = sub_labels.len().saturating_sub(labels_below);
+
+        let mut labels = sub_labels[keep_from..].to_vec();
+        labels.push(registered_domain);
+
+        Some(labels.join("."))
     }
 
     /// Recompile the URL as a String, according to the set of user defined options.
@@ -230,16 +325,16 @@ impl HandyUrl {
         let mut host_src = self.host.clone();
 
         // Host
-        if options.get_or("public_suffix", false) && host_src.is_some() {
-            host_src = self.get_public_suffix();
+        if host_src.is_some() {
+            host_src = self.get_host_with_granularity(options.get_host_granularity());
         }
-        if options.get_or("surt", false) {
-            if let Some(hst) = host_src {
-                host_src = Some(host_to_surt(
-                    hst.clone(),
-                    options.get_or("reverse_ipaddr", true),
-                ))
-            }
+        if options.get_or("surt", false)
+            && let Some(hst) = host_src
+        {
+            host_src = Some(host_to_surt(
+                hst.clone(),
+                options.get_or("reverse_ipaddr", true),
+            ))
         }
 
         // Scheme
@@ -298,25 +393,110 @@ impl HandyUrl {
             }
         }
 
-        if let Some(path) = &self.path {
+        let mut path_src = self.path.clone();
+        let mut query_src = self.query.clone();
+        let mut hash_src = self.hash.clone();
+        let mut last_delimiter_src = self.last_delimiter.clone();
+
+        // Path Depth
+        if let Some(depth) = options.get_path_depth()
+            && host_src.is_some()
+        {
+            let truncated = truncate_path(path_src.as_deref().unwrap_or("/"), depth);
+
+            if truncated.is_some() || !options.get_or("path_depth_keep_query", false) {
+                query_src = None;
+                hash_src = None;
+                last_delimiter_src = None;
+            }
+            if truncated.is_some() {
+                path_src = truncated;
+            }
+        }
+
+        if let Some(path) = &path_src {
             output_string = format!("{}{}", output_string, path);
-        } else if self.query.is_some() || self.hash.is_some() {
+        } else if query_src.is_some() || hash_src.is_some() {
             output_string = format!("{}/", output_string);
         }
 
-        if let Some(query) = &self.query {
+        if let Some(query) = &query_src {
             output_string = format!("{}?{}", output_string, query);
         }
-        if let Some(hash) = &self.hash {
+        if let Some(hash) = &hash_src {
             output_string = format!("{}#{}", output_string, hash);
         }
 
-        if let Some(ld) = &self.last_delimiter {
+        if let Some(ld) = &last_delimiter_src {
             output_string = format!("{}{}", output_string, ld);
         }
 
         Ok(output_string)
     }
+
+    /// Canonicalize the URL with both stages used by `surtr::surt()`: the Google stage, then the IA stage.
+    /// 
+    /// # Errors
+    /// 
+    /// `SurtrError::CanonicalizerError(String)` - If a part of the URL is not UTF-8 encoded once unescaped.
+    /// 
+    /// # Examples
+    /// 
+    /// ```rust
+    /// use surtr::{HandyUrl, SurtrOptions};
+    /// 
+    /// let options = SurtrOptions::default();
+    /// let handy_url = HandyUrl::parse("http://www.Example.com:80/a/../b/?z=1&a=2#top", &options).unwrap();
+    /// 
+    /// assert_eq!(handy_url.canonicalize(&options).unwrap().get_url(&options).unwrap(), "http://example.com/b?a=2&z=1");
+    /// ```
+    pub fn canonicalize(self, options: &SurtrOptions) -> Result<Self, SurtrError> {
+        canonicalizers::default::canonicalize(self, options)
+    }
+
+    /// Canonicalize the URL as Google's Safe Browsing API does. The hash is removed, the host and
+    /// path are unescaped and normalized, and the rest of the URL is minimally escaped.
+    /// 
+    /// The options are not used by this stage.
+    /// 
+    /// # Errors
+    /// 
+    /// `SurtrError::CanonicalizerError(String)` - If a part of the URL is not UTF-8 encoded once unescaped.
+    /// 
+    /// # Examples
+    /// 
+    /// ```rust
+    /// use surtr::{HandyUrl, SurtrOptions};
+    /// 
+    /// let options = SurtrOptions::default();
+    /// let handy_url = HandyUrl::parse("http://www.Example.com/a/../b/?z=1&a=2#top", &options).unwrap();
+    /// 
+    /// assert_eq!(handy_url.canonicalize_google(&options).unwrap().get_url(&options).unwrap(), "http://www.example.com/b/?z=1&a=2");
+    /// ```
+    pub fn canonicalize_google(self, options: &SurtrOptions) -> Result<Self, SurtrError> {
+        canonicalizers::google::canonicalize(self, options)
+    }
+
+    /// Canonicalize the URL as the Internet Archive does, controlled by the Canonicalization options
+    /// of `SurtrOptions`. For example, `www` is removed from the host and the query is sorted.
+    /// 
+    /// # Errors
+    /// 
+    /// This stage does not currently return errors.
+    /// 
+    /// # Examples
+    /// 
+    /// ```rust
+    /// use surtr::{HandyUrl, SurtrOptions};
+    /// 
+    /// let options = SurtrOptions::default();
+    /// let handy_url = HandyUrl::parse("http://www.Example.com/a/../b/?z=1&a=2#top", &options).unwrap();
+    /// 
+    /// assert_eq!(handy_url.canonicalize_ia(&options).unwrap().get_url(&options).unwrap(), "http://example.com/a/../b?a=2&z=1#top");
+    /// ```
+    pub fn canonicalize_ia(self, options: &SurtrOptions) -> Result<Self, SurtrError> {
+        canonicalizers::ia::canonicalize(self, options)
+    }
 }
 
 impl Display for HandyUrl {
@@ -493,7 +673,7 @@ mod tests {
     }
 
     #[test]
-    fn test_get_public_suffix() {
+    fn test_get_registered_domain() {
         // These tests are based off the ones found in HandyURLTest.java
         let mut url = HandyUrl {
             scheme: None,
@@ -505,23 +685,113 @@ mod tests {
             query: None,
             hash: None,
             last_delimiter: None,
+            replay_timestamp: None,
+            replay_modifier: None,
         };
 
         url.host = Some("www.fool.com".to_string());
-        assert_eq!(&url.get_public_suffix().unwrap(), "fool.com");
+        assert_eq!(&url.get_registered_domain().unwrap(), "fool.com");
+        assert_eq!(&url.get_public_suffix().unwrap(), "com");
 
         url.host = Some("www.amazon.co.uk".to_string());
-        assert_eq!(&url.get_public_suffix().unwrap(), "amazon.co.uk");
+        assert_eq!(&url.get_registered_domain().unwrap(), "amazon.co.uk");
+        assert_eq!(&url.get_public_suffix().unwrap(), "co.uk");
 
         url.host = Some("www.images.amazon.co.uk".to_string());
-        assert_eq!(&url.get_public_suffix().unwrap(), "amazon.co.uk");
+        assert_eq!(&url.get_registered_domain().unwrap(), "amazon.co.uk");
 
         url.host = Some("funky-images.fancy.co.jp".to_string());
-        assert_eq!(&url.get_public_suffix().unwrap(), "fancy.co.jp");
+        assert_eq!(&url.get_registered_domain().unwrap(), "fancy.co.jp");
+
+        url.host = Some("192.168.1.254".to_string());
+        assert_eq!(url.get_registered_domain(), None);
+        assert_eq!(url.get_public_suffix(), None);
+
+        url.host = None;
+        assert_eq!(url.get_host_parts(), None);
+    }
+
+    #[test]
+    fn test_get_url_host_granularity() {
+        let mut opts = SurtrOptions::default();
+        opts.set("surt", true);
+        opts.set("with_scheme", false);
+
+        let url = HandyUrl::parse("http://a.news.bbc.co.uk/", &opts).unwrap();
+        let ip_url = HandyUrl::parse("http://192.168.1.254/", &opts).unwrap();
+        let unknown_url = HandyUrl::parse("http://a.intranet.corp/", &opts).unwrap();
+
+        let expected = [
+            (HostGranularity::Full, "uk,co,bbc,news,a)/", "254,1,168,192)/", "corp,intranet,a)/"),
+            (HostGranularity::RegisteredDomain(5), "uk,co,bbc,news,a)/", "254,1,168,192)/", "corp,intranet,a)/"),
+            (HostGranularity::RegisteredDomain(1), "uk,co,bbc,news)/", "254,1,168,192)/", "corp,intranet,a)/"),
+            (HostGranularity::RegisteredDomain(0), "uk,co,bbc)/", "254,1,168,192)/", "corp,intranet,a)/"),
+            (HostGranularity::Suffix, "uk,co)/", "254,1,168,192)/", "corp,intranet,a)/"),
+        ];
+
+        for (granularity, domain, ip, unknown) in expected {
+            opts.set_host_granularity(granularity);
+
+            assert_eq!(url.get_url(&opts).unwrap(), domain);
+            assert_eq!(ip_url.get_url(&opts).unwrap(), ip);
+            assert_eq!(unknown_url.get_url(&opts).unwrap(), unknown);
+        }
+    }
+
+    #[test]
+    fn test_get_url_path_depth() {
+        let mut opts = SurtrOptions::default();
+        opts.set("surt", true);
+        opts.set("with_scheme", false);
+
+        let url = HandyUrl::parse("http://archive.org/details/foo/bar?a=b#top", &opts).unwrap();
+        let short_url = HandyUrl::parse("http://archive.org/details?a=b", &opts).unwrap();
+        let mailto_url = HandyUrl::parse("mailto:foo@example.com", &opts).unwrap();
+
+        opts.set_path_depth(Some(0));
+        assert_eq!(url.get_url(&opts).unwrap(), "org,archive)/");
+
+        opts.set_path_depth(Some(1));
+        assert_eq!(url.get_url(&opts).unwrap(), "org,archive)/details");
+        assert_eq!(short_url.get_url(&opts).unwrap(), "org,archive)/details");
+        assert_eq!(mailto_url.get_url(&opts).unwrap(), "mailto:foo@example.com");
+
+        opts.set("path_depth_keep_query", true);
+        assert_eq!(url.get_url(&opts).unwrap(), "org,archive)/details");
+        assert_eq!(short_url.get_url(&opts).unwrap(), "org,archive)/details?a=b");
+
+        opts.set_path_depth(Some(3));
+        assert_eq!(url.get_url(&opts).unwrap(), "org,archive)/details/foo/bar?a=b#top");
+
+        opts.set_path_depth(None);
+        assert_eq!(url.get_url(&opts).unwrap(), "org,archive)/details/foo/bar?a=b#top");
+    }
+
+    #[test]
+    fn test_parse_replay_unwrap() {
+        let replay = "http://web.archive.org/web/20090517140029id_/http://www.example.com/page?a=b";
+        let mut opts = SurtrOptions::default();
+        opts.set("surt", true);
+        opts.set("with_scheme", false);
+
+        let url = HandyUrl::parse(replay, &opts).unwrap();
+        assert_eq!(url.host.as_deref(), Some("web.archive.org"));
+        assert_eq!(url.replay_timestamp, None);
+
+        opts.set_replay_unwrap(Some(crate::ReplayMatcher::default()));
+        let url = HandyUrl::parse(replay, &opts).unwrap();
+        assert_eq!(url.host.as_deref(), Some("www.example.com"));
+        assert_eq!(url.path.as_deref(), Some("/page"));
+        assert_eq!(url.query.as_deref(), Some("a=b"));
+        assert_eq!(url.replay_timestamp.as_deref(), Some("20090517140029"));
+        assert_eq!(url.replay_modifier.as_deref(), Some("id_"));
+
+        let url = HandyUrl::parse("http://www.example.com/page", &opts).unwrap();
+        assert_eq!(url.replay_timestamp, None);
     }
 
     #[test]
-    fn test_get_public_prefix() {
+    fn test_get_subdomain() {
         // These tests are based off the ones found in HandyURLTest.java
         let mut url = HandyUrl {
             scheme: None,
@@ -533,18 +803,20 @@ mod tests {
             query: None,
             hash: None,
             last_delimiter: None,
+            replay_timestamp: None,
+            replay_modifier: None,
         };
 
         url.host = Some("www.fool.com".to_string());
-        assert_eq!(&url._get_public_prefix().unwrap(), "www");
+        assert_eq!(&url.get_subdomain().unwrap(), "www");
 
         url.host = Some("www.amazon.co.uk".to_string());
-        assert_eq!(&url._get_public_prefix().unwrap(), "www");
+        assert_eq!(&url.get_subdomain().unwrap(), "www");
 
         url.host = Some("www.images.amazon.co.uk".to_string());
-        assert_eq!(&url._get_public_prefix().unwrap(), "www.images");
+        assert_eq!(&url.get_subdomain().unwrap(), "www.images");
 
         url.host = Some("funky-images.fancy.co.jp".to_string());
-        assert_eq!(&url._get_public_prefix().unwrap(), "funky-images");
+        assert_eq!(&url.get_subdomain().unwrap(), "funky-images");
     }
 }
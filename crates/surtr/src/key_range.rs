@@ -0,0 +1,328 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{error::SurtrError, options::SurtrOptions, surt_key};
+
+/// The match types supported by CDX servers when looking up a URL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MatchType {
+    /// Only the exact URL.
+    #[default]
+    Exact,
+    /// Any URL which starts with the given URL. e.g. `example.com/blog/` matches `example.com/blog/post`.
+    Prefix,
+    /// Any URL on the same host. e.g. `example.com` matches `example.com/page`, but not `www2.example.com/`.
+    Host,
+    /// Any URL on the same host or its subdomains. e.g. `example.com` matches `www2.example.com/`.
+    Domain,
+}
+
+impl fmt::Display for MatchType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::Exact => "exact",
+            Self::Prefix => "prefix",
+            Self::Host => "host",
+            Self::Domain => "domain",
+        };
+
+        write!(f, "{}", s)
+    }
+}
+
+impl FromStr for MatchType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "exact" => Ok(Self::Exact),
+            "prefix" => Ok(Self::Prefix),
+            "host" => Ok(Self::Host),
+            "domain" => Ok(Self::Domain),
+            _ => Err(format!(
+                "invalid match type '{}'. Expected exact, prefix, host or domain",
+                s
+            )),
+        }
+    }
+}
+
+/// A half open `[start, end)` range of keys, for range scans over sorted CDX files or key-value stores.
+///
+/// The range is compared byte-wise, consistent with CDX files sorted with `LC_ALL=C sort`.
+/// CDX lines start with the key followed by a space, so the whole line can be compared against the range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyRange {
+    /// The first key in the range, inclusive.
+    pub start: String,
+    /// The end of the range, exclusive. None if the range has no upper bound.
+    pub end: Option<String>,
+}
+
+impl KeyRange {
+    /// Check whether a key, or a CDX line starting with a key, falls within the range.
+    pub fn contains(&self, key: &str) -> bool {
+        if key < self.start.as_str() {
+            return false;
+        }
+
+        match &self.end {
+            Some(end) => key < end.as_str(),
+            None => true,
+        }
+    }
+}
+
+/// Returns the key range a CDX server would scan for a URL and match type.
+///
+/// The key is built with `surt()`, so the same options must be used to build the index.
+/// The ranges follow those used by pywb and the wayback CDX server:
+///
+/// | Match Type | `http://example.com/blog/` |
+/// | :--------- | :------------------------- |
+/// | exact      | `[com,example)/blog, com,example)/blog!)` |
+/// | prefix     | `[com,example)/blog/, com,example)/blog0)` |
+/// | host       | `[com,example)/, com,example*)` |
+/// | domain     | `[com,example), com,example-)` |
+///
+/// # Arguments
+///
+/// * `url` - The URL to look up.
+/// * `match_type` - How the URL should be matched.
+/// * `options` - The options used to build the SURT.
+///
+/// # Returns
+///
+/// A Result containing the KeyRange.
+///
+/// # Errors
+///
+/// `SurtrError::UrlParseError` - If the URL cannot be parsed, or a host or domain match is requested for a URL without a host.
+///
+/// # Examples
+///
+/// ```rust
+/// use surtr::{key_range, MatchType};
+///
+/// let range = key_range("http://example.com/", MatchType::Domain, None).unwrap();
+///
+/// assert!(range.contains("com,example)/ 20200101000000"));
+/// assert!(range.contains("com,example,www)/page 20200101000000"));
+/// assert!(!range.contains("com,examples)/ 20200101000000"));
+/// ```
+pub fn key_range(
+    url: &str,
+    match_type: MatchType,
+    options: Option<SurtrOptions>,
+) -> Result<KeyRange, SurtrError> {
+    let key = surt_key(url, options)?;
+
+    if match_type == MatchType::Exact || match_type == MatchType::Prefix {
+        let mut start = key.into_string();
+
+        if match_type == MatchType::Exact {
+            let end = format!("{}!", start);
+            return Ok(KeyRange { start, end: Some(end) });
+        }
+
+        // Canonicalization strips trailing slashes and question marks, which would make
+        // `/blog/` match `/blog2`. They are restored when the URL ends with them.
+        let url = url.trim();
+        if url.ends_with('/') && !start.ends_with('/') {
+            start.push('/');
+        } else if url.ends_with('?') && !start.ends_with('?') {
+            start.push('?');
+        }
+
+        let end = prefix_successor(&start);
+        return Ok(KeyRange { start, end });
+    }
+
+    let host = match key.host() {
+        Some(h) if key.path().is_some() => h,
+        _ => {
+            return Err(SurtrError::UrlParseError(format!(
+                "cannot build a {} range for a url without a host: {}",
+                match_type, url
+            )));
+        }
+    };
+    let scheme_prefix = match key.scheme() {
+        Some(scheme) => format!("{}://(", scheme),
+        None => String::new(),
+    };
+
+    // Trailing commas would stop subdomains matching. The port is kept, as pywb does, so the range
+    // holds the URL's own key.
+    let labels = host.trim_end_matches(',');
+
+    // IP addresses which are not reversed have no subdomains, so they are matched as a host.
+    if match_type == MatchType::Host || labels.contains('.') {
+        return Ok(KeyRange {
+            start: format!("{}{})/", scheme_prefix, host),
+            end: Some(format!("{}{}*", scheme_prefix, host)),
+        });
+    }
+
+    // Top level domains have no `,`, so the bare TLD is excluded, as pywb does.
+    let start = match labels.contains(',') {
+        true => format!("{}{})", scheme_prefix, labels),
+        false => format!("{}{},", scheme_prefix, labels),
+    };
+
+    Ok(KeyRange {
+        start,
+        end: Some(format!("{}{}-", scheme_prefix, labels)),
+    })
+}
+
+/// Returns the smallest string which is greater than every string starting with `prefix`.
+///
+/// This is the exclusive end of a prefix range scan. None is returned when no such string
+/// exists, i.e. the prefix is empty or made entirely of `char::MAX`.
+///
+/// # Examples
+///
+/// ```rust
+/// use surtr::prefix_successor;
+///
+/// assert_eq!(prefix_successor("com,example)/blog/"), Some("com,example)/blog0".to_string()));
+/// assert_eq!(prefix_successor(""), None);
+/// ```
+pub fn prefix_successor(prefix: &str) -> Option<String> {
+    let mut chars: Vec<char> = prefix.chars().collect();
+
+    while let Some(c) = chars.pop() {
+        let next = match c as u32 + 1 {
+            // Skip the surrogate range, which is not valid in a char.
+            0xD800 => Some('\u{E000}'),
+            n => char::from_u32(n),
+        };
+
+        if let Some(n) = next {
+            chars.push(n);
+            return Some(chars.into_iter().collect());
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn opts(pairs: &[(&str, bool)]) -> Option<SurtrOptions> {
+        let mut options = SurtrOptions::default();
+        for (k, v) in pairs {
+            options.set(k, *v);
+        }
+
+        Some(options)
+    }
+
+    fn range(start: &str, end: &str) -> KeyRange {
+        KeyRange { start: start.to_string(), end: Some(end.to_string()) }
+    }
+
+    #[test]
+    fn test_key_range_exact() {
+        let r = key_range("http://www.example.com/page?b=1&a=2", MatchType::Exact, None).unwrap();
+        assert_eq!(r, range("com,example)/page?a=2&b=1", "com,example)/page?a=2&b=1!"));
+
+        assert!(r.contains("com,example)/page?a=2&b=1 20200101000000 http://example.com/"));
+        assert!(!r.contains("com,example)/page?a=2&b=1&c=3 20200101000000"));
+    }
+
+    #[test]
+    fn test_key_range_prefix() {
+        let r = key_range("http://example.com/blog/", MatchType::Prefix, None).unwrap();
+        assert_eq!(r, range("com,example)/blog/", "com,example)/blog0"));
+        assert!(r.contains("com,example)/blog/post 20200101000000"));
+        assert!(!r.contains("com,example)/blog 20200101000000"));
+        assert!(!r.contains("com,example)/blog2 20200101000000"));
+
+        let r = key_range("http://example.com/blog", MatchType::Prefix, None).unwrap();
+        assert_eq!(r, range("com,example)/blog", "com,example)/bloh"));
+        assert!(r.contains("com,example)/blog2 20200101000000"));
+
+        let r = key_range("http://example.com/page?", MatchType::Prefix, None).unwrap();
+        assert_eq!(r, range("com,example)/page?", "com,example)/page@"));
+    }
+
+    #[test]
+    fn test_key_range_host() {
+        let r = key_range("http://www.example.com/some/page", MatchType::Host, None).unwrap();
+        assert_eq!(r, range("com,example)/", "com,example*"));
+        assert!(r.contains("com,example)/ 20200101000000"));
+        assert!(r.contains("com,example)/zzz 20200101000000"));
+        assert!(!r.contains("com,example,www2)/ 20200101000000"));
+        assert!(!r.contains("com,example:8080)/ 20200101000000"));
+
+        let r = key_range("http://example.com:8080/", MatchType::Host, None).unwrap();
+        assert_eq!(r, range("com,example:8080)/", "com,example:8080*"));
+
+        let r = key_range(
+            "http://example.com/",
+            MatchType::Host,
+            opts(&[("with_scheme", true), ("trailing_comma", true)]),
+        )
+        .unwrap();
+        assert_eq!(r, range("http://(com,example,)/", "http://(com,example,*"));
+    }
+
+    #[test]
+    fn test_key_range_domain() {
+        let r = key_range("http://example.com/page", MatchType::Domain, None).unwrap();
+        assert_eq!(r, range("com,example)", "com,example-"));
+        assert!(r.contains("com,example)/ 20200101000000"));
+        assert!(r.contains("com,example,www)/ 20200101000000"));
+        assert!(r.contains("com,example,a,b)/ 20200101000000"));
+        assert!(!r.contains("com,examples)/ 20200101000000"));
+        assert!(!r.contains("com,example-shop)/ 20200101000000"));
+        assert!(!r.contains("com,exampl)/ 20200101000000"));
+
+        let r = key_range("http://example.com/", MatchType::Domain, opts(&[("trailing_comma", true)])).unwrap();
+        assert_eq!(r, range("com,example)", "com,example-"));
+        assert!(r.contains("com,example,)/ 20200101000000"));
+        assert!(r.contains("com,example,www,)/ 20200101000000"));
+
+        let r = key_range("http://example.com:8080/", MatchType::Domain, None).unwrap();
+        assert_eq!(r, range("com,example:8080)", "com,example:8080-"));
+        assert!(r.contains("com,example:8080)/ 20200101000000"));
+        assert!(!r.contains("com,example)/ 20200101000000"));
+
+        let r = key_range("http://example.com:8080/", MatchType::Domain, opts(&[("trailing_comma", true)])).unwrap();
+        assert!(r.contains("com,example:8080,)/ 20200101000000"));
+
+        let r = key_range("http://com/", MatchType::Domain, None).unwrap();
+        assert_eq!(r, range("com,", "com-"));
+
+        let r = key_range("http://192.168.1.254/", MatchType::Domain, opts(&[("reverse_ipaddr", false)])).unwrap();
+        assert_eq!(r, range("192.168.1.254)/", "192.168.1.254*"));
+    }
+
+    #[test]
+    fn test_key_range_no_host() {
+        assert!(key_range("dns:archive.org", MatchType::Exact, None).is_ok());
+        assert!(key_range("dns:archive.org", MatchType::Host, None).is_err());
+        assert!(key_range("mailto:foo@example.com", MatchType::Domain, None).is_err());
+    }
+
+    #[test]
+    fn test_prefix_successor() {
+        assert_eq!(prefix_successor("abc"), Some("abd".to_string()));
+        assert_eq!(prefix_successor("ab\u{10FFFF}"), Some("ac".to_string()));
+        assert_eq!(prefix_successor("a\u{D7FF}"), Some("a\u{E000}".to_string()));
+        assert_eq!(prefix_successor("\u{10FFFF}"), None);
+        assert_eq!(prefix_successor(""), None);
+    }
+
+    #[test]
+    fn test_match_type_from_str() {
+        for m in [MatchType::Exact, MatchType::Prefix, MatchType::Host, MatchType::Domain] {
+            assert_eq!(m.to_string().parse::<MatchType>(), Ok(m));
+        }
+        assert!("wildcard".parse::<MatchType>().is_err());
+    }
+}
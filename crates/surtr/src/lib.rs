@@ -11,15 +11,41 @@
 //! [The Internet Archive]: https://github.com/internetarchive/surt
 //! [IA implementation]: https://github.com/internetarchive/surt
 
+mod acl;
+mod domain;
 mod error;
+mod fuzzy;
 mod handy_url;
+mod key_range;
+mod lookup;
+mod nested;
 mod options;
 mod canonicalizers;
+mod cdx;
 mod regex_transformer;
+mod replay;
+mod surt_key;
+mod transform;
+mod url_query;
+mod warc;
+mod zipnum;
 pub mod url_split;
 
-pub use options::SurtrOptions;
+pub use options::{HostGranularity, SurtrOptions};
 pub use handy_url::HandyUrl;
+pub use domain::HostParts;
+pub use surt_key::SurtKey;
+pub use key_range::{key_range, prefix_successor, KeyRange, MatchType};
+pub use lookup::{CdxFile, CdxLines, CdxSource, ZipNumIndex};
+pub use zipnum::{write_zipnum, ZipNumWriter, DEFAULT_LINES_PER_BLOCK};
+pub use replay::{ReplayMatcher, ReplayParts, ReplayUrl};
+pub use nested::{NestedUrlMode, NestedUrls};
+pub use url_query::UrlQuery;
+pub use acl::{Access, AclRule, AclRules, Embargo};
+pub use fuzzy::{FilterKind, FuzzyFilter, FuzzyMatch, FuzzyRule, FuzzyRules};
+pub use warc::WarcIndexer;
+pub use cdx::{rekey_cdx, CdxRecord, CdxRekeyer, CdxWriter, CdxjRecord, RekeyLines, RekeyedLine};
+pub use transform::{FieldMode, FieldSelector, FieldTransformer, RecordFormat, Records, TransformError};
 pub use error::SurtrError;
 
 
@@ -63,23 +89,117 @@ pub fn surt(
     url: &str,
     options: Option<options::SurtrOptions>,
 ) -> Result<String, error::SurtrError> {
-    let mut s_options: options::SurtrOptions = match options {
-        Some(opt) => opt.clone(),
-        None => options::SurtrOptions::default(),
-    };
-
-    // Set Default
-    if s_options.get("surt").is_none() {
-        s_options.set("surt", true);
-    }
-    if s_options.get("with_scheme").is_none() {
-        s_options.set("with_scheme", false);
-    }
+    let s_options = options.unwrap_or_default().with_surt_defaults();
 
-    // Default
     _surt(url, &s_options)
 }
 
+/// Returns the Result of a SURT operation as a `SurtKey`.
+/// 
+/// This behaves exactly as `surt()`, but wraps the output in a `SurtKey`, which provides
+/// CDX compatible ordering and host aware prefix comparisons.
+/// 
+/// # Arguments
+/// 
+/// * `url` - The URL to be transformed.
+/// * `options` - The options to be used for the transformation.
+/// 
+/// # Returns
+/// 
+/// A Result containing the SurtKey, or an error if the URL is invalid.
+/// 
+/// # Examples
+/// 
+/// ```rust
+/// use surtr::surt_key;
+/// 
+/// let page = surt_key("http://www.example.com/page", None).unwrap();
+/// let domain = surt_key("http://example.com/", None).unwrap();
+/// 
+/// assert_eq!(page.as_str(), "com,example)/page");
+/// assert!(domain.host_contains(&page));
+/// assert!(domain < page);
+/// ```
+pub fn surt_key(
+    url: &str,
+    options: Option<options::SurtrOptions>,
+) -> Result<SurtKey, error::SurtrError> {
+    surt(url, options).map(SurtKey::from)
+}
+
+/// Returns the Result of a SURT operation, borrowing the options.
+/// 
+/// This behaves exactly as `surt()`, but the options are only copied if `surt` or `with_scheme`
+/// is unset, to apply the defaults of `surt()`. Resolve them once with `SurtrOptions::with_surt_defaults`
+/// to convert many URLs with one set of options, without copying it for each URL.
+/// 
+/// # Arguments
+/// 
+/// * `url` - The URL to be transformed.
+/// * `options` - The options to be used for the transformation.
+/// 
+/// # Returns
+/// 
+/// A Result containing the transformed URL, or an error if the URL is invalid.
+/// 
+/// # Examples
+/// 
+/// ```rust
+/// use surtr::{surt_with, SurtrOptions};
+/// 
+/// let mut options = SurtrOptions::default();
+/// options.set("surt", true);
+/// options.set("with_scheme", false);
+/// 
+/// for (url, expected) in [("http://www.example.com/", "com,example)/"), ("http://example.org/a", "org,example)/a")] {
+///     assert_eq!(surt_with(url, &options).unwrap(), expected);
+/// }
+/// ```
+pub fn surt_with(
+    url: &str,
+    options: &options::SurtrOptions,
+) -> Result<String, error::SurtrError> {
+    match options.get("surt").is_some() && options.get("with_scheme").is_some() {
+        true => _surt(url, options),
+        false => surt(url, Some(options.clone())),
+    }
+}
+
+/// Returns the subdomain, registered domain and public suffix of a URL or bare host.
+/// 
+/// The host is canonicalized before it is split, so it is lowercased, IDNA encoded and 
+/// IP addresses in alternative formats are normalized.
+/// 
+/// # Arguments
+/// 
+/// * `url` - The URL or host to split. A scheme of `http` is assumed if none is given.
+/// 
+/// # Returns
+/// 
+/// A Result containing the `HostParts` of the host, None if the URL has no host, or an error if the URL is invalid.
+/// 
+/// # Examples
+/// 
+/// ```rust
+/// use surtr::{host_parts, HostParts};
+/// 
+/// let parts = host_parts("https://WWW.Example.co.uk/path").unwrap().unwrap();
+/// assert_eq!(parts.registered_domain(), Some("example.co.uk"));
+/// 
+/// assert_eq!(
+///     host_parts("3279880203").unwrap(),
+///     Some(HostParts::IpAddress("195.127.0.11".to_string()))
+/// );
+/// ```
+pub fn host_parts(url: &str) -> Result<Option<HostParts>, error::SurtrError> {
+    let options = options::SurtrOptions::default();
+
+    let mut hurl = handy_url::HandyUrl::parse(url, &options)?;
+    hurl = canonicalizers::google::canonicalize(hurl, &options)?;
+
+    Ok(hurl.get_host_parts())
+}
+
 fn _surt(
     url: &str,
     options: &options::SurtrOptions,
@@ -579,6 +699,19 @@ mod tests {
         // Code currently outputs: "com,example)/script?+c&grape=wine&type=a+b+"
     }
 
+    #[test]
+    fn test_surt_with() {
+        let mut options = options::SurtrOptions::default();
+        assert_eq!(surt_with("http://www.example.com/", &options).unwrap(), "com,example)/");
+
+        options.set("with_scheme", true);
+        assert_eq!(surt_with("http://www.example.com/", &options).unwrap(), "http://(com,example)/");
+
+        options.set("surt", false);
+        assert_eq!(surt_with("http://www.example.com/", &options).unwrap(), "http://example.com/");
+        assert_eq!(surt_with("filedesc:foo.arc.gz", &options).unwrap(), "filedesc:foo.arc.gz");
+    }
+
     #[test]
     fn test_surt_nonascii() {
         assert_eq!(
@@ -587,6 +720,56 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_host_parts() {
+        assert_eq!(
+            host_parts("www.amazon.co.uk").unwrap(),
+            Some(HostParts::Domain {
+                subdomain: Some("www".to_string()),
+                registered_domain: Some("amazon.co.uk".to_string()),
+                suffix: "co.uk".to_string(),
+            })
+        );
+        assert_eq!(
+            host_parts("http://192.168.1.254/info/").unwrap(),
+            Some(HostParts::IpAddress("192.168.1.254".to_string()))
+        );
+        assert_eq!(
+            host_parts("http://intranet.corp/").unwrap(),
+            Some(HostParts::UnknownSuffix("intranet.corp".to_string()))
+        );
+        assert_eq!(host_parts("mailto:foo@example.com").unwrap(), None);
+    }
+
+    #[test]
+    fn test_surt_public_suffix() {
+        let mut opts = options::SurtrOptions::default();
+        opts.set("public_suffix", true);
+
+        assert_eq!(
+            surt("http://www.images.amazon.co.uk/foo", Some(opts.clone())).unwrap(),
+            "uk,co,amazon)/foo"
+        );
+        // IP addresses and unknown suffixes have no registered domain, so the full host is kept.
+        assert_eq!(
+            surt("http://192.168.1.254/info/", Some(opts.clone())).unwrap(),
+            "254,1,168,192)/info"
+        );
+        assert_eq!(
+            surt("http://192.168.1.1/x", Some(opts.clone())).unwrap(),
+            "1,1,168,192)/x"
+        );
+        assert_eq!(
+            surt("http://intranet.corp/info/", Some(opts.clone())).unwrap(),
+            "corp,intranet)/info"
+        );
+        // The registered domain of an internationalized host is kept in punycode, like the rest of the host.
+        assert_eq!(
+            surt("http://www.bücher.de/", Some(opts)).unwrap(),
+            "de,xn--bcher-kva)/"
+        );
+    }
+
     #[test]
     fn test_surt_ipaddress() {
         let mut reverse_ip_opts = options::SurtrOptions::default();
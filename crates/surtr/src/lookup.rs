@@ -0,0 +1,610 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use flate2::bufread::MultiGzDecoder;
+use memmap2::Mmap;
+
+use crate::{
+    error::SurtrError,
+    key_range::{key_range, KeyRange, MatchType},
+    options::SurtrOptions,
+};
+
+/// The lines returned by a lookup, in index order.
+pub type CdxLines<'a> = Box<dyn Iterator<Item = io::Result<String>> + 'a>;
+
+/// A sorted CDX or CDXJ index which can be searched by key without loading it into memory.
+///
+/// The index must be sorted byte-wise, with `LC_ALL=C sort`, and keyed with the same
+/// options that are passed to `lookup()`.
+pub trait CdxSource {
+    /// Returns the lines of the index which fall within a key range.
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - The range of keys to return. Whole lines are compared against the range.
+    ///
+    /// # Returns
+    ///
+    /// An iterator over the matching lines, without their line endings. CDX header lines are skipped.
+    fn lines_in_range<'a>(&'a self, range: &KeyRange) -> CdxLines<'a>;
+
+    /// Returns the lines of the index which match a URL.
+    ///
+    /// The key of the URL is built with `surt()`, and the range scanned depends on the match type,
+    /// as described by `key_range()`.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL to look up.
+    /// * `match_type` - How the URL should be matched.
+    /// * `options` - The options used to build the index.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing an iterator over the matching lines.
+    ///
+    /// # Errors
+    ///
+    /// `SurtrError::UrlParseError` - If the URL cannot be parsed, or a host or domain match is requested for a URL without a host.
+    fn lookup<'a>(
+        &'a self,
+        url: &str,
+        match_type: MatchType,
+        options: Option<SurtrOptions>,
+    ) -> Result<CdxLines<'a>, SurtrError> {
+        let range = key_range(url, match_type, options)?;
+        Ok(self.lines_in_range(&range))
+    }
+}
+
+/// A sorted, uncompressed CDX or CDXJ file.
+///
+/// The file is memory-mapped, and the first matching line is found with a binary search,
+/// so only the pages around the search path and the matching lines are read.
+///
+/// # Examples
+///
+/// ```rust
+/// use surtr::{CdxFile, CdxSource, MatchType};
+///
+/// let cdx = CdxFile::from_bytes(
+///     b"com,example)/ 20200101000000 http://example.com/\n\
+///       com,example)/about 20200101000000 http://example.com/about\n\
+///       org,example)/ 20200101000000 http://example.org/\n"
+///         .to_vec(),
+/// );
+///
+/// let lines: Vec<String> = cdx
+///     .lookup("http://www.example.com/", MatchType::Host, None)
+///     .unwrap()
+///     .map(|l| l.unwrap())
+///     .collect();
+///
+/// assert_eq!(lines.len(), 2);
+/// assert!(lines[1].starts_with("com,example)/about "));
+/// ```
+pub struct CdxFile {
+    data: Data,
+}
+
+impl CdxFile {
+    /// Open a sorted CDX or CDXJ file.
+    ///
+    /// # Errors
+    ///
+    /// `io::Error` - If the file cannot be opened or mapped.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self { data: Data::open(path.as_ref())? })
+    }
+
+    /// Create an index from the contents of a sorted CDX or CDXJ file held in memory.
+    pub fn from_bytes(data: Vec<u8>) -> Self {
+        Self { data: Data::Owned(data) }
+    }
+}
+
+impl CdxSource for CdxFile {
+    fn lines_in_range<'a>(&'a self, range: &KeyRange) -> CdxLines<'a> {
+        let data = self.data.bytes();
+        let pos = lower_bound(data, range.start.as_bytes());
+
+        Box::new(FileLines { data, pos, end: range.end.clone() })
+    }
+}
+
+/// A ZipNum cluster: a sorted CDX split into gzip compressed blocks, with a secondary index.
+///
+/// Each line of the secondary `.idx` file is the first key of a block, followed by
+/// tab separated fields for the part file holding it, the offset and the length of its
+/// gzip member, and the block number, as written by pywb and webarchive-commons.
+///
+/// Part names are resolved with the `.loc` file next to the `.idx` file, if there is one,
+/// where each line is a part name and its path, separated by a tab. Otherwise, parts are
+/// read from the directory of the `.idx` file. Relative paths are resolved from the directory
+/// of the file which names them.
+///
+/// The secondary index and the part files are memory-mapped. Lookups binary search the
+/// secondary index and only decompress the blocks which may hold matching lines.
+pub struct ZipNumIndex {
+    index: Data,
+    dir: PathBuf,
+    locations: HashMap<String, PathBuf>,
+    parts: Mutex<HashMap<PathBuf, Arc<Data>>>,
+}
+
+impl ZipNumIndex {
+    /// Open the secondary index of a ZipNum cluster.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the `.idx` file.
+    ///
+    /// # Errors
+    ///
+    /// `io::Error` - If the index or its `.loc` file cannot be read.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+
+        let loc = path.with_extension("loc");
+        let locations = if loc.is_file() {
+            read_locations(&loc, &dir)?
+        } else {
+            HashMap::new()
+        };
+
+        Ok(Self {
+            index: Data::open(path)?,
+            dir,
+            locations,
+            parts: Mutex::new(HashMap::new()),
+        })
+    }
+
+    // Returns the mapped part file, opening it on first use.
+    fn part(&self, name: &str) -> io::Result<Arc<Data>> {
+        let path = match self.locations.get(name) {
+            Some(path) => path.clone(),
+            None => self.dir.join(name),
+        };
+
+        let mut parts = self.parts.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(data) = parts.get(&path) {
+            return Ok(data.clone());
+        }
+
+        let data = Arc::new(Data::open(&path)?);
+        parts.insert(path, data.clone());
+        Ok(data)
+    }
+
+    // Decompresses the block named by a line of the secondary index.
+    fn read_block(&self, line: &[u8]) -> io::Result<Vec<String>> {
+        let line = std::str::from_utf8(line).map_err(|_| invalid("invalid utf-8 in zipnum index".to_string()))?;
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 4 {
+            return Err(invalid(format!("invalid zipnum index line '{}'. Expected key, part, offset and length", line)));
+        }
+
+        let offset: usize = fields[2]
+            .parse()
+            .map_err(|_| invalid(format!("invalid zipnum offset '{}'", fields[2])))?;
+        let length: usize = fields[3]
+            .parse()
+            .map_err(|_| invalid(format!("invalid zipnum length '{}'", fields[3])))?;
+
+        let part = self.part(fields[1])?;
+        let block = offset
+            .checked_add(length)
+            .and_then(|end| part.bytes().get(offset..end))
+            .ok_or_else(|| invalid(format!("zipnum block {}+{} is outside of part '{}'", offset, length, fields[1])))?;
+
+        BufReader::new(MultiGzDecoder::new(block))
+            .lines()
+            .map(|l| l.map(|l| l.trim_end_matches('\r').to_string()))
+            .collect()
+    }
+}
+
+impl CdxSource for ZipNumIndex {
+    fn lines_in_range<'a>(&'a self, range: &KeyRange) -> CdxLines<'a> {
+        let data = self.index.bytes();
+
+        // Blocks are keyed by their first line, so matches may start in the block before
+        // the first block keyed at or after the start of the range.
+        let pos = previous_line_start(data, lower_bound(data, range.start.as_bytes()));
+
+        Box::new(ZipNumLines {
+            index: self,
+            pos,
+            range: range.clone(),
+            block: Vec::new().into_iter(),
+            done: false,
+        })
+    }
+}
+
+// The contents of an index file, memory-mapped or held in memory.
+enum Data {
+    Mapped(Mmap),
+    Owned(Vec<u8>),
+}
+
+impl Data {
+    fn open(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+
+        // Empty files cannot be mapped.
+        if file.metadata()?.len() == 0 {
+            return Ok(Self::Owned(Vec::new()));
+        }
+
+        // Safety: index files are treated as read only. Modifying a file while it is
+        // being searched is undefined behaviour, as with any memory-mapped file.
+        let map = unsafe { Mmap::map(&file)? };
+        Ok(Self::Mapped(map))
+    }
+
+    fn bytes(&self) -> &[u8] {
+        match self {
+            Self::Mapped(map) => map,
+            Self::Owned(data) => data,
+        }
+    }
+}
+
+struct FileLines<'a> {
+    data: &'a [u8],
+    pos: usize,
+    end: Option<String>,
+}
+
+impl Iterator for FileLines<'_> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pos < self.data.len() {
+            let (line, next) = line_at(self.data, self.pos);
+            self.pos = next;
+
+            if let Some(end) = &self.end
+                && line >= end.as_bytes()
+            {
+                self.pos = self.data.len();
+                return None;
+            }
+            if is_skipped(line) {
+                continue;
+            }
+
+            return Some(
+                String::from_utf8(line.to_vec()).map_err(|_| invalid("invalid utf-8 in cdx line".to_string())),
+            );
+        }
+
+        None
+    }
+}
+
+struct ZipNumLines<'a> {
+    index: &'a ZipNumIndex,
+    pos: usize,
+    range: KeyRange,
+    block: std::vec::IntoIter<String>,
+    done: bool,
+}
+
+impl Iterator for ZipNumLines<'_> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            if let Some(line) = self.block.next() {
+                if line < self.range.start || is_skipped(line.as_bytes()) {
+                    continue;
+                }
+                if let Some(end) = &self.range.end
+                    && line >= *end
+                {
+                    self.done = true;
+                    return None;
+                }
+
+                return Some(Ok(line));
+            }
+
+            let data = self.index.index.bytes();
+            if self.pos >= data.len() {
+                self.done = true;
+                return None;
+            }
+
+            let (line, next) = line_at(data, self.pos);
+            self.pos = next;
+            if line.is_empty() {
+                continue;
+            }
+
+            // A block which starts at or after the end of the range holds no matches.
+            if let Some(end) = &self.range.end {
+                let key = line.split(|b| *b == b'\t').next().unwrap_or(line);
+                if key >= end.as_bytes() {
+                    self.done = true;
+                    return None;
+                }
+            }
+
+            match self.index.read_block(line) {
+                Ok(lines) => self.block = lines.into_iter(),
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// CDX headers and blank lines are not records.
+fn is_skipped(line: &[u8]) -> bool {
+    line.is_empty() || line.starts_with(b" CDX ")
+}
+
+// Reads the part locations of a ZipNum cluster.
+fn read_locations(path: &Path, dir: &Path) -> io::Result<HashMap<String, PathBuf>> {
+    let mut locations = HashMap::new();
+
+    for line in fs::read_to_string(path)?.lines() {
+        let mut fields = line.split('\t');
+        if let (Some(name), Some(location)) = (fields.next(), fields.next()) {
+            locations.entry(name.to_string()).or_insert_with(|| dir.join(location));
+        }
+    }
+
+    Ok(locations)
+}
+
+// Returns the start of the first line which starts at or after `pos`.
+fn line_start(data: &[u8], pos: usize) -> usize {
+    if pos == 0 || data[pos - 1] == b'\n' {
+        return pos;
+    }
+
+    match data[pos..].iter().position(|b| *b == b'\n') {
+        Some(i) => pos + i + 1,
+        None => data.len(),
+    }
+}
+
+// Returns the start of the line before the line starting at `pos`.
+fn previous_line_start(data: &[u8], pos: usize) -> usize {
+    if pos == 0 {
+        return 0;
+    }
+
+    match data[..pos - 1].iter().rposition(|b| *b == b'\n') {
+        Some(i) => i + 1,
+        None => 0,
+    }
+}
+
+// Returns the line starting at `pos`, without its line ending, and the start of the next line.
+fn line_at(data: &[u8], pos: usize) -> (&[u8], usize) {
+    let end = match data[pos..].iter().position(|b| *b == b'\n') {
+        Some(i) => pos + i,
+        None => data.len(),
+    };
+
+    let line = &data[pos..end];
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+
+    (line, (end + 1).min(data.len()))
+}
+
+// Returns the start of the first line which sorts at or after `key`, or the end of the data.
+fn lower_bound(data: &[u8], key: &[u8]) -> usize {
+    // Every line starting before `lo` sorts before the key, and `hi` is a line start, or the end.
+    let (mut lo, mut hi) = (0, data.len());
+
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        let start = line_start(data, mid);
+
+        // No line starts between `mid` and `hi`, so step forward from `lo`.
+        if start >= hi {
+            let (line, next) = line_at(data, lo);
+            if line >= key {
+                return lo;
+            }
+            lo = next;
+            continue;
+        }
+
+        let (line, next) = line_at(data, start);
+        if line < key {
+            lo = next;
+        } else {
+            hi = start;
+        }
+    }
+
+    lo
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    fn lines(source: &dyn CdxSource, url: &str, match_type: MatchType) -> Vec<String> {
+        source
+            .lookup(url, match_type, None)
+            .unwrap()
+            .map(|l| l.unwrap())
+            .collect()
+    }
+
+    fn sample() -> Vec<String> {
+        [
+            "com,example)/ 20200101000000 http://example.com/",
+            "com,example)/ 20210101000000 http://example.com/",
+            "com,example)/blog 20200101000000 http://example.com/blog",
+            "com,example)/blog/post 20200101000000 http://example.com/blog/post",
+            "com,example)/blog2 20200101000000 http://example.com/blog2",
+            "com,example,www2)/ 20200101000000 http://www2.example.com/",
+            "com,examples)/ 20200101000000 http://examples.com/",
+            "org,example)/ 20200101000000 http://example.org/",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("surtr-lookup-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_lower_bound() {
+        let data = b"b 1\nb 2\nd 1\nf 1";
+
+        assert_eq!(lower_bound(data, b"a"), 0);
+        assert_eq!(lower_bound(data, b"b"), 0);
+        assert_eq!(lower_bound(data, b"b 2"), 4);
+        assert_eq!(lower_bound(data, b"c"), 8);
+        assert_eq!(lower_bound(data, b"e"), 12);
+        assert_eq!(lower_bound(data, b"g"), data.len());
+        assert_eq!(lower_bound(b"", b"a"), 0);
+
+        assert_eq!(previous_line_start(data, 8), 4);
+        assert_eq!(previous_line_start(data, data.len()), 12);
+        assert_eq!(previous_line_start(data, 0), 0);
+
+        // Every key in a larger file.
+        let keys: Vec<String> = (0..500).map(|i| format!("key{:04} {}", i, "x".repeat(i % 7))).collect();
+        let data = keys.join("\n");
+        for (i, key) in keys.iter().enumerate() {
+            let pos = lower_bound(data.as_bytes(), key.as_bytes());
+            assert_eq!(line_at(data.as_bytes(), pos).0, key.as_bytes(), "key {}", i);
+        }
+    }
+
+    #[test]
+    fn test_cdx_file() {
+        let mut data = format!("{}\n", crate::cdx::CdxRecord::HEADER);
+        data.push_str(&sample().join("\r\n"));
+        let cdx = CdxFile::from_bytes(data.into_bytes());
+        let sample = sample();
+
+        assert_eq!(lines(&cdx, "http://example.com/", MatchType::Exact), sample[0..2]);
+        assert_eq!(lines(&cdx, "http://example.com/blog", MatchType::Exact), sample[2..3]);
+        assert_eq!(lines(&cdx, "http://example.com/blog/", MatchType::Prefix), sample[3..4]);
+        assert_eq!(lines(&cdx, "http://example.com/blog", MatchType::Prefix), sample[2..5]);
+        assert_eq!(lines(&cdx, "http://example.com/", MatchType::Host), sample[0..5]);
+        assert_eq!(lines(&cdx, "http://example.com/", MatchType::Domain), sample[0..6]);
+        assert_eq!(lines(&cdx, "http://example.org/", MatchType::Domain), sample[7..8]);
+        assert!(lines(&cdx, "http://example.net/", MatchType::Domain).is_empty());
+
+        assert!(cdx.lookup("mailto:foo@example.com", MatchType::Host, None).is_err());
+        assert!(lines(&CdxFile::from_bytes(Vec::new()), "http://example.com/", MatchType::Exact).is_empty());
+    }
+
+    #[test]
+    fn test_cdx_file_open() {
+        let dir = temp_dir("plain");
+        let path = dir.join("index.cdx");
+        fs::write(&path, sample().join("\n") + "\n").unwrap();
+
+        let cdx = CdxFile::open(&path).unwrap();
+        assert_eq!(lines(&cdx, "http://example.com/blog2", MatchType::Exact), sample()[4..5]);
+
+        fs::write(dir.join("empty.cdx"), "").unwrap();
+        let cdx = CdxFile::open(dir.join("empty.cdx")).unwrap();
+        assert!(lines(&cdx, "http://example.com/", MatchType::Exact).is_empty());
+
+        assert!(CdxFile::open(dir.join("missing.cdx")).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    // Writes a ZipNum cluster with two lines per block, and returns the path of its index.
+    fn write_zipnum(dir: &Path, with_loc: bool) -> PathBuf {
+        let mut part = Vec::new();
+        let mut idx = String::new();
+
+        for (i, block) in sample().chunks(2).enumerate() {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all((block.join("\n") + "\n").as_bytes()).unwrap();
+            let member = encoder.finish().unwrap();
+
+            let key = block[0].splitn(3, ' ').take(2).collect::<Vec<_>>().join(" ");
+            let name = if with_loc { "part-a" } else { "part-a.cdx.gz" };
+            idx.push_str(&format!("{}\t{}\t{}\t{}\t{}\n", key, name, part.len(), member.len(), i + 1));
+            part.extend(member);
+        }
+
+        fs::write(dir.join("part-a.cdx.gz"), part).unwrap();
+        fs::write(dir.join("cluster.idx"), idx).unwrap();
+        if with_loc {
+            fs::write(dir.join("cluster.loc"), "part-a\tpart-a.cdx.gz\n").unwrap();
+        }
+
+        dir.join("cluster.idx")
+    }
+
+    #[test]
+    fn test_zipnum() {
+        let sample = sample();
+
+        for with_loc in [false, true] {
+            let dir = temp_dir(if with_loc { "zipnum-loc" } else { "zipnum" });
+            let index = ZipNumIndex::open(write_zipnum(&dir, with_loc)).unwrap();
+
+            assert_eq!(lines(&index, "http://example.com/", MatchType::Exact), sample[0..2]);
+            // Starts in the middle of a block, and spans blocks.
+            assert_eq!(lines(&index, "http://example.com/blog/post", MatchType::Exact), sample[3..4]);
+            assert_eq!(lines(&index, "http://example.com/blog", MatchType::Prefix), sample[2..5]);
+            assert_eq!(lines(&index, "http://example.com/", MatchType::Domain), sample[0..6]);
+            assert_eq!(lines(&index, "http://example.org/", MatchType::Domain), sample[7..8]);
+            assert!(lines(&index, "http://example.net/", MatchType::Domain).is_empty());
+            assert!(lines(&index, "http://aaa.com/", MatchType::Domain).is_empty());
+
+            fs::remove_dir_all(dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_zipnum_errors() {
+        let dir = temp_dir("zipnum-errors");
+
+        fs::write(dir.join("missing.idx"), "com,example)/ 20200101000000\tnothing.gz\t0\t10\t1\n").unwrap();
+        let index = ZipNumIndex::open(dir.join("missing.idx")).unwrap();
+        let mut results = index.lookup("http://example.com/", MatchType::Exact, None).unwrap();
+        assert_eq!(results.next().unwrap().unwrap_err().kind(), io::ErrorKind::NotFound);
+        assert!(results.next().is_none());
+
+        fs::write(dir.join("part.gz"), b"not gzip").unwrap();
+        fs::write(dir.join("bad.idx"), "com,example)/ 20200101000000\tpart.gz\t0\t100\t1\n").unwrap();
+        let index = ZipNumIndex::open(dir.join("bad.idx")).unwrap();
+        let err = index.lookup("http://example.com/", MatchType::Exact, None).unwrap().next().unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        fs::write(dir.join("short.idx"), "com,example)/ 20200101000000\tpart.gz\n").unwrap();
+        let index = ZipNumIndex::open(dir.join("short.idx")).unwrap();
+        assert!(index.lookup("http://example.com/", MatchType::Exact, None).unwrap().next().unwrap().is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
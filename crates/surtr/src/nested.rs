@@ -0,0 +1,267 @@
+use std::fmt;
+use std::str::FromStr;
+
+use percent_encoding::{percent_decode_str, utf8_percent_encode};
+
+use crate::canonicalizers;
+use crate::error::SurtrError;
+use crate::handy_url::HandyUrl;
+use crate::options::{HostGranularity, SurtrOptions};
+use crate::replay::URI_COMPONENT;
+
+// URLs nested in the query of nested URLs are extracted, up to this depth.
+const MAX_DEPTH: usize = 8;
+
+/// What is done with URLs found in query parameters by `NestedUrls`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NestedUrlMode {
+    /// Canonicalize the nested URL with the same options, and keep it in the query.
+    #[default]
+    Canonicalize,
+    /// Replace the URL with the nested URL, so the nested URL is keyed.
+    Extract,
+}
+
+impl fmt::Display for NestedUrlMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Canonicalize => write!(f, "canonicalize"),
+            Self::Extract => write!(f, "extract"),
+        }
+    }
+}
+
+impl FromStr for NestedUrlMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "canonicalize" => Ok(Self::Canonicalize),
+            "extract" => Ok(Self::Extract),
+            _ => Err(format!("invalid nested url mode '{}'. Expected canonicalize or extract", s)),
+        }
+    }
+}
+
+/// Finds URLs nested in query parameters, such as the target of `http://example.com/redirect?url=http%3A%2F%2Fexample.org%2F`.
+///
+/// A parameter holds a nested URL if its name is one of the configured names, compared without
+/// case, and its value is an `http` or `https` URL once percent decoded. By default, the
+/// parameters `url`, `u`, `r` and `redirect` are checked.
+///
+/// Set with `SurtrOptions::set_nested_urls`. In `NestedUrlMode::Canonicalize`, nested URLs are
+/// canonicalized with the same options, so differently written redirects to the same page share a
+/// key. In `NestedUrlMode::Extract`, the nested URL is keyed instead of the URL holding it.
+///
+/// # Examples
+///
+/// ```rust
+/// use surtr::{surt, NestedUrlMode, NestedUrls, SurtrOptions};
+///
+/// let nested = NestedUrls::default().with_params(vec!["target".to_string()]);
+/// assert_eq!(
+///     nested.nested_url("http://example.com/out?id=1&target=https%3A%2F%2Fexample.org%2F").as_deref(),
+///     Some("https://example.org/")
+/// );
+///
+/// let mut options = SurtrOptions::default();
+/// options.set_nested_urls(Some(NestedUrls::default()));
+/// assert_eq!(
+///     surt("http://example.com/out?url=http%3A%2F%2FWWW.Example.org%3A80%2F", Some(options.clone())).unwrap(),
+///     "com,example)/out?url=http://example.org/"
+/// );
+///
+/// options.set_nested_urls(Some(NestedUrls::default().with_mode(NestedUrlMode::Extract)));
+/// assert_eq!(
+///     surt("http://example.com/out?url=http%3A%2F%2FWWW.Example.org%2Fpage", Some(options)).unwrap(),
+///     "org,example)/page"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NestedUrls {
+    params: Vec<String>,
+    mode: NestedUrlMode,
+}
+
+impl Default for NestedUrls {
+    fn default() -> Self {
+        Self {
+            params: ["url", "u", "r", "redirect"].iter().map(|p| p.to_string()).collect(),
+            mode: NestedUrlMode::default(),
+        }
+    }
+}
+
+impl NestedUrls {
+    /// Set the names of the query parameters which may hold a nested URL.
+    pub fn with_params(mut self, params: Vec<String>) -> Self {
+        self.params = params.into_iter().map(|p| p.to_lowercase()).collect();
+        self
+    }
+
+    /// Set what is done with nested URLs.
+    pub fn with_mode(mut self, mode: NestedUrlMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Returns the names of the query parameters which may hold a nested URL.
+    pub fn params(&self) -> &[String] {
+        &self.params
+    }
+
+    /// Returns what is done with nested URLs.
+    pub fn mode(&self) -> NestedUrlMode {
+        self.mode
+    }
+
+    /// Find the URL nested in the query of a URL.
+    ///
+    /// If the nested URL holds a nested URL itself, the innermost URL is returned.
+    ///
+    /// # Returns
+    ///
+    /// The decoded nested URL, or None if no parameter holds a URL.
+    pub fn nested_url(&self, url: &str) -> Option<String> {
+        let mut nested = self.nested_url_once(url)?;
+
+        for _ in 1..MAX_DEPTH {
+            match self.nested_url_once(&nested) {
+                Some(inner) => nested = inner,
+                None => break,
+            }
+        }
+
+        Some(nested)
+    }
+
+    // Canonicalize each nested URL in a raw query, leaving every other parameter as it is.
+    // Nested URLs which cannot be parsed are left as they are.
+    pub(crate) fn canonicalize_query(&self, query: &str, options: &SurtrOptions) -> String {
+        query
+            .split('&')
+            .map(|param| {
+                let canonical = self
+                    .param_url(param)
+                    .and_then(|nested| canonical_url(&nested, options).ok());
+
+                match (canonical, param.split_once('=')) {
+                    (Some(nested), Some((name, _))) => format!("{}={}", name, utf8_percent_encode(&nested, URI_COMPONENT)),
+                    _ => param.to_string(),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("&")
+    }
+
+    fn nested_url_once(&self, url: &str) -> Option<String> {
+        let query = url.split_once('?')?.1;
+        let query = query.split('#').next().unwrap_or(query);
+
+        query.split('&').find_map(|param| self.param_url(param))
+    }
+
+    // The decoded value of a query parameter, if it is one of the parameters and holds a URL.
+    fn param_url(&self, param: &str) -> Option<String> {
+        let (name, value) = param.split_once('=')?;
+        if !self.params.iter().any(|p| p.eq_ignore_ascii_case(name)) {
+            return None;
+        }
+
+        let value = percent_decode_str(value).decode_utf8_lossy();
+        let lower = value.get(..8).unwrap_or(&value).to_ascii_lowercase();
+        if lower.starts_with("http://") || lower.starts_with("https://") {
+            Some(value.into_owned())
+        } else {
+            None
+        }
+    }
+}
+
+// The canonicalized form of a nested URL, as a full URL rather than a SURT.
+fn canonical_url(url: &str, options: &SurtrOptions) -> Result<String, SurtrError> {
+    let mut hurl = HandyUrl::parse(url, options)?;
+    hurl = canonicalizers::default::canonicalize(hurl, options)?;
+
+    let mut url_options = options.clone();
+    url_options.set("surt", false);
+    url_options.set("with_scheme", true);
+    url_options.set_path_depth(None);
+    url_options.set_host_granularity(HostGranularity::Full);
+
+    hurl.get_url(&url_options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::surt;
+
+    #[test]
+    fn test_nested_url() {
+        let nested = NestedUrls::default();
+
+        assert_eq!(
+            nested.nested_url("http://example.com/?a=1&U=https%3A%2F%2Fexample.org%2F%3Fq%3D1#top").as_deref(),
+            Some("https://example.org/?q=1")
+        );
+        assert_eq!(
+            nested.nested_url("http://example.com/?r=http%3A%2F%2Fexample.org%2F%3Fredirect%3Dhttp%253A%252F%252Fexample.net%252F").as_deref(),
+            Some("http://example.net/")
+        );
+        assert_eq!(nested.nested_url("http://example.com/?url=example.org"), None);
+        assert_eq!(nested.nested_url("http://example.com/?next=http://example.org/"), None);
+        assert_eq!(nested.nested_url("http://example.com/url=http://example.org/"), None);
+
+        let nested = nested.with_params(vec!["Next".to_string()]);
+        assert_eq!(nested.params(), ["next"]);
+        assert_eq!(nested.nested_url("http://example.com/?next=http://example.org/").as_deref(), Some("http://example.org/"));
+    }
+
+    #[test]
+    fn test_nested_url_canonicalize() {
+        let mut options = SurtrOptions::default();
+        options.set_nested_urls(Some(NestedUrls::default()));
+
+        let expected = "com,example)/redirect?id=1&url=http://example.org/page";
+        for url in [
+            "http://example.com/redirect?url=http%3A%2F%2FWWW.EXAMPLE.ORG%3A80%2FPage%2F&id=1",
+            "http://example.com/redirect?id=1&url=http://www.example.org/page#top",
+            "http://example.com/redirect?id=1&url=http%3A%2F%2Fexample.org%2Fpage%3Fjsessionid%3D0123456789abcdefghijklmnopqrstuv",
+        ] {
+            assert_eq!(surt(url, Some(options.clone())).unwrap(), expected, "{}", url);
+        }
+
+        // Without the option, the nested URLs are only percent decoded.
+        assert_eq!(
+            surt("http://example.com/redirect?id=1&url=http://www.example.org/page", None).unwrap(),
+            "com,example)/redirect?id=1&url=http://www.example.org/page"
+        );
+
+        // Other parameters and values which are not URLs are untouched.
+        assert_eq!(
+            NestedUrls::default().canonicalize_query("next=http://WWW.example.org/&url=Example&u", &options),
+            "next=http://WWW.example.org/&url=Example&u"
+        );
+    }
+
+    #[test]
+    fn test_nested_url_extract() {
+        let mut options = SurtrOptions::default();
+        options.set_nested_urls(Some(NestedUrls::default().with_mode(NestedUrlMode::Extract)));
+
+        assert_eq!(
+            surt("http://example.com/redirect?redirect=https%3A%2F%2Fwww.example.org%2Fa%3Fb%3D2%26a%3D1", Some(options.clone())).unwrap(),
+            "org,example)/a?a=1&b=2"
+        );
+        assert_eq!(surt("http://example.com/redirect?id=1", Some(options)).unwrap(), "com,example)/redirect?id=1");
+    }
+
+    #[test]
+    fn test_nested_url_mode_from_str() {
+        for mode in [NestedUrlMode::Canonicalize, NestedUrlMode::Extract] {
+            assert_eq!(mode.to_string().parse::<NestedUrlMode>(), Ok(mode));
+        }
+        assert!("recursive".parse::<NestedUrlMode>().is_err());
+    }
+}
@@ -1,4 +1,9 @@
 use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::nested::NestedUrls;
+use crate::replay::ReplayMatcher;
 
 
 /// SurtrOptions contains all the options possible for use with Surtr.
@@ -10,7 +15,7 @@ use std::collections::HashMap;
 ///
 /// |              Surtr Option              | Stage Affected    | Default | Description                                                                                               |
 /// | :------------------------------------- | :---------------  | :------ | :-------------------------------------------------------------------------------------------------------- |
-/// |             public_suffix              | SURT Compilation  |  false  | Discard any subdomains in the URL.                                                                        |
+/// |             public_suffix              | SURT Compilation  |  false  | Discard any subdomains in the URL. IP addresses and hosts with unknown suffixes are kept whole.           |
 /// |                  surt                  | SURT Compilation  |  true   | Return the URL as a SURT. Returns as a valid URL if false.                                                |
 /// |             reverse_ipaddr             | SURT Compilation  |  true   | Reverses the IP address in the SURT. Only valid when surt=true                                            |
 /// |              with_scheme               | SURT Compilation  |  true   | Includes the scheme (http, dns, ftp) as part of the SURT.                                                 |
@@ -29,6 +34,7 @@ use std::collections::HashMap;
 /// |            query_lowercase             | Canonicalization  |  true   | Convert all elements of the query parameters to lowercase.                                                |
 /// |          query_alpha_reorder           | Canonicalization  |  true   | Reorder the query parameters into alphabetical order.                                                     |
 /// |           query_strip_empty            | Canonicalization  |  true   | Remove the query parameter ? if there aren't any query parameters.                                        |
+/// |         path_depth_keep_query          | SURT Compilation  |  false  | Keep the query when `path_depth` is set and the path was not truncated.                                   |
 /// 
 /// # Examples
 /// 
@@ -48,13 +54,101 @@ use std::collections::HashMap;
 /// assert_eq!(options.get_or("public_suffix", false), true);
 /// assert_eq!(options.get_or("query_strip_session_id", false), false);
 /// ```
+/// 
+/// Alongside the toggles, the amount of the host kept in the output can be set with 
+/// `SurtrOptions::set_host_granularity`. See `HostGranularity` for details. The number of 
+/// path segments kept can be set with `SurtrOptions::set_path_depth`, archive replay URLs
+/// can be unwrapped with `SurtrOptions::set_replay_unwrap`, and URLs nested in query parameters
+/// can be handled with `SurtrOptions::set_nested_urls`.
 #[derive(Debug, Clone, Default)]
 pub struct SurtrOptions {
     options: HashMap<String, bool>,
+    host_granularity: Option<HostGranularity>,
+    path_depth: Option<usize>,
+    replay_unwrap: Option<ReplayMatcher>,
+    nested_urls: Option<NestedUrls>,
 }
 
 impl SurtrOptions {
 
+    /// The names of every toggle understood by Surtr.
+    pub const OPTION_NAMES: [&'static str; 20] = [
+        "public_suffix",
+        "surt",
+        "reverse_ipaddr",
+        "with_scheme",
+        "trailing_comma",
+        "host_lowercase",
+        "host_massage",
+        "auth_exclude",
+        "auth_strip_user",
+        "auth_strip_pass",
+        "port_strip_default",
+        "path_strip_empty",
+        "path_lowercase",
+        "path_strip_session_id",
+        "path_srtip_trailing_slash_unless_empty",
+        "query_strip_session_id",
+        "query_lowercase",
+        "query_alpha_reorder",
+        "query_strip_empty",
+        "path_depth_keep_query",
+    ];
+
+    /// The names of the profiles accepted by `SurtrOptions::profile`.
+    pub const PROFILES: [&'static str; 3] = ["default", "heritrix", "canonical"];
+
+    /// Get a named set of options, matching the keys produced by other tools.
+    /// 
+    /// The profiles are:
+    /// 
+    /// |  Profile  | `http://www.example.com/`    | Description                                              |
+    /// | :-------- | :--------------------------- | :------------------------------------------------------- |
+    /// | default   | `com,example)/`              | The keys produced by IA's Python `surt` and CDX servers. |
+    /// | heritrix  | `http://(com,example,www,)/` | The SURT prefixes used by Heritrix scope rules.          |
+    /// | canonical | `http://example.com/`        | The canonicalized URL, rather than a SURT.               |
+    /// 
+    /// Options can be set on the returned value to adjust the profile.
+    /// 
+    /// # Errors
+    /// 
+    /// Returns an error message if the profile is not known.
+    /// 
+    /// # Examples
+    /// 
+    /// ```rust
+    /// use surtr::{surt, SurtrOptions};
+    /// 
+    /// let options = SurtrOptions::profile("heritrix").unwrap();
+    /// 
+    /// assert_eq!(surt("http://www.example.com/", Some(options)).unwrap(), "http://(com,example,www,)/");
+    /// ```
+    pub fn profile(name: &str) -> Result<Self, String> {
+        let mut options = Self::default();
+
+        match name {
+            "default" => {}
+            "heritrix" => {
+                options.set("with_scheme", true);
+                options.set("trailing_comma", true);
+                options.set("host_massage", false);
+            }
+            "canonical" => {
+                options.set("surt", false);
+                options.set("with_scheme", true);
+            }
+            _ => {
+                return Err(format!(
+                    "invalid profile '{}'. Expected one of {}",
+                    name,
+                    Self::PROFILES.join(", ")
+                ));
+            }
+        }
+
+        Ok(options)
+    }
+
     /// Get the value of an option.
     /// 
     /// Will return None if the given option is not present within the HashMap.
@@ -82,6 +176,211 @@ impl SurtrOptions {
         self.options.insert(option.to_string(), value);
     }
 
+    /// Set `surt` and `with_scheme` to the defaults used by `surt()`, unless they are already set.
+    /// 
+    /// Options resolved once in this way can be passed to `surt_with()` for many URLs, without
+    /// being copied for each URL.
+    /// 
+    /// # Examples
+    /// 
+    /// ```rust
+    /// use surtr::{surt_with, SurtrOptions};
+    /// 
+    /// let options = SurtrOptions::default().with_surt_defaults();
+    /// 
+    /// assert_eq!(options.get("surt"), Some(true));
+    /// assert_eq!(surt_with("http://www.example.com/", &options).unwrap(), "com,example)/");
+    /// ```
+    pub fn with_surt_defaults(mut self) -> Self {
+        if self.get("surt").is_none() {
+            self.set("surt", true);
+        }
+        if self.get("with_scheme").is_none() {
+            self.set("with_scheme", false);
+        }
+
+        self
+    }
+
+    /// Get the granularity of the host used when compiling the URL.
+    /// 
+    /// If no granularity has been set, the `public_suffix` option is used. `public_suffix=true` is
+    /// equivalent to `HostGranularity::RegisteredDomain(0)`, otherwise the full host is kept.
+    pub fn get_host_granularity(&self) -> HostGranularity {
+        match self.host_granularity {
+            Some(g) => g,
+            None if self.get_or("public_suffix", false) => HostGranularity::RegisteredDomain(0),
+            None => HostGranularity::Full,
+        }
+    }
+
+    /// Set the granularity of the host used when compiling the URL.
+    /// 
+    /// This takes priority over the `public_suffix` option.
+    pub fn set_host_granularity(&mut self, granularity: HostGranularity) {
+        self.host_granularity = Some(granularity);
+    }
+
+    /// Get the number of path segments kept when compiling the URL.
+    /// 
+    /// None keeps the full path. This is the default.
+    pub fn get_path_depth(&self) -> Option<usize> {
+        self.path_depth
+    }
+
+    /// Set the number of path segments kept when compiling the URL.
+    /// 
+    /// Keys truncated to a path depth are useful for directory level grouping. For example, a depth 
+    /// of 1 rolls `org,archive)/details/foo/bar` up to `org,archive)/details`.
+    /// 
+    /// The query and hash are always dropped from truncated paths, as they belong to the full path.
+    /// They are also dropped from paths which were not truncated, unless `path_depth_keep_query` is set.
+    /// 
+    /// # Examples
+    /// 
+    /// ```rust
+    /// use surtr::{surt, SurtrOptions};
+    /// 
+    /// let mut options = SurtrOptions::default();
+    /// options.set_path_depth(Some(1));
+    /// 
+    /// assert_eq!(surt("http://archive.org/details/foo/bar?a=b", Some(options.clone())).unwrap(), "org,archive)/details");
+    /// assert_eq!(surt("http://archive.org/details?a=b", Some(options.clone())).unwrap(), "org,archive)/details");
+    /// 
+    /// options.set("path_depth_keep_query", true);
+    /// assert_eq!(surt("http://archive.org/details?a=b", Some(options)).unwrap(), "org,archive)/details?a=b");
+    /// ```
+    pub fn set_path_depth(&mut self, depth: Option<usize>) {
+        self.path_depth = depth;
+    }
+
+    /// Get the matcher used to unwrap archive replay URLs when parsing.
+    /// 
+    /// None keeps replay URLs as they are. This is the default.
+    pub fn get_replay_unwrap(&self) -> Option<&ReplayMatcher> {
+        self.replay_unwrap.as_ref()
+    }
+
+    /// Set the matcher used to unwrap archive replay URLs when parsing.
+    /// 
+    /// Replay URLs, such as `http://web.archive.org/web/20090517140029/http://example.com/`, are
+    /// replaced by the original URL they embed, so the original URL is keyed. The timestamp and
+    /// modifier are kept on the parsed `HandyUrl`.
+    /// 
+    /// # Examples
+    /// 
+    /// ```rust
+    /// use surtr::{surt, ReplayMatcher, SurtrOptions};
+    /// 
+    /// let mut options = SurtrOptions::default();
+    /// options.set_replay_unwrap(Some(ReplayMatcher::default()));
+    /// 
+    /// assert_eq!(
+    ///     surt("http://web.archive.org/web/20090517140029/http://www.example.com/page", Some(options)).unwrap(),
+    ///     "com,example)/page"
+    /// );
+    /// ```
+    pub fn set_replay_unwrap(&mut self, matcher: Option<ReplayMatcher>) {
+        self.replay_unwrap = matcher;
+    }
+
+    /// Get the handling of URLs nested in query parameters.
+    /// 
+    /// None leaves nested URLs as they are. This is the default.
+    pub fn get_nested_urls(&self) -> Option<&NestedUrls> {
+        self.nested_urls.as_ref()
+    }
+
+    /// Set the handling of URLs nested in query parameters, such as `?url=http%3A%2F%2Fexample.com%2F`.
+    /// 
+    /// Nested URLs are either canonicalized with these options, or replace the URL holding them.
+    /// See `NestedUrls` for details.
+    /// 
+    /// # Examples
+    /// 
+    /// ```rust
+    /// use surtr::{surt, NestedUrlMode, NestedUrls, SurtrOptions};
+    /// 
+    /// let mut options = SurtrOptions::default();
+    /// options.set_nested_urls(Some(NestedUrls::default().with_mode(NestedUrlMode::Extract)));
+    /// 
+    /// assert_eq!(
+    ///     surt("http://example.com/redirect?u=http%3A%2F%2Fwww.example.org%2F", Some(options)).unwrap(),
+    ///     "org,example)/"
+    /// );
+    /// ```
+    pub fn set_nested_urls(&mut self, nested_urls: Option<NestedUrls>) {
+        self.nested_urls = nested_urls;
+    }
+
+}
+
+/// HostGranularity controls how much of the host is kept when the URL is compiled.
+/// 
+/// This allows one URL to produce keys for hierarchical aggregation. For `http://news.bbc.co.uk/`:
+/// 
+/// |        Granularity        |      SURT      |
+/// | :------------------------ | :------------- |
+/// | `Full`                    | `uk,co,bbc,news)/` |
+/// | `RegisteredDomain(1)`     | `uk,co,bbc,news)/` |
+/// | `RegisteredDomain(0)`     | `uk,co,bbc)/`  |
+/// | `Suffix`                  | `uk,co)/`      |
+/// 
+/// IP addresses and hosts with an unknown suffix have no registered domain, so they are always kept whole.
+/// 
+/// The granularity can be parsed from the strings `full`, `suffix`, `registered_domain` and 
+/// `registered_domain+N`, where N is the number of subdomain labels to keep.
+/// 
+/// # Examples
+/// 
+/// ```rust
+/// use surtr::{surt, HostGranularity, SurtrOptions};
+/// 
+/// let mut options = SurtrOptions::default();
+/// options.set_host_granularity("registered_domain+1".parse().unwrap());
+/// 
+/// assert_eq!(options.get_host_granularity(), HostGranularity::RegisteredDomain(1));
+/// assert_eq!(surt("http://a.news.bbc.co.uk/", Some(options)).unwrap(), "uk,co,bbc,news)/");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HostGranularity {
+    /// Keep the full host.
+    #[default]
+    Full,
+    /// Keep the registered domain and the given number of subdomain labels directly below it.
+    RegisteredDomain(usize),
+    /// Keep only the public suffix.
+    Suffix,
+}
+
+impl fmt::Display for HostGranularity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Full => write!(f, "full"),
+            Self::RegisteredDomain(0) => write!(f, "registered_domain"),
+            Self::RegisteredDomain(n) => write!(f, "registered_domain+{}", n),
+            Self::Suffix => write!(f, "suffix"),
+        }
+    }
+}
+
+impl FromStr for HostGranularity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "full" => Ok(Self::Full),
+            "suffix" => Ok(Self::Suffix),
+            "registered_domain" => Ok(Self::RegisteredDomain(0)),
+            _ => match s.strip_prefix("registered_domain+").map(|n| n.parse::<usize>()) {
+                Some(Ok(n)) => Ok(Self::RegisteredDomain(n)),
+                _ => Err(format!(
+                    "invalid host granularity '{}'. Expected full, suffix, registered_domain or registered_domain+N",
+                    s
+                )),
+            },
+        }
+    }
 }
 
 
@@ -152,6 +451,53 @@ mod tests {
     }
 
     #[test]
+    fn test_profiles() {
+        let url = "http://www.example.com/path/?b=2&a=1";
+        let expected = [
+            ("default", "com,example)/path?a=1&b=2"),
+            ("heritrix", "http://(com,example,www,)/path?a=1&b=2"),
+            ("canonical", "http://example.com/path?a=1&b=2"),
+        ];
+
+        for (profile, out) in expected {
+            let options = SurtrOptions::profile(profile).unwrap();
+            assert_eq!(crate::surt(url, Some(options)).unwrap(), out, "{}", profile);
+        }
+        assert_eq!(expected.len(), SurtrOptions::PROFILES.len());
+
+        assert!(SurtrOptions::profile("wayback").is_err());
+    }
+
+    #[test]
+    fn test_host_granularity() {
+        let mut options = SurtrOptions::default();
+        assert_eq!(options.get_host_granularity(), HostGranularity::Full);
+
+        options.set("public_suffix", true);
+        assert_eq!(options.get_host_granularity(), HostGranularity::RegisteredDomain(0));
+
+        options.set_host_granularity(HostGranularity::Suffix);
+        assert_eq!(options.get_host_granularity(), HostGranularity::Suffix);
+    }
+
+    #[test]
+    fn test_host_granularity_from_str() {
+        for granularity in [
+            HostGranularity::Full,
+            HostGranularity::Suffix,
+            HostGranularity::RegisteredDomain(0),
+            HostGranularity::RegisteredDomain(3),
+        ] {
+            assert_eq!(granularity.to_string().parse::<HostGranularity>(), Ok(granularity));
+        }
+
+        assert!("registered_domain+".parse::<HostGranularity>().is_err());
+        assert!("registered_domain+-1".parse::<HostGranularity>().is_err());
+        assert!("subdomain".parse::<HostGranularity>().is_err());
+    }
+
+    #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn test_into_iter() {
         let option: Vec<(String, bool)> = vec![
             ("public_suffix".to_string(), true),
@@ -1,3 +1,5 @@
+#![allow(clippy::unnecessary_unwrap)]
+
 use lazy_static::lazy_static;
 use regex::{Regex, RegexBuilder};
 
@@ -0,0 +1,545 @@
+use std::fmt;
+use std::str::FromStr;
+
+use lazy_static::lazy_static;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use regex::Regex;
+
+lazy_static! {
+    static ref RE_TIMESTAMP_MODIFIER: Regex = Regex::new(r#"^(\d{1,14})([a-z]{2}_)?$"#).expect("Failed to compile Timestamp Modifier Regex");
+    static ref RE_SCHEME: Regex = Regex::new(r#"^([a-zA-Z][a-zA-Z0-9\+\-\.]*):/*"#).expect("Failed to compile Scheme Regex");
+    static ref RE_ENCODED_SCHEME: Regex = Regex::new(r#"^[a-zA-Z][a-zA-Z0-9\+\-\.]*%3[aA]"#).expect("Failed to compile Encoded Scheme Regex");
+}
+
+// The characters left unencoded by JavaScript's encodeURIComponent, as used by replay front ends.
+pub(crate) const URI_COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'!')
+    .remove(b'~')
+    .remove(b'*')
+    .remove(b'\'')
+    .remove(b'(')
+    .remove(b')');
+
+// Replay URLs of archived replay URLs are unwrapped, up to this depth.
+const MAX_DEPTH: usize = 8;
+
+/// The parts of a replay URL found by a `ReplayMatcher`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayParts {
+    /// The timestamp of the capture. Between 1 and 14 digits.
+    pub timestamp: String,
+    /// The replay modifier, such as `id_` or `im_`, if any.
+    pub modifier: Option<String>,
+    /// The original URL, with its scheme restored and any percent encoding removed.
+    pub url: String,
+}
+
+/// Recognizes Wayback and pywb replay URLs, such as `http://web.archive.org/web/20090517140029/http://example.com/`
+/// or `https://archive.example.org/collection/2020id_/https://example.com/`.
+///
+/// A replay URL is an `http` or `https` URL on one of the replay hosts, with a path made of a
+/// replay path prefix, a timestamp of up to 14 digits with an optional modifier, and the original URL.
+/// Path prefixes are matched by segment, where `*` matches any one segment, such as a collection name.
+///
+/// By default, any host is accepted, with the path prefix `/*/`, and any modifier of two
+/// lowercase letters followed by `_`. The original URL must have a scheme, so paths which only
+/// look similar, such as `/downloads/2020/report.pdf`, are not unwrapped. Once the replay hosts
+/// are set, an original URL without a scheme is also accepted if it starts with a host containing a `.`.
+///
+/// Set with `SurtrOptions::set_replay_unwrap` to key the original URL, rather than the replay URL.
+///
+/// # Examples
+///
+/// ```rust
+/// use surtr::ReplayMatcher;
+///
+/// let matcher = ReplayMatcher::default().with_hosts(vec!["web.archive.org".to_string()]);
+/// let parts = matcher.unwrap_url("http://web.archive.org/web/20090517140029im_/http%3A//example.com/a.png").unwrap();
+///
+/// assert_eq!(parts.timestamp, "20090517140029");
+/// assert_eq!(parts.modifier.as_deref(), Some("im_"));
+/// assert_eq!(parts.url, "http://example.com/a.png");
+///
+/// assert!(matcher.unwrap_url("http://example.com/web/20090517140029/http://example.com/").is_none());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayMatcher {
+    hosts: Vec<String>,
+    path_prefixes: Vec<String>,
+    modifiers: Vec<String>,
+}
+
+impl Default for ReplayMatcher {
+    fn default() -> Self {
+        Self {
+            hosts: vec![],
+            path_prefixes: vec!["/*/".to_string()],
+            modifiers: vec![],
+        }
+    }
+}
+
+impl ReplayMatcher {
+    /// Set the hosts of the replay services. Subdomains of the hosts also match.
+    /// An empty list accepts any host, but only original URLs with a scheme.
+    pub fn with_hosts(mut self, hosts: Vec<String>) -> Self {
+        self.hosts = hosts.into_iter().map(|h| h.to_lowercase()).collect();
+        self
+    }
+
+    /// Set the path prefixes before the timestamp, such as `/web/` or `/*/`.
+    pub fn with_path_prefixes(mut self, path_prefixes: Vec<String>) -> Self {
+        self.path_prefixes = path_prefixes;
+        self
+    }
+
+    /// Set the modifiers accepted after the timestamp, such as `id_` and `im_`.
+    /// An empty list accepts any modifier of two lowercase letters followed by `_`.
+    pub fn with_modifiers(mut self, modifiers: Vec<String>) -> Self {
+        self.modifiers = modifiers;
+        self
+    }
+
+    /// Returns the hosts of the replay services.
+    pub fn hosts(&self) -> &[String] {
+        &self.hosts
+    }
+
+    /// Returns the path prefixes before the timestamp.
+    pub fn path_prefixes(&self) -> &[String] {
+        &self.path_prefixes
+    }
+
+    /// Returns the modifiers accepted after the timestamp.
+    pub fn modifiers(&self) -> &[String] {
+        &self.modifiers
+    }
+
+    /// Find the timestamp, modifier and original URL of a replay URL.
+    ///
+    /// Replay URLs of archived replay URLs are unwrapped to the innermost original URL,
+    /// and the innermost timestamp and modifier are returned.
+    ///
+    /// # Returns
+    ///
+    /// The parts of the replay URL, or None if the URL is not a replay URL.
+    pub fn unwrap_url(&self, url: &str) -> Option<ReplayParts> {
+        let mut parts = self.unwrap_once(url)?;
+
+        for _ in 1..MAX_DEPTH {
+            match self.unwrap_once(&parts.url) {
+                Some(inner) => parts = inner,
+                None => break,
+            }
+        }
+
+        Some(parts)
+    }
+
+    fn unwrap_once(&self, url: &str) -> Option<ReplayParts> {
+        let rest = strip_prefix_ignore_case(url, "http://").or_else(|| strip_prefix_ignore_case(url, "https://"))?;
+        let path_start = rest.find('/')?;
+        let (netloc, path) = rest.split_at(path_start);
+
+        let host = netloc.rsplit('@').next().unwrap_or(netloc);
+        let host = host.split(':').next().unwrap_or(host).to_lowercase();
+        if !self.hosts.is_empty()
+            && !self.hosts.iter().any(|h| host == *h || host.ends_with(&format!(".{}", h)))
+        {
+            return None;
+        }
+
+        self.path_prefixes.iter().find_map(|prefix| {
+            let rest = match_path_prefix(path, prefix)?;
+            let (segment, target) = rest.split_once('/')?;
+
+            let caps = RE_TIMESTAMP_MODIFIER.captures(segment)?;
+            let modifier = caps.get(2).map(|m| m.as_str().to_string());
+            if let Some(m) = &modifier
+                && !self.modifiers.is_empty()
+                && !self.modifiers.contains(m)
+            {
+                return None;
+            }
+
+            Some(ReplayParts {
+                timestamp: caps[1].to_string(),
+                modifier,
+                url: original_url(target, !self.hosts.is_empty())?,
+            })
+        })
+    }
+}
+
+/// A Wayback or pywb replay URL, such as `https://web.archive.org/web/20090517140029id_/http://example.com/`.
+///
+/// A replay URL is built from the address of the replay service, a collection, a timestamp, an
+/// optional modifier and the original URL: `<prefix><collection>/<timestamp><modifier>/<url>`.
+/// For the Wayback Machine, the collection is `web`.
+///
+/// Replay URLs are parsed from their string form. The original URL may be unencoded, percent
+/// encoded, as in `http%3A%2F%2Fexample.com%2F`, or have the slashes after its scheme collapsed,
+/// as in `http:/example.com/`. It is always stored unencoded, with `//` after the scheme.
+///
+/// # Examples
+///
+/// ```rust
+/// use surtr::ReplayUrl;
+///
+/// let replay = ReplayUrl::new("https://web.archive.org/", "web", "20090517140029", "http://example.com/?a=b")
+///     .unwrap()
+///     .with_modifier(Some("id_"))
+///     .unwrap();
+///
+/// assert_eq!(replay.to_string(), "https://web.archive.org/web/20090517140029id_/http://example.com/?a=b");
+/// assert_eq!(
+///     replay.clone().with_encoded_url(true).to_string(),
+///     "https://web.archive.org/web/20090517140029id_/http%3A%2F%2Fexample.com%2F%3Fa%3Db"
+/// );
+///
+/// let parsed: ReplayUrl = "http://localhost:8080/my-collection/2020/http%3A%2F%2Fexample.com%2F".parse().unwrap();
+///
+/// assert_eq!(parsed.prefix, "http://localhost:8080/");
+/// assert_eq!(parsed.collection, "my-collection");
+/// assert_eq!(parsed.timestamp, "2020");
+/// assert_eq!(parsed.modifier, None);
+/// assert_eq!(parsed.url, "http://example.com/");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayUrl {
+    /// The address of the replay service, up to the collection, such as `https://web.archive.org/`.
+    /// It may be a path alone, such as `/`, for relative replay URLs.
+    pub prefix: String,
+    /// The collection, such as `web`.
+    pub collection: String,
+    /// The timestamp of the capture. Between 1 and 14 digits.
+    pub timestamp: String,
+    /// The replay modifier, such as `id_` or `im_`, if any.
+    pub modifier: Option<String>,
+    /// The original URL, unencoded.
+    pub url: String,
+    /// Whether the original URL is percent encoded when the replay URL is written.
+    /// Parsed replay URLs keep whether their original URL was fully encoded.
+    pub encode_url: bool,
+}
+
+impl ReplayUrl {
+    /// Create a replay URL.
+    ///
+    /// # Arguments
+    ///
+    /// * `prefix` - The address of the replay service. A trailing `/` is added if it is missing.
+    /// * `collection` - The collection, such as `web`.
+    /// * `timestamp` - The timestamp of the capture.
+    /// * `url` - The original URL.
+    ///
+    /// # Errors
+    ///
+    /// An error message if the collection is empty or contains a `/`, or the timestamp is not between 1 and 14 digits.
+    pub fn new(prefix: &str, collection: &str, timestamp: &str, url: &str) -> Result<Self, String> {
+        if collection.is_empty() || collection.contains('/') {
+            return Err(format!("invalid replay collection '{}'. Expected a single path segment", collection));
+        }
+        if timestamp.is_empty() || timestamp.len() > 14 || !timestamp.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(format!("invalid replay timestamp '{}'. Expected up to 14 digits", timestamp));
+        }
+
+        let mut prefix = prefix.to_string();
+        if !prefix.ends_with('/') {
+            prefix.push('/');
+        }
+
+        Ok(Self {
+            prefix,
+            collection: collection.to_string(),
+            timestamp: timestamp.to_string(),
+            modifier: None,
+            url: url.to_string(),
+            encode_url: false,
+        })
+    }
+
+    /// Set the replay modifier, such as `id_`.
+    ///
+    /// # Errors
+    ///
+    /// An error message if the modifier is not two lowercase letters followed by `_`.
+    pub fn with_modifier(mut self, modifier: Option<&str>) -> Result<Self, String> {
+        if let Some(m) = modifier
+            && !RE_TIMESTAMP_MODIFIER.is_match(&format!("0{}", m))
+        {
+            return Err(format!("invalid replay modifier '{}'. Expected two lowercase letters followed by _, such as id_", m));
+        }
+
+        self.modifier = modifier.map(str::to_string);
+        Ok(self)
+    }
+
+    /// Set whether the original URL is percent encoded when the replay URL is written.
+    pub fn with_encoded_url(mut self, encode_url: bool) -> Self {
+        self.encode_url = encode_url;
+        self
+    }
+}
+
+impl fmt::Display for ReplayUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}/{}{}/",
+            self.prefix,
+            self.collection,
+            self.timestamp,
+            self.modifier.as_deref().unwrap_or("")
+        )?;
+
+        match self.encode_url {
+            true => write!(f, "{}", utf8_percent_encode(&self.url, URI_COMPONENT)),
+            false => write!(f, "{}", self.url),
+        }
+    }
+}
+
+impl FromStr for ReplayUrl {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let invalid = || format!("invalid replay URL '{}'. Expected <prefix><collection>/<timestamp><modifier>/<url>", s);
+
+        // The prefix is the scheme and host, or nothing for relative replay URLs.
+        let path_start = match s.find("://") {
+            Some(i) if s[..i].bytes().all(|b| b.is_ascii_alphanumeric()) => s[i + 3..].find('/').map(|j| i + 3 + j),
+            _ => s.starts_with('/').then_some(0),
+        }
+        .ok_or_else(invalid)?;
+
+        // The collection is the segment before the first timestamp which is followed by a URL.
+        let mut segment_start = path_start + 1;
+        let mut collection: Option<(usize, &str)> = None;
+        while let Some(len) = s[segment_start..].find('/') {
+            let segment = &s[segment_start..segment_start + len];
+            let rest = &s[segment_start + len + 1..];
+
+            if let Some((collection_start, collection)) = collection
+                && let Some(caps) = RE_TIMESTAMP_MODIFIER.captures(segment)
+                && let Some(url) = original_url(rest, true)
+            {
+                return Ok(Self {
+                    prefix: s[..collection_start].to_string(),
+                    collection: collection.to_string(),
+                    timestamp: caps[1].to_string(),
+                    modifier: caps.get(2).map(|m| m.as_str().to_string()),
+                    url,
+                    encode_url: !rest.contains('/'),
+                });
+            }
+
+            collection = match segment.is_empty() {
+                true => None,
+                false => Some((segment_start, segment)),
+            };
+            segment_start += len + 1;
+        }
+
+        Err(invalid())
+    }
+}
+
+fn strip_prefix_ignore_case<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    match s.get(..prefix.len()) {
+        Some(head) if head.eq_ignore_ascii_case(prefix) => Some(&s[prefix.len()..]),
+        _ => None,
+    }
+}
+
+// Matches the path against a prefix such as `/*/` segment by segment, and returns the rest of the path.
+fn match_path_prefix<'a>(path: &'a str, prefix: &str) -> Option<&'a str> {
+    let mut rest = path.strip_prefix('/')?;
+
+    for segment in prefix.trim_matches('/').split('/').filter(|s| !s.is_empty()) {
+        let (head, tail) = rest.split_once('/')?;
+        if head.is_empty() || (segment != "*" && head != segment) {
+            return None;
+        }
+        rest = tail;
+    }
+
+    Some(rest)
+}
+
+// Restores the original URL embedded in a replay URL. URLs without a scheme are only accepted with `schemeless`.
+fn original_url(target: &str, schemeless: bool) -> Option<String> {
+    let decoded;
+    let mut target = target;
+
+    // `http%3A%2F%2Fexample.com%2F` or `http%3A//example.com/`
+    if RE_ENCODED_SCHEME.is_match(target) {
+        decoded = percent_decode_str(target).decode_utf8().ok()?.into_owned();
+        target = &decoded;
+    }
+
+    // Slashes after the scheme are often collapsed, as in `http:/example.com/`.
+    if let Some(caps) = RE_SCHEME.captures(target) {
+        let rest = &target[caps[0].len()..];
+        if rest.is_empty() {
+            return None;
+        }
+        return Some(format!("{}://{}", &caps[1], rest));
+    }
+
+    let host = target.split(['/', '?', '#']).next().unwrap_or("");
+    match schemeless && host.contains('.') && !host.starts_with('.') {
+        true => Some(target.to_string()),
+        false => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parts(timestamp: &str, modifier: Option<&str>, url: &str) -> Option<ReplayParts> {
+        Some(ReplayParts {
+            timestamp: timestamp.to_string(),
+            modifier: modifier.map(str::to_string),
+            url: url.to_string(),
+        })
+    }
+
+    #[test]
+    fn test_unwrap_url() {
+        let matcher = ReplayMatcher::default();
+
+        assert_eq!(
+            matcher.unwrap_url("http://web.archive.org/web/20090517140029/http://example.com/"),
+            parts("20090517140029", None, "http://example.com/")
+        );
+        assert_eq!(
+            matcher.unwrap_url("https://pywb.example.org/my-collection/2020id_/https://example.com/page?a=b#top"),
+            parts("2020", Some("id_"), "https://example.com/page?a=b#top")
+        );
+        assert_eq!(
+            matcher.unwrap_url("http://web.archive.org/web/20090517140029/http:/example.com/"),
+            parts("20090517140029", None, "http://example.com/")
+        );
+        assert_eq!(
+            matcher.unwrap_url("http://web.archive.org/web/20090517140029/http%3A%2F%2Fexample.com%2F%3Fa%3Db"),
+            parts("20090517140029", None, "http://example.com/?a=b")
+        );
+
+        // Archived replay URLs are unwrapped to the innermost URL.
+        assert_eq!(
+            matcher.unwrap_url("http://web.archive.org/web/2015/http://web.archive.org/web/2009/http://example.com/"),
+            parts("2009", None, "http://example.com/")
+        );
+
+        assert!(matcher.unwrap_url("http://example.com/").is_none());
+        assert!(matcher.unwrap_url("http://example.com/blog/2020/post").is_none());
+        assert!(matcher.unwrap_url("http://example.com/blog/2020/").is_none());
+        assert!(matcher.unwrap_url("http://example.com/blog/2020abc/http://example.org/").is_none());
+        assert!(matcher.unwrap_url("http://example.com/blog/123456789012345/http://example.org/").is_none());
+        assert!(matcher.unwrap_url("http://example.com/web/2020/http:").is_none());
+        assert!(matcher.unwrap_url("ftp://example.com/web/2020/http://example.org/").is_none());
+
+        // Without replay hosts, ordinary paths ending in a file name are not replay URLs.
+        assert!(matcher.unwrap_url("http://example.com/downloads/2020/report.pdf").is_none());
+        assert!(matcher.unwrap_url("http://example.com/news/2021/index.html").is_none());
+        assert!(matcher.unwrap_url("http://web.archive.org/web/20090517140029/example.com/page").is_none());
+
+        // On a replay host, the original URL may omit its scheme.
+        let matcher = ReplayMatcher::default().with_hosts(vec!["web.archive.org".to_string()]);
+        assert_eq!(
+            matcher.unwrap_url("http://web.archive.org/web/20090517140029/example.com/page"),
+            parts("20090517140029", None, "example.com/page")
+        );
+        assert!(matcher.unwrap_url("http://web.archive.org/downloads/2020/report").is_none());
+        assert!(matcher.unwrap_url("http://example.com/downloads/2020/report.pdf").is_none());
+    }
+
+    #[test]
+    fn test_replay_url() {
+        let replay = ReplayUrl::new("https://web.archive.org", "web", "20200101000000", "https://example.com/a b?q=1&r=2#top").unwrap();
+        assert_eq!(replay.prefix, "https://web.archive.org/");
+        assert_eq!(replay.to_string(), "https://web.archive.org/web/20200101000000/https://example.com/a b?q=1&r=2#top");
+
+        let encoded = replay.clone().with_encoded_url(true);
+        assert_eq!(
+            encoded.to_string(),
+            "https://web.archive.org/web/20200101000000/https%3A%2F%2Fexample.com%2Fa%20b%3Fq%3D1%26r%3D2%23top"
+        );
+        assert_eq!(encoded.to_string().parse::<ReplayUrl>().unwrap(), encoded);
+        assert_eq!(replay.to_string().parse::<ReplayUrl>(), Ok(replay));
+
+        assert!(ReplayUrl::new("/", "", "2020", "http://example.com/").is_err());
+        assert!(ReplayUrl::new("/", "a/b", "2020", "http://example.com/").is_err());
+        assert!(ReplayUrl::new("/", "web", "", "http://example.com/").is_err());
+        assert!(ReplayUrl::new("/", "web", "202001010000001", "http://example.com/").is_err());
+        assert!(ReplayUrl::new("/", "web", "2020-01", "http://example.com/").is_err());
+        assert!(ReplayUrl::new("/", "web", "2020", "http://example.com/").unwrap().with_modifier(Some("id")).is_err());
+        assert!(ReplayUrl::new("/", "web", "2020", "http://example.com/").unwrap().with_modifier(Some("ID_")).is_err());
+    }
+
+    #[test]
+    fn test_parse_replay_url() {
+        let parse = |s: &str| s.parse::<ReplayUrl>().unwrap();
+
+        let replay = parse("http://localhost:8080/pywb/my-coll/2020im_/http:/example.com/a.png");
+        assert_eq!(replay.prefix, "http://localhost:8080/pywb/");
+        assert_eq!(replay.collection, "my-coll");
+        assert_eq!(replay.timestamp, "2020");
+        assert_eq!(replay.modifier.as_deref(), Some("im_"));
+        assert_eq!(replay.url, "http://example.com/a.png");
+        assert!(!replay.encode_url);
+        assert_eq!(replay.to_string(), "http://localhost:8080/pywb/my-coll/2020im_/http://example.com/a.png");
+
+        let replay = parse("/web/20090517140029/http%3A//example.com/?a=b");
+        assert_eq!(replay.prefix, "/");
+        assert_eq!(replay.url, "http://example.com/?a=b");
+
+        let replay = parse("https://web.archive.org/web/20090517140029/example.com/");
+        assert_eq!(replay.url, "example.com/");
+
+        // The first timestamp followed by a URL ends the prefix.
+        let replay = parse("https://archive.example.org/2019/web/2020/http://example.com/2021/http://example.org/");
+        assert_eq!(replay.prefix, "https://archive.example.org/2019/");
+        assert_eq!(replay.url, "http://example.com/2021/http://example.org/");
+
+        for invalid in [
+            "http://example.com/",
+            "http://example.com/web/2020/",
+            "http://example.com/2020/http://example.org/",
+            "http://example.com//2020/http://example.org/",
+            "web/2020/http://example.com/",
+            "http://example.com/web/now/http://example.org/",
+        ] {
+            assert!(invalid.parse::<ReplayUrl>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_matcher_config() {
+        let matcher = ReplayMatcher::default()
+            .with_hosts(vec!["Archive.org".to_string()])
+            .with_path_prefixes(vec!["/wayback/web/".to_string(), "/*/".to_string()])
+            .with_modifiers(vec!["id_".to_string()]);
+
+        assert_eq!(
+            matcher.unwrap_url("http://web.archive.org:80/wayback/web/2020id_/http://example.com/"),
+            parts("2020", Some("id_"), "http://example.com/")
+        );
+        assert_eq!(
+            matcher.unwrap_url("http://archive.org/web/2020/http://example.com/"),
+            parts("2020", None, "http://example.com/")
+        );
+        assert!(matcher.unwrap_url("http://web.archive.org/web/2020im_/http://example.com/").is_none());
+        assert!(matcher.unwrap_url("http://notarchive.org/web/2020/http://example.com/").is_none());
+        assert!(matcher.unwrap_url("http://archive.org/2020/http://example.com/").is_none());
+
+        assert_eq!(matcher.hosts(), ["archive.org"]);
+        assert_eq!(matcher.modifiers(), ["id_"]);
+    }
+}
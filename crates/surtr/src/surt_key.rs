@@ -0,0 +1,261 @@
+use std::fmt;
+
+/// A SURT, as produced by `surtr::surt_key()`.
+///
+/// SurtKey wraps the SURT string and adds helpers which understand the `,` and `)` delimiters
+/// of the host portion, so comparisons such as "is `com,example)` a prefix of `com,examples)`"
+/// are answered correctly.
+///
+/// Ordering is byte-wise, which is consistent with CDX files sorted with `LC_ALL=C sort`.
+///
+/// Keys produced with `with_scheme=true` (`http://(com,example)/`) are supported. SURTs
+/// without a host, such as `dns:archive.org` or `filedesc:foo.arc.gz`, have no host, path or query.
+///
+/// # Examples
+///
+/// ```rust
+/// use surtr::SurtKey;
+///
+/// let key = SurtKey::from("com,example,www)/path/page?a=b");
+///
+/// assert_eq!(key.host(), Some("com,example,www"));
+/// assert_eq!(key.path(), Some("/path/page"));
+/// assert_eq!(key.query(), Some("a=b"));
+///
+/// assert!(SurtKey::from("com,example").is_prefix_of(&key));
+/// assert!(!SurtKey::from("com,example").is_prefix_of(&SurtKey::from("com,examples)/")));
+///
+/// assert!(SurtKey::from("com,example)/").host_contains(&key));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct SurtKey(String);
+
+impl SurtKey {
+    /// Get the SURT as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Consume the SurtKey and return the SURT as a String.
+    pub fn into_string(self) -> String {
+        self.0
+    }
+
+    // Split the key into the scheme prefix, e.g. `http://(`, and the remainder.
+    fn split_scheme(&self) -> (Option<&str>, &str) {
+        match self.0.find("://(") {
+            Some(idx) => (Some(&self.0[..idx]), &self.0[idx + 4..]),
+            None => (None, &self.0),
+        }
+    }
+
+    /// The scheme of the SURT. Only present on keys built with `with_scheme=true`.
+    pub fn scheme(&self) -> Option<&str> {
+        self.split_scheme().0
+    }
+
+    /// The host portion of the SURT, without the closing `)`. e.g. `com,example,www`
+    ///
+    /// Ports and trailing commas are included as they appear in the SURT.
+    /// A key containing only a host prefix, such as `com,example`, returns the whole key.
+    pub fn host(&self) -> Option<&str> {
+        let (scheme, rest) = self.split_scheme();
+
+        match rest.find(')') {
+            Some(idx) => Some(&rest[..idx]),
+            None if scheme.is_some() || !rest.contains(':') => Some(rest),
+            None => None,
+        }
+    }
+
+    // The portion of the SURT after the closing `)` of the host.
+    fn after_host(&self) -> Option<&str> {
+        let (_, rest) = self.split_scheme();
+
+        rest.find(')').map(|idx| &rest[idx + 1..])
+    }
+
+    /// The path portion of the SURT, without the query or hash. e.g. `/path/page`
+    pub fn path(&self) -> Option<&str> {
+        let after = self.after_host()?;
+        let end = after.find(['?', '#']).unwrap_or(after.len());
+
+        Some(&after[..end])
+    }
+
+    /// The query portion of the SURT, without the leading `?`. e.g. `a=b`
+    pub fn query(&self) -> Option<&str> {
+        let after = self.after_host()?;
+        let start = after.find('?')? + 1;
+        let end = after[start..].find('#').map(|i| start + i).unwrap_or(after.len());
+
+        Some(&after[start..end])
+    }
+
+    // The host labels, without the port or a trailing comma.
+    fn host_labels(&self) -> Option<&str> {
+        let host = self.host()?;
+        let host = host.split(':').next().unwrap_or(host);
+
+        Some(host.trim_end_matches(','))
+    }
+
+    /// Check whether this key is a prefix of another key, respecting host boundaries.
+    ///
+    /// Within the host, the prefix must end on a label boundary. `com,example` is a prefix of
+    /// `com,example)/` and `com,example,www)/`, but not of `com,examples)/`. Once the prefix
+    /// includes the closing `)`, it is compared as a plain string prefix, as CDX prefix queries do.
+    pub fn is_prefix_of(&self, other: &SurtKey) -> bool {
+        if !other.0.starts_with(&self.0) {
+            return false;
+        }
+
+        // Prefixes that close the host, or end on a delimiter, need no further checks.
+        if self.0.contains(')') || self.0.ends_with([',', '(']) || self.0.is_empty() {
+            return true;
+        }
+
+        matches!(other.0[self.0.len()..].chars().next(), None | Some(',' | ')' | ':'))
+    }
+
+    /// Check whether the host of another key is equal to, or a subdomain of, the host of this key.
+    ///
+    /// Ports and trailing commas are ignored. Schemes must match if both keys have one.
+    pub fn host_contains(&self, other: &SurtKey) -> bool {
+        if let (Some(a), Some(b)) = (self.scheme(), other.scheme())
+            && a != b
+        {
+            return false;
+        }
+
+        match (self.host_labels(), other.host_labels()) {
+            (Some(host), Some(other_host)) => {
+                other_host == host
+                    || (other_host.starts_with(host) && other_host[host.len()..].starts_with(','))
+            }
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for SurtKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<String> for SurtKey {
+    fn from(s: String) -> Self {
+        Self(s)
+    }
+}
+
+impl From<&str> for SurtKey {
+    fn from(s: &str) -> Self {
+        Self(s.to_string())
+    }
+}
+
+impl From<SurtKey> for String {
+    fn from(key: SurtKey) -> Self {
+        key.0
+    }
+}
+
+impl AsRef<str> for SurtKey {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_surt_key_parts() {
+        let key = SurtKey::from("com,example)/path/page?a=b&c=d");
+        assert_eq!(key.scheme(), None);
+        assert_eq!(key.host(), Some("com,example"));
+        assert_eq!(key.path(), Some("/path/page"));
+        assert_eq!(key.query(), Some("a=b&c=d"));
+
+        let key = SurtKey::from("http://(com,example,www,)/?a=b#frag");
+        assert_eq!(key.scheme(), Some("http"));
+        assert_eq!(key.host(), Some("com,example,www,"));
+        assert_eq!(key.path(), Some("/"));
+        assert_eq!(key.query(), Some("a=b"));
+
+        let key = SurtKey::from("com,example:8080)");
+        assert_eq!(key.host(), Some("com,example:8080"));
+        assert_eq!(key.path(), Some(""));
+        assert_eq!(key.query(), None);
+
+        let key = SurtKey::from("com,example");
+        assert_eq!(key.host(), Some("com,example"));
+        assert_eq!(key.path(), None);
+
+        let key = SurtKey::from("dns:archive.org");
+        assert_eq!(key.host(), None);
+        assert_eq!(key.path(), None);
+        assert_eq!(key.query(), None);
+    }
+
+    #[test]
+    fn test_surt_key_ordering() {
+        let mut keys: Vec<SurtKey> = vec![
+            "com,example,www)/".into(),
+            "com,example)/z".into(),
+            "com,example-a)/".into(),
+            "com,examples)/".into(),
+            "com,example)/".into(),
+            "com,example)/A".into(),
+        ];
+        keys.sort();
+
+        let sorted: Vec<&str> = keys.iter().map(|k| k.as_str()).collect();
+        assert_eq!(
+            sorted,
+            vec![
+                "com,example)/",
+                "com,example)/A",
+                "com,example)/z",
+                "com,example,www)/",
+                "com,example-a)/",
+                "com,examples)/",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_surt_key_is_prefix_of() {
+        let key = SurtKey::from("com,example,www)/path");
+
+        assert!(SurtKey::from("com").is_prefix_of(&key));
+        assert!(SurtKey::from("com,").is_prefix_of(&key));
+        assert!(SurtKey::from("com,example").is_prefix_of(&key));
+        assert!(SurtKey::from("com,example,www)/pa").is_prefix_of(&key));
+        assert!(SurtKey::from("com,example").is_prefix_of(&SurtKey::from("com,example:8080)/")));
+        assert!(key.is_prefix_of(&key));
+
+        assert!(!SurtKey::from("com,exam").is_prefix_of(&key));
+        assert!(!SurtKey::from("com,example").is_prefix_of(&SurtKey::from("com,examples)/")));
+        assert!(!SurtKey::from("com,example)/").is_prefix_of(&key));
+    }
+
+    #[test]
+    fn test_surt_key_host_contains() {
+        let domain = SurtKey::from("com,example)/");
+
+        assert!(domain.host_contains(&SurtKey::from("com,example)/path")));
+        assert!(domain.host_contains(&SurtKey::from("com,example,www)/")));
+        assert!(domain.host_contains(&SurtKey::from("com,example,)/")));
+        assert!(domain.host_contains(&SurtKey::from("com,example:8080)/")));
+        assert!(!domain.host_contains(&SurtKey::from("com,examples)/")));
+        assert!(!domain.host_contains(&SurtKey::from("com)/")));
+        assert!(!domain.host_contains(&SurtKey::from("dns:example.com")));
+
+        assert!(SurtKey::from("http://(com,example)/").host_contains(&SurtKey::from("http://(com,example,www)/")));
+        assert!(!SurtKey::from("http://(com,example)/").host_contains(&SurtKey::from("https://(com,example)/")));
+    }
+}
@@ -0,0 +1,887 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::io::{self, BufRead};
+use std::ops::Range;
+use std::str::FromStr;
+
+use crate::{error::SurtrError, options::SurtrOptions, surt_with};
+
+/// The record formats supported by `FieldTransformer`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordFormat {
+    /// Tab separated values. Fields are not quoted.
+    Tsv,
+    /// Comma separated values, quoted as described in RFC 4180.
+    Csv,
+    /// One JSON value per line. The field is selected with a JSON pointer.
+    JsonLines,
+}
+
+impl fmt::Display for RecordFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::Tsv => "tsv",
+            Self::Csv => "csv",
+            Self::JsonLines => "jsonl",
+        };
+
+        write!(f, "{}", s)
+    }
+}
+
+impl FromStr for RecordFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tsv" => Ok(Self::Tsv),
+            "csv" => Ok(Self::Csv),
+            "jsonl" => Ok(Self::JsonLines),
+            _ => Err(format!("invalid record format '{}'. Expected tsv, csv or jsonl", s)),
+        }
+    }
+}
+
+/// The field holding the URL in each record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldSelector {
+    /// A zero based column index. TSV and CSV only.
+    Index(usize),
+    /// A column name, looked up in the header row. TSV and CSV only.
+    Name(String),
+    /// A JSON pointer, as described in RFC 6901. e.g. `/request/url`. JSON Lines only.
+    Pointer(String),
+}
+
+/// What to do with the SURT of each record.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum FieldMode {
+    /// Replace the URL with its SURT.
+    #[default]
+    Replace,
+    /// Keep the URL, and add the SURT as a new field with the given name.
+    ///
+    /// For TSV and CSV the SURT is added as the last column, and the name is added to the header row.
+    /// For JSON Lines the SURT is added to the object containing the URL, replacing any existing
+    /// member with the same name.
+    Append(String),
+}
+
+/// The errors produced while transforming records.
+///
+/// Every error except `Io` refers to a single record. The record is included, so callers can
+/// choose to skip it, write it unchanged, or set a placeholder with `FieldTransformer::set_field`.
+#[derive(Debug)]
+pub enum TransformError {
+    /// Reading the input failed.
+    Io(io::Error),
+    /// The record does not contain the selected field, or the field is not a string.
+    Field {
+        line: usize,
+        record: Vec<u8>,
+        message: String,
+    },
+    /// The URL in the record could not be converted into a SURT.
+    Surt {
+        line: usize,
+        record: Vec<u8>,
+        error: SurtrError,
+    },
+}
+
+impl fmt::Display for TransformError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "IoError: {}", e),
+            Self::Field { line, message, .. } => write!(f, "line {}: FieldError: {}", line, message),
+            Self::Surt { line, error, .. } => write!(f, "line {}: {}", line, error),
+        }
+    }
+}
+
+impl From<io::Error> for TransformError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// Replace or append a SURT field in TSV, CSV or JSON Lines records.
+///
+/// Only the selected field is rewritten. All other bytes of the record, including quoting,
+/// whitespace and line endings, are kept exactly as they were read.
+///
+/// Empty URLs are converted into `-`, as IA's Python `surt` does.
+///
+/// # Examples
+///
+/// ```rust
+/// use surtr::{FieldMode, FieldSelector, FieldTransformer, RecordFormat};
+///
+/// let input = "status,url\n200,http://www.example.com/\n";
+///
+/// let transformer = FieldTransformer::new(RecordFormat::Csv, FieldSelector::Name("url".to_string()))
+///     .with_header(true)
+///     .with_mode(FieldMode::Append("surt".to_string()));
+///
+/// let output: Vec<u8> = transformer
+///     .records(input.as_bytes())
+///     .flat_map(|r| r.unwrap())
+///     .collect();
+///
+/// assert_eq!(
+///     String::from_utf8(output).unwrap(),
+///     "status,url,surt\n200,http://www.example.com/,\"com,example)/\"\n"
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct FieldTransformer {
+    format: RecordFormat,
+    field: FieldSelector,
+    mode: FieldMode,
+    header: bool,
+    options: SurtrOptions,
+}
+
+impl FieldTransformer {
+    /// Create a transformer which replaces the selected field with its SURT.
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - The format of the records.
+    /// * `field` - The field holding the URL.
+    pub fn new(format: RecordFormat, field: FieldSelector) -> Self {
+        Self {
+            format,
+            field,
+            mode: FieldMode::default(),
+            header: false,
+            options: SurtrOptions::default().with_surt_defaults(),
+        }
+    }
+
+    /// Set whether to replace the URL, or append the SURT as a new field.
+    pub fn with_mode(mut self, mode: FieldMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Set whether the first record is a header row. Required to select TSV and CSV fields by name.
+    pub fn with_header(mut self, header: bool) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Set the options used to build each SURT.
+    pub fn with_options(mut self, options: SurtrOptions) -> Self {
+        self.options = options.with_surt_defaults();
+        self
+    }
+
+    /// Read records from `input` and yield each transformed record, including its line ending.
+    ///
+    /// CSV records with quoted fields spanning several lines are read as a single record.
+    pub fn records<R: BufRead>(self, input: R) -> Records<R> {
+        Records {
+            transformer: self,
+            input,
+            line: 0,
+            column: None,
+            header_done: false,
+            ending: Vec::new(),
+        }
+    }
+
+    /// Convert a single record, without its line ending.
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - The record to convert.
+    /// * `column` - The resolved column index for TSV and CSV records. Ignored for JSON Lines.
+    /// * `line` - The line number of the record, used in errors.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the converted record.
+    ///
+    /// # Errors
+    ///
+    /// `TransformError::Field` - If the record does not contain the field.
+    /// `TransformError::Surt` - If the URL cannot be converted.
+    pub fn transform_record(&self, record: &[u8], column: usize, line: usize) -> Result<Vec<u8>, TransformError> {
+        let value = self.get_field(record, column, line)?;
+
+        let key = match value.trim() {
+            "" => "-".to_string(),
+            url => surt_with(url, &self.options).map_err(|error| TransformError::Surt {
+                line,
+                record: record.to_vec(),
+                error,
+            })?,
+        };
+
+        self.set_field(record, column, line, &key)
+    }
+
+    /// Get the URL from a single record, without its line ending.
+    ///
+    /// # Errors
+    ///
+    /// `TransformError::Field` - If the record does not contain the field.
+    pub fn get_field<'a>(&self, record: &'a [u8], column: usize, line: usize) -> Result<Cow<'a, str>, TransformError> {
+        let field_err = |message: String| TransformError::Field {
+            line,
+            record: record.to_vec(),
+            message,
+        };
+
+        match self.format {
+            RecordFormat::Tsv | RecordFormat::Csv => {
+                let spans = split_fields(record, self.format);
+                let span = spans
+                    .get(column)
+                    .ok_or_else(|| field_err(format!("record has no column {}", column)))?;
+
+                Ok(unquote_field(&record[span.clone()], self.format))
+            }
+            RecordFormat::JsonLines => {
+                let target = json_target(record, &self.pointer()).map_err(field_err)?;
+
+                decode_json_string(&record[target.value]).map(Cow::Owned).map_err(field_err)
+            }
+        }
+    }
+
+    /// Write `value` to the SURT field of a single record, without its line ending.
+    ///
+    /// In `Replace` mode this replaces the URL. In `Append` mode the value is added as a new field.
+    ///
+    /// # Errors
+    ///
+    /// `TransformError::Field` - If the record does not contain the field.
+    pub fn set_field(&self, record: &[u8], column: usize, line: usize, value: &str) -> Result<Vec<u8>, TransformError> {
+        let field_err = |message: String| TransformError::Field {
+            line,
+            record: record.to_vec(),
+            message,
+        };
+
+        match self.format {
+            RecordFormat::Tsv | RecordFormat::Csv => {
+                let encoded = quote_field(value, self.format);
+
+                let mut out = Vec::with_capacity(record.len() + encoded.len() + 1);
+                match &self.mode {
+                    FieldMode::Replace => {
+                        let spans = split_fields(record, self.format);
+                        let span = spans
+                            .get(column)
+                            .ok_or_else(|| field_err(format!("record has no column {}", column)))?;
+
+                        out.extend_from_slice(&record[..span.start]);
+                        out.extend_from_slice(encoded.as_bytes());
+                        out.extend_from_slice(&record[span.end..]);
+                    }
+                    FieldMode::Append(_) => {
+                        out.extend_from_slice(record);
+                        out.push(delimiter(self.format));
+                        out.extend_from_slice(encoded.as_bytes());
+                    }
+                }
+
+                Ok(out)
+            }
+            RecordFormat::JsonLines => {
+                let target = json_target(record, &self.pointer()).map_err(field_err)?;
+                let encoded = encode_json_string(value);
+
+                let (replace, insert) = match &self.mode {
+                    FieldMode::Replace => (target.value, encoded),
+                    FieldMode::Append(name) => {
+                        let parent = target
+                            .parent
+                            .ok_or_else(|| field_err("appending requires the field to be in an object".to_string()))?;
+                        let members = object_members(record, parent.clone()).map_err(field_err)?;
+
+                        match members.iter().find(|(key, _)| key == name) {
+                            Some((_, existing)) => (existing.clone(), encoded),
+                            None => {
+                                let close = parent.end - 1;
+                                let sep = if members.is_empty() { "" } else { "," };
+                                (close..close, format!("{}{}:{}", sep, encode_json_string(name), encoded))
+                            }
+                        }
+                    }
+                };
+
+                let mut out = Vec::with_capacity(record.len() + insert.len());
+                out.extend_from_slice(&record[..replace.start]);
+                out.extend_from_slice(insert.as_bytes());
+                out.extend_from_slice(&record[replace.end..]);
+
+                Ok(out)
+            }
+        }
+    }
+
+    // Resolve the selected column against a header row, and add the appended column name.
+    fn transform_header(&self, record: &[u8], line: usize) -> Result<(usize, Vec<u8>), TransformError> {
+        let column = match &self.field {
+            FieldSelector::Name(name) => split_fields(record, self.format)
+                .into_iter()
+                .position(|span| unquote_field(&record[span], self.format) == name.as_str())
+                .ok_or_else(|| TransformError::Field {
+                    line,
+                    record: record.to_vec(),
+                    message: format!("header has no column named {}", name),
+                })?,
+            _ => self.column(record, line)?,
+        };
+
+        let out = match &self.mode {
+            FieldMode::Replace => record.to_vec(),
+            FieldMode::Append(name) => {
+                let mut out = record.to_vec();
+                out.push(delimiter(self.format));
+                out.extend_from_slice(quote_field(name, self.format).as_bytes());
+                out
+            }
+        };
+
+        Ok((column, out))
+    }
+
+    // The column index for TSV and CSV records without a header row.
+    fn column(&self, record: &[u8], line: usize) -> Result<usize, TransformError> {
+        match (&self.field, self.format) {
+            (_, RecordFormat::JsonLines) => Ok(0),
+            (FieldSelector::Index(idx), _) => Ok(*idx),
+            (field, format) => Err(TransformError::Field {
+                line,
+                record: record.to_vec(),
+                message: format!("{:?} cannot be used to select a {} field without a header", field, format),
+            }),
+        }
+    }
+
+    // The JSON pointer for JSON Lines records. Any other selector is treated as a top level member name.
+    fn pointer(&self) -> Cow<'_, str> {
+        match &self.field {
+            FieldSelector::Pointer(p) => Cow::Borrowed(p),
+            FieldSelector::Name(n) => Cow::Owned(format!("/{}", n.replace('~', "~0").replace('/', "~1"))),
+            FieldSelector::Index(i) => Cow::Owned(format!("/{}", i)),
+        }
+    }
+}
+
+/// An iterator over transformed records, created by `FieldTransformer::records`.
+pub struct Records<R> {
+    transformer: FieldTransformer,
+    input: R,
+    line: usize,
+    column: Option<usize>,
+    header_done: bool,
+    ending: Vec<u8>,
+}
+
+impl<R> Records<R> {
+    /// The transformer used for each record.
+    pub fn transformer(&self) -> &FieldTransformer {
+        &self.transformer
+    }
+
+    /// The resolved column index. None until the header row, if any, has been read.
+    pub fn column(&self) -> Option<usize> {
+        self.column
+    }
+
+    /// The line ending of the last record read, empty if it had none.
+    ///
+    /// Errors carry the record without its ending, so it can be written back as it was read.
+    pub fn line_ending(&self) -> &[u8] {
+        &self.ending
+    }
+}
+
+impl<R: BufRead> Records<R> {
+    // Read the next record, including its line ending. CSV records are read until all quotes are closed.
+    fn read_record(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let mut buf = Vec::new();
+
+        loop {
+            if self.input.read_until(b'\n', &mut buf)? == 0 {
+                return Ok(if buf.is_empty() { None } else { Some(buf) });
+            }
+            self.line += 1;
+
+            let quotes = buf.iter().filter(|b| **b == b'"').count();
+            if self.transformer.format != RecordFormat::Csv || quotes % 2 == 0 {
+                return Ok(Some(buf));
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for Records<R> {
+    type Item = Result<Vec<u8>, TransformError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let buf = match self.read_record() {
+            Ok(Some(b)) => b,
+            Ok(None) => return None,
+            Err(e) => return Some(Err(e.into())),
+        };
+
+        let (record, ending) = split_line_ending(&buf);
+        self.ending = ending.to_vec();
+        let line = self.line;
+
+        let result = if self.transformer.header && !self.header_done && self.transformer.format != RecordFormat::JsonLines {
+            self.header_done = true;
+            self.transformer.transform_header(record, line).map(|(column, out)| {
+                self.column = Some(column);
+                out
+            })
+        } else {
+            let column = match self.column {
+                Some(c) => Ok(c),
+                None => self.transformer.column(record, line),
+            };
+
+            column.and_then(|c| {
+                self.column = Some(c);
+                self.transformer.transform_record(record, c, line)
+            })
+        };
+
+        Some(result.map(|mut out| {
+            out.extend_from_slice(ending);
+            out
+        }))
+    }
+}
+
+// Split a line into the record and its `\n` or `\r\n` ending.
+pub(crate) fn split_line_ending(buf: &[u8]) -> (&[u8], &[u8]) {
+    let len = buf.len() - buf.iter().rev().take_while(|b| matches!(b, b'\n' | b'\r')).count();
+
+    buf.split_at(len)
+}
+
+fn delimiter(format: RecordFormat) -> u8 {
+    match format {
+        RecordFormat::Csv => b',',
+        _ => b'\t',
+    }
+}
+
+// Get the byte ranges of each field in a TSV or CSV record, including any quotes.
+fn split_fields(record: &[u8], format: RecordFormat) -> Vec<Range<usize>> {
+    let delim = delimiter(format);
+    let mut spans = vec![];
+    let mut start = 0;
+    let mut quoted = false;
+
+    for (i, b) in record.iter().enumerate() {
+        if format == RecordFormat::Csv && *b == b'"' {
+            quoted = !quoted;
+        } else if *b == delim && !quoted {
+            spans.push(start..i);
+            start = i + 1;
+        }
+    }
+    spans.push(start..record.len());
+
+    spans
+}
+
+// Remove the CSV quoting from a field. TSV fields are returned as they are.
+fn unquote_field(field: &[u8], format: RecordFormat) -> Cow<'_, str> {
+    let text = String::from_utf8_lossy(field);
+
+    if format != RecordFormat::Csv || !(text.len() >= 2 && text.starts_with('"') && text.ends_with('"')) {
+        return text;
+    }
+
+    Cow::Owned(text[1..text.len() - 1].replace("\"\"", "\""))
+}
+
+// Quote a CSV field if it contains a delimiter, quote or line break.
+fn quote_field(value: &str, format: RecordFormat) -> String {
+    if format == RecordFormat::Csv && value.contains([',', '"', '\r', '\n']) {
+        return format!("\"{}\"", value.replace('"', "\"\""));
+    }
+
+    value.to_string()
+}
+
+// Get the string referenced by a JSON pointer.
+pub(crate) fn json_string(record: &[u8], pointer: &str) -> Result<String, String> {
+    let target = json_target(record, pointer)?;
+
+    decode_json_string(&record[target.value])
+}
+
+// The location of a JSON pointer target, and the object containing it.
+struct JsonTarget {
+    value: Range<usize>,
+    parent: Option<Range<usize>>,
+}
+
+// Find the string value referenced by a JSON pointer.
+fn json_target(record: &[u8], pointer: &str) -> Result<JsonTarget, String> {
+    if !pointer.is_empty() && !pointer.starts_with('/') {
+        return Err(format!("invalid JSON pointer {}", pointer));
+    }
+
+    let start = skip_ws(record, 0);
+    let mut value = start..scan_value(record, start)?;
+    let mut parent = None;
+
+    for token in pointer.split('/').skip(1) {
+        let token = token.replace("~1", "/").replace("~0", "~");
+
+        let next = match record.get(value.start) {
+            Some(b'{') => object_members(record, value.clone())?
+                .into_iter()
+                .find(|(key, _)| *key == token)
+                .map(|(_, v)| v),
+            Some(b'[') => token
+                .parse::<usize>()
+                .ok()
+                .and_then(|idx| array_elements(record, value.clone()).ok()?.into_iter().nth(idx)),
+            _ => None,
+        };
+
+        let next = next.ok_or_else(|| format!("JSON pointer {} does not match the record", pointer))?;
+        parent = match record.get(value.start) {
+            Some(b'{') => Some(value),
+            _ => None,
+        };
+        value = next;
+    }
+
+    if record.get(value.start) != Some(&b'"') {
+        return Err(format!("JSON pointer {} does not reference a string", pointer));
+    }
+
+    Ok(JsonTarget { value, parent })
+}
+
+pub(crate) fn skip_ws(b: &[u8], mut i: usize) -> usize {
+    while i < b.len() && matches!(b[i], b' ' | b'\t' | b'\n' | b'\r') {
+        i += 1;
+    }
+
+    i
+}
+
+// Return the end of the JSON value starting at `i`.
+pub(crate) fn scan_value(b: &[u8], i: usize) -> Result<usize, String> {
+    match b.get(i) {
+        Some(b'{' | b'[') => container_end(b, i),
+        Some(b'"') => scan_string(b, i),
+        Some(_) => {
+            let end = b[i..]
+                .iter()
+                .position(|c| matches!(c, b',' | b']' | b'}' | b' ' | b'\t' | b'\n' | b'\r'))
+                .map(|p| i + p)
+                .unwrap_or(b.len());
+
+            match end > i {
+                true => Ok(end),
+                false => Err(format!("invalid JSON at byte {}", i)),
+            }
+        }
+        None => Err("unexpected end of JSON".to_string()),
+    }
+}
+
+// Return the end of the object or array starting at `i`.
+fn container_end(b: &[u8], i: usize) -> Result<usize, String> {
+    let close = if b[i] == b'{' { b'}' } else { b']' };
+    let mut pos = skip_ws(b, i + 1);
+
+    if b.get(pos) == Some(&close) {
+        return Ok(pos + 1);
+    }
+
+    loop {
+        if close == b'}' {
+            pos = skip_ws(b, scan_string(b, pos)?);
+            if b.get(pos) != Some(&b':') {
+                return Err(format!("expected ':' at byte {}", pos));
+            }
+            pos = skip_ws(b, pos + 1);
+        }
+
+        pos = skip_ws(b, scan_value(b, pos)?);
+        match b.get(pos) {
+            Some(b',') => pos = skip_ws(b, pos + 1),
+            Some(c) if *c == close => return Ok(pos + 1),
+            _ => return Err(format!("expected ',' or '{}' at byte {}", close as char, pos)),
+        }
+    }
+}
+
+// Return the end of the JSON string starting at `i`.
+fn scan_string(b: &[u8], i: usize) -> Result<usize, String> {
+    if b.get(i) != Some(&b'"') {
+        return Err(format!("expected a string at byte {}", i));
+    }
+
+    let mut pos = i + 1;
+    while pos < b.len() {
+        match b[pos] {
+            b'\\' => pos += 2,
+            b'"' => return Ok(pos + 1),
+            _ => pos += 1,
+        }
+    }
+
+    Err("unterminated JSON string".to_string())
+}
+
+// Get the decoded key and value range of each member of the object in `range`.
+pub(crate) fn object_members(b: &[u8], range: Range<usize>) -> Result<Vec<(String, Range<usize>)>, String> {
+    let mut members = vec![];
+    let mut pos = skip_ws(b, range.start + 1);
+
+    if b.get(pos) == Some(&b'}') {
+        return Ok(members);
+    }
+
+    loop {
+        let key_end = scan_string(b, pos)?;
+        let key = decode_json_string(&b[pos..key_end])?;
+
+        pos = skip_ws(b, key_end);
+        if b.get(pos) != Some(&b':') {
+            return Err(format!("expected ':' at byte {}", pos));
+        }
+
+        let start = skip_ws(b, pos + 1);
+        let end = scan_value(b, start)?;
+        members.push((key, start..end));
+
+        pos = skip_ws(b, end);
+        match b.get(pos) {
+            Some(b',') => pos = skip_ws(b, pos + 1),
+            Some(b'}') => return Ok(members),
+            _ => return Err(format!("expected ',' or '}}' at byte {}", pos)),
+        }
+    }
+}
+
+// Get the value range of each element of the array in `range`.
+fn array_elements(b: &[u8], range: Range<usize>) -> Result<Vec<Range<usize>>, String> {
+    let mut elements = vec![];
+    let mut pos = skip_ws(b, range.start + 1);
+
+    if b.get(pos) == Some(&b']') {
+        return Ok(elements);
+    }
+
+    loop {
+        let end = scan_value(b, pos)?;
+        elements.push(pos..end);
+
+        pos = skip_ws(b, end);
+        match b.get(pos) {
+            Some(b',') => pos = skip_ws(b, pos + 1),
+            Some(b']') => return Ok(elements),
+            _ => return Err(format!("expected ',' or ']' at byte {}", pos)),
+        }
+    }
+}
+
+// Decode a JSON string, including its quotes.
+pub(crate) fn decode_json_string(b: &[u8]) -> Result<String, String> {
+    let text = std::str::from_utf8(&b[1..b.len() - 1]).map_err(|e| e.to_string())?;
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('"') => out.push('"'),
+            Some('\\') => out.push('\\'),
+            Some('/') => out.push('/'),
+            Some('b') => out.push('\u{8}'),
+            Some('f') => out.push('\u{c}'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('t') => out.push('\t'),
+            Some('u') => {
+                let mut code = read_hex(&mut chars)?;
+
+                // Characters outside the BMP are encoded as a surrogate pair.
+                if (0xD800..0xDC00).contains(&code) {
+                    if chars.next() != Some('\\') || chars.next() != Some('u') {
+                        return Err("unpaired surrogate in JSON string".to_string());
+                    }
+                    let low = read_hex(&mut chars)?;
+                    if !(0xDC00..0xE000).contains(&low) {
+                        return Err("unpaired surrogate in JSON string".to_string());
+                    }
+                    code = 0x10000 + ((code - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF);
+                }
+
+                out.push(char::from_u32(code).ok_or("invalid unicode escape in JSON string")?);
+            }
+            _ => return Err("invalid escape in JSON string".to_string()),
+        }
+    }
+
+    Ok(out)
+}
+
+fn read_hex(chars: &mut std::str::Chars<'_>) -> Result<u32, String> {
+    let hex: String = chars.take(4).collect();
+
+    u32::from_str_radix(&hex, 16).map_err(|_| format!("invalid unicode escape \\u{}", hex))
+}
+
+// Encode a string as a JSON string, including its quotes.
+pub(crate) fn encode_json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(transformer: FieldTransformer, input: &str) -> Vec<Result<String, String>> {
+        transformer
+            .records(input.as_bytes())
+            .map(|r| r.map(|b| String::from_utf8(b).unwrap()).map_err(|e| e.to_string()))
+            .collect()
+    }
+
+    fn ok(transformer: FieldTransformer, input: &str) -> String {
+        run(transformer, input).into_iter().map(|r| r.unwrap()).collect()
+    }
+
+    #[test]
+    fn test_tsv() {
+        let t = FieldTransformer::new(RecordFormat::Tsv, FieldSelector::Index(1));
+        assert_eq!(
+            ok(t.clone(), "a\thttp://www.example.com/\tb\r\nc\thttp://archive.org/goo/?b&a\n"),
+            "a\tcom,example)/\tb\r\nc\torg,archive)/goo?a&b\n"
+        );
+
+        let t = FieldTransformer::new(RecordFormat::Tsv, FieldSelector::Name("url".to_string()))
+            .with_header(true)
+            .with_mode(FieldMode::Append("surt".to_string()));
+        assert_eq!(
+            ok(t, "url\tstatus\nhttp://www.example.com/\t200\n\t404"),
+            "url\tstatus\tsurt\nhttp://www.example.com/\t200\tcom,example)/\n\t404\t-"
+        );
+    }
+
+    #[test]
+    fn test_csv() {
+        let t = FieldTransformer::new(RecordFormat::Csv, FieldSelector::Index(0));
+        assert_eq!(
+            ok(t.clone(), "http://www.example.com/,\"a, b\",\"c\"\"d\"\n"),
+            "\"com,example)/\",\"a, b\",\"c\"\"d\"\n"
+        );
+        assert_eq!(
+            ok(t, "\"http://example.com/?a=1,2\",\"multi\nline\"\n"),
+            "\"com,example)/?a=1,2\",\"multi\nline\"\n"
+        );
+
+        let t = FieldTransformer::new(RecordFormat::Csv, FieldSelector::Name("url".to_string())).with_header(true);
+        assert_eq!(
+            ok(t, "id,\"url\"\n1,http://www.example.com/\n"),
+            "id,\"url\"\n1,\"com,example)/\"\n"
+        );
+    }
+
+    #[test]
+    fn test_json_lines() {
+        let t = FieldTransformer::new(RecordFormat::JsonLines, FieldSelector::Pointer("/request/url".to_string()));
+        assert_eq!(
+            ok(t.clone(), "{\"id\": 1, \"request\": {\"url\": \"http:\\/\\/www.example.com\\/\"}}  \n"),
+            "{\"id\": 1, \"request\": {\"url\": \"com,example)/\"}}  \n"
+        );
+
+        let t = t.with_mode(FieldMode::Append("surt".to_string()));
+        assert_eq!(
+            ok(t.clone(), "{\"request\":{\"url\":\"http://www.example.com/\"},\"x\":[1,2.5,null,true]}\n"),
+            "{\"request\":{\"url\":\"http://www.example.com/\",\"surt\":\"com,example)/\"},\"x\":[1,2.5,null,true]}\n"
+        );
+        assert_eq!(
+            ok(t, "{\"request\":{\"surt\":\"old\", \"url\":\"http://www.example.com/\"}}\n"),
+            "{\"request\":{\"surt\":\"com,example)/\", \"url\":\"http://www.example.com/\"}}\n"
+        );
+
+        let t = FieldTransformer::new(RecordFormat::JsonLines, FieldSelector::Pointer("/urls/1".to_string()));
+        assert_eq!(ok(t, "{\"urls\":[\"a\",\"http://a.b/c~\"]}"), "{\"urls\":[\"a\",\"b,a)/c~\"]}");
+
+        let t = FieldTransformer::new(RecordFormat::JsonLines, FieldSelector::Name("a/b".to_string()));
+        assert_eq!(ok(t, "{\"a/b\":\"http://example.com/\\u00e9\"}"), "{\"a/b\":\"com,example)/%c3%a9\"}");
+    }
+
+    #[test]
+    fn test_errors() {
+        let t = FieldTransformer::new(RecordFormat::Tsv, FieldSelector::Index(2));
+        let out = run(t, "a\tb\tc\na\tb\n");
+        assert!(out[0].is_ok());
+        assert_eq!(out[1], Err("line 2: FieldError: record has no column 2".to_string()));
+
+        let t = FieldTransformer::new(RecordFormat::JsonLines, FieldSelector::Pointer("/url".to_string()));
+        let out = run(t, "{\"url\":null}\n{\"url\":\"http://example.com/%ff\"}\n{\"url\": \n");
+        assert!(matches!(out[0], Err(ref e) if e.contains("does not reference a string")));
+        assert!(matches!(out[1], Err(ref e) if e.contains("CanonicalizerError")));
+        assert!(out[2].is_err());
+
+        let t = FieldTransformer::new(RecordFormat::JsonLines, FieldSelector::Pointer("/url".to_string()));
+        let out = run(t, "{\"url\":\"http://example.com/\\ud83d\\ude00\"}\n{\"url\":\"http://example.com/\\ud83d\\u0041\"}\n");
+        assert_eq!(out[0], Ok("{\"url\":\"com,example)/%f0%9f%98%80\"}\n".to_string()));
+        assert!(matches!(out[1], Err(ref e) if e.contains("unpaired surrogate")));
+
+        let t = FieldTransformer::new(RecordFormat::Csv, FieldSelector::Name("url".to_string())).with_header(true);
+        assert!(run(t, "id,link\n1,http://example.com/\n")[0].is_err());
+
+        let t = FieldTransformer::new(RecordFormat::Csv, FieldSelector::Name("url".to_string()));
+        let out = run(t, "http://example.com/\nhttp://example.org/\n");
+        assert!(matches!(out[0], Err(ref e) if e.starts_with("line 1: FieldError")));
+        assert!(matches!(out[1], Err(ref e) if e.starts_with("line 2: FieldError")));
+    }
+
+    #[test]
+    fn test_set_field_placeholder() {
+        let t = FieldTransformer::new(RecordFormat::Csv, FieldSelector::Index(1));
+
+        let mut records = t.records("1,http://example.com/%ff,x\n".as_bytes());
+
+        match records.next() {
+            Some(Err(TransformError::Surt { line, record, .. })) => {
+                assert_eq!(line, 1);
+                assert_eq!(records.column(), Some(1));
+                assert_eq!(records.transformer().set_field(&record, 1, line, "-").unwrap(), b"1,-,x");
+            }
+            other => panic!("expected a surt error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use crate::{
+    error::SurtrError,
+    key_range::{key_range, KeyRange, MatchType},
+    options::SurtrOptions,
+    surt_key,
+    surt_key::SurtKey,
+};
+
+/// A wildcard URL query, as accepted by pywb and the wayback machine, resolved to a SURT key range.
+///
+/// The supported patterns are:
+///
+/// |         Pattern             | Match Type |     Key Range Start     |
+/// | :-------------------------- | :--------- | :---------------------- |
+/// | `example.com/page`          | exact      | `com,example)/page`     |
+/// | `example.com/blog/*`        | prefix     | `com,example)/blog/`    |
+/// | `http://example.com/page?*` | prefix     | `com,example)/page?`    |
+/// | `*.example.com`             | domain     | `com,example)`          |
+///
+/// Wildcards anywhere else in the pattern are rejected, so every front end resolves a pattern the same way.
+/// The host of a `*.` pattern is matched as written, without removing `www.`, and cannot be followed by a path.
+///
+/// # Examples
+///
+/// ```rust
+/// use surtr::{MatchType, UrlQuery};
+///
+/// let query = UrlQuery::parse("example.com/blog/*", None).unwrap();
+///
+/// assert_eq!(query.match_type, MatchType::Prefix);
+/// assert_eq!(query.key.as_str(), "com,example)/blog");
+/// assert!(query.range.contains("com,example)/blog/post 20200101000000"));
+/// assert!(!query.range.contains("com,example)/blogroll 20200101000000"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UrlQuery {
+    /// The URL with any wildcards removed.
+    pub url: String,
+    /// The match type implied by the wildcards.
+    pub match_type: MatchType,
+    /// The SURT of the URL.
+    pub key: SurtKey,
+    /// The range of keys matched by the query.
+    pub range: KeyRange,
+}
+
+impl UrlQuery {
+    /// Parse a wildcard URL pattern into a match type and key range.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - The URL pattern. e.g. `*.example.com` or `example.com/blog/*`
+    /// * `options` - The options used to build the SURT.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the UrlQuery.
+    ///
+    /// # Errors
+    ///
+    /// `SurtrError::UrlParseError` - If the pattern contains an unsupported wildcard, or the URL cannot be parsed.
+    pub fn parse(pattern: &str, options: Option<SurtrOptions>) -> Result<Self, SurtrError> {
+        Self::parse_with_match_type(pattern, None, options)
+    }
+
+    /// Parse a URL pattern with an explicit match type, as given by a `matchType=` parameter.
+    ///
+    /// A pattern without wildcards is matched using `match_type`. If the pattern contains a wildcard
+    /// which implies a different match type, an error is returned rather than guessing.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - The URL pattern.
+    /// * `match_type` - The explicit match type. None uses the match type implied by the pattern.
+    /// * `options` - The options used to build the SURT.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the UrlQuery.
+    ///
+    /// # Errors
+    ///
+    /// `SurtrError::UrlParseError` - If the pattern contains an unsupported wildcard, the wildcard conflicts with
+    /// the explicit match type, or the URL cannot be parsed.
+    pub fn parse_with_match_type(
+        pattern: &str,
+        match_type: Option<MatchType>,
+        options: Option<SurtrOptions>,
+    ) -> Result<Self, SurtrError> {
+        let (url, implied) = strip_wildcards(pattern.trim())?;
+
+        let match_type = match (implied, match_type) {
+            (Some(i), Some(m)) if i != m => {
+                return Err(SurtrError::UrlParseError(format!(
+                    "pattern {} implies a {} match, but a {} match was requested",
+                    pattern, i, m
+                )));
+            }
+            (Some(i), _) => i,
+            (None, Some(m)) => m,
+            (None, None) => MatchType::Exact,
+        };
+
+        // A wildcard domain names its host exactly, so `*.www.example.com` must not be widened to
+        // `example.com` by host massaging.
+        let options = match implied {
+            Some(MatchType::Domain) => {
+                let mut opts = options.unwrap_or_default();
+                opts.set("host_massage", false);
+                Some(opts)
+            }
+            _ => options,
+        };
+
+        let key = surt_key(&url, options.clone())?;
+        let range = key_range(&url, match_type, options)?;
+
+        Ok(Self {
+            url,
+            match_type,
+            key,
+            range,
+        })
+    }
+}
+
+// Remove the wildcards from a URL pattern, and return the match type they imply.
+fn strip_wildcards(pattern: &str) -> Result<(String, Option<MatchType>), SurtrError> {
+    let (scheme, rest) = match pattern.find("://") {
+        Some(idx) => pattern.split_at(idx + 3),
+        None => ("", pattern),
+    };
+
+    let (rest, mut match_type) = match rest.strip_prefix("*.") {
+        Some(r) => (r, Some(MatchType::Domain)),
+        None => (rest, None),
+    };
+
+    // `*.example.com/*` is still a domain match, so the trailing wildcard is dropped.
+    let rest = match rest.strip_suffix('*') {
+        Some(r) => {
+            match_type.get_or_insert(MatchType::Prefix);
+            r
+        }
+        None => rest,
+    };
+
+    if rest.contains('*') {
+        return Err(SurtrError::UrlParseError(format!(
+            "unsupported wildcard in url pattern: {}",
+            pattern
+        )));
+    }
+    // A domain match covers every path, so a path after the host would be silently ignored.
+    if match_type == Some(MatchType::Domain)
+        && let Some((_, path)) = rest.split_once('/')
+        && !path.is_empty()
+    {
+        return Err(SurtrError::UrlParseError(format!(
+            "wildcard domain pattern cannot have a path: {}",
+            pattern
+        )));
+    }
+    if rest.is_empty() {
+        return Err(SurtrError::UrlParseError(format!(
+            "url pattern has no url: {}",
+            pattern
+        )));
+    }
+
+    Ok((format!("{}{}", scheme, rest), match_type))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url_query_exact() {
+        let query = UrlQuery::parse("example.com/page", None).unwrap();
+        assert_eq!(query.url, "example.com/page");
+        assert_eq!(query.match_type, MatchType::Exact);
+        assert_eq!(query.key.as_str(), "com,example)/page");
+        assert_eq!(query.range, key_range("example.com/page", MatchType::Exact, None).unwrap());
+    }
+
+    #[test]
+    fn test_url_query_prefix() {
+        let query = UrlQuery::parse("example.com/blog/*", None).unwrap();
+        assert_eq!(query.url, "example.com/blog/");
+        assert_eq!(query.match_type, MatchType::Prefix);
+        assert_eq!(query.range.start, "com,example)/blog/");
+
+        let query = UrlQuery::parse("http://example.com/page?*", None).unwrap();
+        assert_eq!(query.url, "http://example.com/page?");
+        assert_eq!(query.match_type, MatchType::Prefix);
+        assert_eq!(query.range.start, "com,example)/page?");
+        assert!(query.range.contains("com,example)/page?a=b 20200101000000"));
+        assert!(!query.range.contains("com,example)/page2 20200101000000"));
+    }
+
+    #[test]
+    fn test_url_query_domain() {
+        for pattern in ["*.example.com", "http://*.example.com/", "*.example.com/*"] {
+            let query = UrlQuery::parse(pattern, None).unwrap();
+            assert_eq!(query.match_type, MatchType::Domain, "{}", pattern);
+            assert_eq!(query.range.start, "com,example)", "{}", pattern);
+            assert!(query.range.contains("com,example,sub)/ 20200101000000"));
+        }
+
+        // The host is not massaged, so the query is not widened to example.com.
+        let query = UrlQuery::parse("*.www.example.com", None).unwrap();
+        assert_eq!(query.key.as_str(), "com,example,www)/");
+        assert_eq!(query.range.start, "com,example,www)");
+        assert!(query.range.contains("com,example,www,sub)/ 20200101000000"));
+        assert!(!query.range.contains("com,example)/ 20200101000000"));
+        assert!(!query.range.contains("com,example,sub)/ 20200101000000"));
+    }
+
+    #[test]
+    fn test_url_query_explicit_match_type() {
+        let query = UrlQuery::parse_with_match_type("example.com", Some(MatchType::Host), None).unwrap();
+        assert_eq!(query.match_type, MatchType::Host);
+        assert_eq!(query.range.start, "com,example)/");
+
+        let query = UrlQuery::parse_with_match_type("example.com/*", Some(MatchType::Prefix), None).unwrap();
+        assert_eq!(query.match_type, MatchType::Prefix);
+
+        assert!(UrlQuery::parse_with_match_type("*.example.com", Some(MatchType::Prefix), None).is_err());
+    }
+
+    #[test]
+    fn test_url_query_invalid() {
+        assert!(UrlQuery::parse("example.*.com", None).is_err());
+        assert!(UrlQuery::parse("example.com/*/page", None).is_err());
+        assert!(UrlQuery::parse("*", None).is_err());
+        assert!(UrlQuery::parse("*.", None).is_err());
+        assert!(UrlQuery::parse("*.example.com/page", None).is_err());
+        assert!(UrlQuery::parse("http://*.example.com/blog/*", None).is_err());
+    }
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::collapsible_if)]
+
 use lazy_static::lazy_static;
 use regex::Regex;
 
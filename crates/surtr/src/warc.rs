@@ -0,0 +1,574 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+use flate2::bufread::GzDecoder;
+
+use crate::{cdx::CdxjRecord, options::SurtrOptions, surt_with};
+
+// The WARC record types which are indexed, as pywb does.
+const INDEXED_TYPES: [&str; 3] = ["response", "revisit", "resource"];
+
+/// Build a CDXJ index from a WARC or ARC file.
+///
+/// Files may be compressed with one gzip member per record, as produced by crawlers, or
+/// uncompressed. The `offset` and `length` of each record are those of its gzip member,
+/// or of the raw record for uncompressed files, so the record can be read back directly.
+///
+/// `response`, `revisit` and `resource` records are indexed. Other WARC records, and the
+/// `filedesc:` record of ARC files, are skipped. Each record is keyed with `surt()`, and its
+/// status, mime type and payload digest are read from the HTTP headers and WARC headers. If a
+/// record has no `WARC-Payload-Digest`, as with ARC files, the SHA-1 of the payload is computed.
+///
+/// Records are returned in file order. The index must be sorted, with `LC_ALL=C sort`, before use.
+///
+/// # Examples
+///
+/// ```rust
+/// use surtr::WarcIndexer;
+///
+/// let warc = "WARC/1.0\r\n\
+///             WARC-Type: resource\r\n\
+///             WARC-Target-URI: http://www.example.com/\r\n\
+///             WARC-Date: 2020-01-01T00:00:00Z\r\n\
+///             Content-Type: text/plain\r\n\
+///             Content-Length: 5\r\n\
+///             \r\n\
+///             hello\r\n\r\n";
+///
+/// let records: Vec<_> = WarcIndexer::new(warc.as_bytes(), "example.warc").map(|r| r.unwrap()).collect();
+///
+/// assert_eq!(records[0].urlkey, "com,example)/");
+/// assert_eq!(records[0].timestamp, "20200101000000");
+/// assert_eq!(records[0].digest.as_deref(), Some("VL2MMHO4YXUKFWV63YHTWSBM3GXKSQ2N"));
+/// assert_eq!(records[0].offset, Some(0));
+/// assert_eq!(records[0].length, Some(warc.len() as u64));
+/// ```
+pub struct WarcIndexer<R> {
+    input: CountingReader<BufReader<R>>,
+    filename: String,
+    options: SurtrOptions,
+    done: bool,
+}
+
+impl<R: Read> WarcIndexer<R> {
+    /// Create an indexer for a WARC or ARC file.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The contents of the file.
+    /// * `filename` - The name of the file, written to the `filename` field of each record.
+    pub fn new(input: R, filename: &str) -> Self {
+        Self {
+            input: CountingReader {
+                inner: BufReader::new(input),
+                pos: 0,
+            },
+            filename: filename.to_string(),
+            options: SurtrOptions::default().with_surt_defaults(),
+            done: false,
+        }
+    }
+
+    /// Set the options used to build each key.
+    pub fn with_options(mut self, options: SurtrOptions) -> Self {
+        self.options = options.with_surt_defaults();
+        self
+    }
+
+    // Read the next record. Returns None at the end of the file, and Some(None) for skipped records.
+    fn read_record(&mut self) -> io::Result<Option<Option<ParsedRecord>>> {
+        // Skip any extra blank lines between uncompressed records.
+        loop {
+            let buf = self.input.fill_buf()?;
+            let blank = buf.iter().take_while(|b| matches!(b, b'\r' | b'\n')).count();
+
+            match (buf.is_empty(), blank) {
+                (true, _) => return Ok(None),
+                (false, 0) => break,
+                (false, n) => self.input.consume(n),
+            }
+        }
+
+        let offset = self.input.pos;
+        let buf = self.input.fill_buf()?;
+        let parsed = if buf.starts_with(&[0x1f, 0x8b]) {
+            let mut member = BufReader::new(GzDecoder::new(&mut self.input));
+            let parsed = parse_record(&mut member)?;
+            io::copy(&mut member, &mut io::sink())?;
+            parsed
+        } else {
+            parse_record(&mut self.input)?
+        };
+
+        let length = self.input.pos - offset;
+
+        Ok(Some(parsed.map(|mut p| {
+            p.offset = offset;
+            p.length = length;
+            p
+        })))
+    }
+}
+
+impl WarcIndexer<File> {
+    /// Open a WARC or ARC file for indexing. The file name is used as the `filename` of each record.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let filename = path
+            .file_name()
+            .map(|f| f.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.to_string_lossy().into_owned());
+
+        Ok(Self::new(File::open(path)?, &filename))
+    }
+}
+
+impl<R: Read> Iterator for WarcIndexer<R> {
+    type Item = io::Result<CdxjRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let parsed = match self.read_record() {
+                Ok(Some(Some(p))) => p,
+                Ok(Some(None)) => continue,
+                Ok(None) => {
+                    self.done = true;
+                    return None;
+                }
+                // The position in the file is lost, so no more records can be read.
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            };
+
+            // A URL which cannot be keyed only affects its own record.
+            let urlkey = match surt_with(&parsed.url, &self.options) {
+                Ok(k) => k,
+                Err(e) => {
+                    return Some(Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("record at offset {}: {}: {}", parsed.offset, e, parsed.url),
+                    )));
+                }
+            };
+
+            return Some(Ok(CdxjRecord {
+                urlkey,
+                timestamp: parsed.timestamp,
+                original: parsed.url,
+                mime: parsed.mime,
+                status: parsed.status,
+                digest: parsed.digest,
+                length: Some(parsed.length),
+                offset: Some(parsed.offset),
+                filename: Some(self.filename.clone()),
+                extra: vec![],
+            }));
+        }
+
+        None
+    }
+}
+
+// A reader which counts the bytes consumed from the file, to find the offsets of records.
+struct CountingReader<R> {
+    inner: R,
+    pos: u64,
+}
+
+impl<R: BufRead> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for CountingReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.inner.consume(amt);
+        self.pos += amt as u64;
+    }
+}
+
+// The fields read from a single record.
+#[derive(Debug, Default)]
+struct ParsedRecord {
+    url: String,
+    timestamp: String,
+    mime: Option<String>,
+    status: Option<u16>,
+    digest: Option<String>,
+    offset: u64,
+    length: u64,
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// Read a line, without its line ending. Returns None at the end of the input.
+fn read_line<R: BufRead + ?Sized>(r: &mut R) -> io::Result<Option<String>> {
+    let mut buf = Vec::new();
+    if r.read_until(b'\n', &mut buf)? == 0 {
+        return Ok(None);
+    }
+
+    let len = buf.len() - buf.iter().rev().take_while(|b| matches!(b, b'\n' | b'\r')).count();
+    Ok(Some(String::from_utf8_lossy(&buf[..len]).into_owned()))
+}
+
+// Read `Name: value` headers up to a blank line. Names are lowercased.
+fn read_headers<R: BufRead + ?Sized>(r: &mut R) -> io::Result<HashMap<String, String>> {
+    let mut headers = HashMap::new();
+
+    while let Some(line) = read_line(r)? {
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+
+    Ok(headers)
+}
+
+// Read a WARC or ARC record, consuming exactly the record.
+fn parse_record<R: BufRead + ?Sized>(r: &mut R) -> io::Result<Option<ParsedRecord>> {
+    let first = read_line(r)?.ok_or_else(|| invalid("unexpected end of file".to_string()))?;
+
+    if first.starts_with("WARC/") {
+        return parse_warc_record(r);
+    }
+
+    // ARC headers are `URL IP-address Archive-date Content-type [...] Archive-length`.
+    let fields: Vec<&str> = first.split_whitespace().collect();
+    if fields.len() < 5 {
+        return Err(invalid(format!("invalid record header: {}", first)));
+    }
+    let length: u64 = fields[fields.len() - 1]
+        .parse()
+        .map_err(|_| invalid(format!("invalid ARC record length: {}", first)))?;
+
+    let mut content = r.take(length);
+    if fields[0].starts_with("filedesc:") {
+        finish_content(&mut content)?;
+        consume_record_end(r)?;
+        return Ok(None);
+    }
+
+    let mut record = ParsedRecord {
+        url: fields[0].to_string(),
+        timestamp: fields[2].chars().take(14).collect(),
+        mime: normalize_mime(fields[3]),
+        ..Default::default()
+    };
+
+    if record.url.starts_with("http:") || record.url.starts_with("https:") {
+        let (status, mime) = parse_http_headers(&mut content)?;
+        record.status = status;
+        record.mime = mime.or(record.mime);
+    }
+    record.digest = Some(payload_digest(&mut content)?);
+    finish_content(&mut content)?;
+    consume_record_end(r)?;
+
+    Ok(Some(record))
+}
+
+// Read a WARC record after its version line.
+fn parse_warc_record<R: BufRead + ?Sized>(r: &mut R) -> io::Result<Option<ParsedRecord>> {
+    let headers = read_headers(r)?;
+    let length: u64 = headers
+        .get("content-length")
+        .and_then(|l| l.parse().ok())
+        .ok_or_else(|| invalid("WARC record has no valid Content-Length".to_string()))?;
+    let mut content = r.take(length);
+
+    let warc_type = headers.get("warc-type").map(String::as_str).unwrap_or("");
+    let url = headers.get("warc-target-uri").map(|u| u.trim_start_matches('<').trim_end_matches('>'));
+
+    let url = match url {
+        Some(u) if INDEXED_TYPES.contains(&warc_type) => u.to_string(),
+        _ => {
+            finish_content(&mut content)?;
+            consume_record_end(r)?;
+            return Ok(None);
+        }
+    };
+
+    let content_type = headers.get("content-type").map(String::as_str).unwrap_or("");
+    let mut record = ParsedRecord {
+        url,
+        timestamp: headers
+            .get("warc-date")
+            .map(|d| d.chars().filter(char::is_ascii_digit).take(14).collect())
+            .unwrap_or_default(),
+        mime: normalize_mime(content_type),
+        digest: headers
+            .get("warc-payload-digest")
+            .map(|d| d.strip_prefix("sha1:").unwrap_or(d).to_string()),
+        ..Default::default()
+    };
+
+    if warc_type != "resource" && content_type.starts_with("application/http") {
+        let (status, mime) = parse_http_headers(&mut content)?;
+        record.status = status;
+        record.mime = mime;
+    }
+    if warc_type == "revisit" {
+        record.mime = Some("warc/revisit".to_string());
+    }
+    if record.digest.is_none() && warc_type != "revisit" {
+        record.digest = Some(payload_digest(&mut content)?);
+    }
+
+    finish_content(&mut content)?;
+    consume_record_end(r)?;
+
+    Ok(Some(record))
+}
+
+// Read the rest of the content of a record, which must not end before its declared length.
+fn finish_content<R: Read>(content: &mut io::Take<R>) -> io::Result<()> {
+    io::copy(content, &mut io::sink())?;
+
+    match content.limit() {
+        0 => Ok(()),
+        missing => Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("record is truncated, {} bytes of its content are missing", missing),
+        )),
+    }
+}
+
+// Consume the line endings which close a record, `\r\n\r\n` for WARC and `\n` for ARC.
+fn consume_record_end<R: BufRead + ?Sized>(r: &mut R) -> io::Result<()> {
+    let buf = r.fill_buf()?;
+    let n = buf.iter().take(4).take_while(|b| matches!(b, b'\r' | b'\n')).count();
+    r.consume(n);
+
+    Ok(())
+}
+
+// Read the status and mime type from HTTP response headers.
+fn parse_http_headers<R: BufRead + ?Sized>(r: &mut R) -> io::Result<(Option<u16>, Option<String>)> {
+    let status = match read_line(r)? {
+        Some(line) if line.starts_with("HTTP/") => line.split_whitespace().nth(1).and_then(|s| s.parse().ok()),
+        _ => return Ok((None, None)),
+    };
+    let headers = read_headers(r)?;
+
+    Ok((status, headers.get("content-type").and_then(|c| normalize_mime(c))))
+}
+
+// The mime type without parameters. e.g. `text/html; charset=utf-8` -> `text/html`
+fn normalize_mime(value: &str) -> Option<String> {
+    match value.split(';').next().unwrap_or("").trim() {
+        "" | "-" => None,
+        mime => Some(mime.to_ascii_lowercase()),
+    }
+}
+
+// The base32 encoded SHA-1 of the rest of the payload, as written to `WARC-Payload-Digest`.
+fn payload_digest<R: BufRead + ?Sized>(r: &mut R) -> io::Result<String> {
+    let mut hash = sha1_smol::Sha1::new();
+
+    loop {
+        let buf = r.fill_buf()?;
+        if buf.is_empty() {
+            break;
+        }
+        hash.update(buf);
+
+        let n = buf.len();
+        r.consume(n);
+    }
+
+    Ok(base32(&hash.digest().bytes()))
+}
+
+fn base32(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+    let mut out = String::with_capacity(bytes.len().div_ceil(5) * 8);
+
+    for chunk in bytes.chunks(5) {
+        let mut buf = [0u8; 5];
+        buf[..chunk.len()].copy_from_slice(chunk);
+        let bits = buf.iter().fold(0u64, |acc, b| (acc << 8) | *b as u64);
+
+        let chars = (chunk.len() * 8).div_ceil(5);
+        for i in 0..chars {
+            out.push(ALPHABET[((bits >> (35 - i * 5)) & 0x1f) as usize] as char);
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    fn warc_record(warc_type: &str, url: &str, content_type: &str, extra: &str, body: &str) -> String {
+        format!(
+            "WARC/1.0\r\nWARC-Type: {}\r\nWARC-Target-URI: {}\r\nWARC-Date: 2020-01-02T03:04:05.123Z\r\n\
+             Content-Type: {}\r\n{}Content-Length: {}\r\n\r\n{}\r\n\r\n",
+            warc_type,
+            url,
+            content_type,
+            extra,
+            body.len(),
+            body
+        )
+    }
+
+    fn gzip(data: &str) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data.as_bytes()).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn records() -> Vec<String> {
+        let http = "HTTP/1.1 200 OK\r\nContent-Type: Text/HTML; charset=utf-8\r\n\r\n<html></html>";
+
+        vec![
+            warc_record("warcinfo", "", "application/warc-fields", "", "software: test"),
+            warc_record("response", "http://www.example.com/", "application/http; msgtype=response", "", http),
+            warc_record("request", "http://www.example.com/", "application/http; msgtype=request", "", "GET / HTTP/1.1"),
+            warc_record(
+                "revisit",
+                "http://www.example.com/",
+                "application/http; msgtype=response",
+                "WARC-Payload-Digest: sha1:ABCDEF\r\n",
+                "HTTP/1.1 304 Not Modified\r\n\r\n",
+            ),
+            warc_record("response", "dns:example.com", "text/dns", "", "20200102030405\nexample.com. 300 IN A 1.2.3.4"),
+        ]
+    }
+
+    #[test]
+    fn test_index_warc_gz() {
+        let members: Vec<Vec<u8>> = records().iter().map(|r| gzip(r)).collect();
+        let file: Vec<u8> = members.concat();
+
+        let index: Vec<CdxjRecord> = WarcIndexer::new(file.as_slice(), "test.warc.gz").map(|r| r.unwrap()).collect();
+        assert_eq!(index.len(), 3);
+
+        let offsets: Vec<u64> = members.iter().scan(0, |pos, m| { let o = *pos; *pos += m.len() as u64; Some(o) }).collect();
+
+        let response = &index[0];
+        assert_eq!(response.urlkey, "com,example)/");
+        assert_eq!(response.timestamp, "20200102030405");
+        assert_eq!(response.original, "http://www.example.com/");
+        assert_eq!(response.status, Some(200));
+        assert_eq!(response.mime.as_deref(), Some("text/html"));
+        assert_eq!(response.digest.as_deref(), Some(base32(&sha1_smol::Sha1::from("<html></html>").digest().bytes()).as_str()));
+        assert_eq!(response.offset, Some(offsets[1]));
+        assert_eq!(response.length, Some(members[1].len() as u64));
+        assert_eq!(response.filename.as_deref(), Some("test.warc.gz"));
+
+        let revisit = &index[1];
+        assert_eq!(revisit.status, Some(304));
+        assert_eq!(revisit.mime.as_deref(), Some("warc/revisit"));
+        assert_eq!(revisit.digest.as_deref(), Some("ABCDEF"));
+        assert_eq!(revisit.offset, Some(offsets[3]));
+
+        let dns = &index[2];
+        assert_eq!(dns.urlkey, "dns:example.com");
+        assert_eq!(dns.status, None);
+        assert_eq!(dns.mime.as_deref(), Some("text/dns"));
+        assert_eq!(dns.offset, Some(offsets[4]));
+        assert_eq!(dns.length, Some(members[4].len() as u64));
+    }
+
+    #[test]
+    fn test_index_warc_uncompressed() {
+        let records = records();
+        let file = records.concat();
+
+        let index: Vec<CdxjRecord> = WarcIndexer::new(file.as_bytes(), "test.warc").map(|r| r.unwrap()).collect();
+        assert_eq!(index.len(), 3);
+
+        let start = records[0].len() as u64;
+        assert_eq!(index[0].offset, Some(start));
+        assert_eq!(index[0].length, Some(records[1].len() as u64));
+        assert_eq!(index[1].offset, Some(start + records[1].len() as u64 + records[2].len() as u64));
+    }
+
+    #[test]
+    fn test_index_arc_gz() {
+        let filedesc = "filedesc://test.arc 0.0.0.0 20050614070144 text/plain 9\n1 0 test\n\n";
+        let body = "HTTP/1.0 404 Not Found\r\nContent-Type: text/plain\r\n\r\nmissing";
+        let record = format!("http://www.example.com/a 1.2.3.4 20050614070159 text/html {}\n{}\n", body.len(), body);
+
+        let file = [gzip(filedesc), gzip(&record)].concat();
+        let index: Vec<CdxjRecord> = WarcIndexer::new(file.as_slice(), "test.arc.gz").map(|r| r.unwrap()).collect();
+
+        assert_eq!(index.len(), 1);
+        assert_eq!(index[0].urlkey, "com,example)/a");
+        assert_eq!(index[0].timestamp, "20050614070159");
+        assert_eq!(index[0].status, Some(404));
+        assert_eq!(index[0].mime.as_deref(), Some("text/plain"));
+        assert_eq!(index[0].digest.as_deref(), Some(base32(&sha1_smol::Sha1::from("missing").digest().bytes()).as_str()));
+        assert_eq!(index[0].offset, Some(gzip(filedesc).len() as u64));
+    }
+
+    #[test]
+    fn test_index_errors() {
+        let file = [
+            warc_record("resource", "http://example.com/%ff", "text/plain", "", "a"),
+            warc_record("resource", "http://example.com/", "text/plain", "", "b"),
+        ]
+        .concat();
+
+        let index: Vec<io::Result<CdxjRecord>> = WarcIndexer::new(file.as_bytes(), "test.warc").collect();
+        assert_eq!(index.len(), 2);
+        assert!(index[0].is_err());
+        assert_eq!(index[1].as_ref().unwrap().urlkey, "com,example)/");
+
+        let index: Vec<io::Result<CdxjRecord>> = WarcIndexer::new("not a warc\n".as_bytes(), "test.warc").collect();
+        assert_eq!(index.len(), 1);
+        assert!(index[0].is_err());
+
+        let truncated = &gzip(&records()[1])[..20];
+        let index: Vec<io::Result<CdxjRecord>> = WarcIndexer::new(truncated, "test.warc.gz").collect();
+        assert!(index.last().unwrap().is_err());
+
+        // Uncompressed records which end before their Content-Length are not indexed.
+        let record = warc_record("resource", "http://example.com/", "text/plain", "", "hello");
+        let truncated = &record[..record.len() - 7];
+        let index: Vec<io::Result<CdxjRecord>> = WarcIndexer::new(truncated.as_bytes(), "test.warc").collect();
+        assert_eq!(index.len(), 1);
+        assert_eq!(index[0].as_ref().unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+
+        let arc = "http://example.com/ 1.2.3.4 20050614070159 text/plain 10
+short
+";
+        let index: Vec<io::Result<CdxjRecord>> = WarcIndexer::new(arc.as_bytes(), "test.arc").collect();
+        assert_eq!(index[0].as_ref().unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_base32() {
+        assert_eq!(base32(b""), "");
+        assert_eq!(base32(b"f"), "MY");
+        assert_eq!(base32(b"fooba"), "MZXW6YTB");
+        assert_eq!(base32(b"foobar"), "MZXW6YTBOI");
+        assert_eq!(base32(&sha1_smol::Sha1::from("").digest().bytes()), "3I42H3S6NNFQ2MSVX7XZKYAYSCX5QBYJ");
+    }
+}
@@ -0,0 +1,322 @@
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use flate2::{write::GzEncoder, Compression};
+
+/// The number of lines in each block, as used by IA's ZipNum tooling and pywb.
+pub const DEFAULT_LINES_PER_BLOCK: usize = 3000;
+
+/// Write a ZipNum cluster from sorted CDX or CDXJ lines.
+///
+/// Lines are grouped into blocks of a fixed number of lines, and each block is written to
+/// the part file as its own gzip member. For each block, a line is written to the secondary
+/// index with the key and timestamp of its first line, the part name, the offset and length
+/// of its gzip member and its block number, separated by tabs. The cluster can be searched
+/// with `ZipNumIndex`.
+///
+/// Lines must be sorted byte-wise, with `LC_ALL=C sort`. CDX headers and blank lines are skipped.
+///
+/// # Examples
+///
+/// ```rust
+/// use surtr::ZipNumWriter;
+///
+/// let mut writer = ZipNumWriter::new(Vec::new(), Vec::new(), "part-00000").with_lines_per_block(2);
+///
+/// writer.write_line("com,example)/ 20200101000000 http://example.com/").unwrap();
+/// writer.write_line("com,example)/about 20200101000000 http://example.com/about").unwrap();
+/// writer.write_line("org,example)/ 20200101000000 http://example.org/").unwrap();
+///
+/// let (part, index) = writer.finish().unwrap();
+/// let index = String::from_utf8(index).unwrap();
+///
+/// assert_eq!(index.lines().count(), 2);
+/// assert!(index.starts_with("com,example)/ 20200101000000\tpart-00000\t0\t"));
+/// assert!(index.lines().nth(1).unwrap().starts_with("org,example)/ 20200101000000\tpart-00000\t"));
+/// assert!(!part.is_empty());
+/// ```
+pub struct ZipNumWriter<W: Write, I: Write> {
+    part: W,
+    index: I,
+    part_name: String,
+    lines_per_block: usize,
+    block: Vec<u8>,
+    block_lines: usize,
+    block_key: String,
+    offset: u64,
+    blocks: usize,
+    last_line: String,
+}
+
+impl<W: Write, I: Write> ZipNumWriter<W, I> {
+    /// Create a writer for a ZipNum cluster with a single part.
+    ///
+    /// # Arguments
+    ///
+    /// * `part` - The output for the compressed blocks.
+    /// * `index` - The output for the secondary index.
+    /// * `part_name` - The name of the part, as written to the secondary index.
+    pub fn new(part: W, index: I, part_name: &str) -> Self {
+        Self {
+            part,
+            index,
+            part_name: part_name.to_string(),
+            lines_per_block: DEFAULT_LINES_PER_BLOCK,
+            block: Vec::new(),
+            block_lines: 0,
+            block_key: String::new(),
+            offset: 0,
+            blocks: 0,
+            last_line: String::new(),
+        }
+    }
+
+    /// Set the number of lines in each block. Values below 1 are treated as 1.
+    pub fn with_lines_per_block(mut self, lines_per_block: usize) -> Self {
+        self.lines_per_block = lines_per_block.max(1);
+        self
+    }
+
+    /// Write a CDX or CDXJ line, without its line ending.
+    ///
+    /// # Errors
+    ///
+    /// `io::ErrorKind::InvalidInput` - If the line sorts before the previous line.
+    ///
+    /// `io::Error` - If the part or index cannot be written.
+    pub fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.is_empty() || line.starts_with(" CDX ") {
+            return Ok(());
+        }
+
+        if line < self.last_line.as_str() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("cdx lines are not sorted: '{}' is after '{}'", line, self.last_line),
+            ));
+        }
+        self.last_line.clear();
+        self.last_line.push_str(line);
+
+        if self.block_lines == 0 {
+            // The key and timestamp of the first line.
+            self.block_key = line.splitn(3, ' ').take(2).collect::<Vec<_>>().join(" ");
+        }
+        self.block.extend_from_slice(line.as_bytes());
+        self.block.push(b'\n');
+        self.block_lines += 1;
+
+        if self.block_lines >= self.lines_per_block {
+            self.write_block()?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the last block and flush the outputs.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing the part and index outputs.
+    pub fn finish(mut self) -> io::Result<(W, I)> {
+        self.write_block()?;
+        self.part.flush()?;
+        self.index.flush()?;
+
+        Ok((self.part, self.index))
+    }
+
+    // Compresses the current block as a gzip member and writes its index line.
+    fn write_block(&mut self) -> io::Result<()> {
+        if self.block_lines == 0 {
+            return Ok(());
+        }
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&self.block)?;
+        let member = encoder.finish()?;
+
+        self.part.write_all(&member)?;
+        self.blocks += 1;
+        writeln!(
+            self.index,
+            "{}\t{}\t{}\t{}\t{}",
+            self.block_key,
+            self.part_name,
+            self.offset,
+            member.len(),
+            self.blocks
+        )?;
+
+        self.offset += member.len() as u64;
+        self.block.clear();
+        self.block_lines = 0;
+        Ok(())
+    }
+}
+
+/// Write a ZipNum cluster to a directory from a sorted CDX or CDXJ file.
+///
+/// The cluster is written as `<name>.cdx.gz`, `<name>.idx`, and a `<name>.loc` file
+/// naming the part, as expected by pywb.
+///
+/// # Arguments
+///
+/// * `input` - The sorted CDX or CDXJ lines.
+/// * `dir` - The directory to write the cluster to.
+/// * `name` - The name of the cluster.
+/// * `lines_per_block` - The number of lines in each block.
+///
+/// # Returns
+///
+/// A Result containing the path of the secondary index.
+///
+/// # Errors
+///
+/// `io::ErrorKind::InvalidInput` - If the input is not sorted.
+///
+/// `io::Error` - If the input cannot be read, or the cluster cannot be written.
+///
+/// On error, the files this call created are removed. Files of an earlier cluster with the same
+/// name which were not yet overwritten are kept.
+pub fn write_zipnum<R: BufRead>(input: R, dir: &Path, name: &str, lines_per_block: usize) -> io::Result<PathBuf> {
+    let part_name = format!("{}.cdx.gz", name);
+    let part_path = dir.join(&part_name);
+    let index_path = dir.join(format!("{}.idx", name));
+    let loc_path = dir.join(format!("{}.loc", name));
+    let mut created: Vec<&Path> = vec![];
+
+    let mut create = |path| {
+        let file = File::create(path)?;
+        created.push(path);
+        Ok::<_, io::Error>(BufWriter::new(file))
+    };
+
+    let result = (|| {
+        let part = create(&part_path)?;
+        let index = create(&index_path)?;
+        let mut writer = ZipNumWriter::new(part, index, name).with_lines_per_block(lines_per_block);
+
+        for line in input.lines() {
+            writer.write_line(&line?)?;
+        }
+        writer.finish()?;
+
+        let mut loc = create(&loc_path)?;
+        writeln!(loc, "{}\t{}", name, part_name)?;
+        loc.flush()
+    })();
+
+    if let Err(e) = result {
+        for path in created {
+            let _ = fs::remove_file(path);
+        }
+        return Err(e);
+    }
+    Ok(index_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cdx::CdxRecord, lookup::CdxSource, key_range::MatchType, ZipNumIndex};
+    use flate2::read::MultiGzDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn test_zipnum_writer() {
+        let lines: Vec<String> = (0..7).map(|i| format!("com,example)/{} 2020010100000{} a", i, i)).collect();
+
+        let mut writer = ZipNumWriter::new(Vec::new(), Vec::new(), "part").with_lines_per_block(3);
+        writer.write_line(CdxRecord::HEADER).unwrap();
+        for line in &lines {
+            writer.write_line(&format!("{}\n", line)).unwrap();
+        }
+        writer.write_line("").unwrap();
+        let (part, index) = writer.finish().unwrap();
+        let index = String::from_utf8(index).unwrap();
+
+        let index_lines: Vec<Vec<&str>> = index.lines().map(|l| l.split('\t').collect()).collect();
+        assert_eq!(index_lines.len(), 3);
+        assert_eq!(index_lines[0][..2], ["com,example)/0 20200101000000", "part"]);
+        assert_eq!(index_lines[1][0], "com,example)/3 20200101000003");
+        assert_eq!(index_lines[2][4], "3");
+
+        // Each block is a gzip member which can be read on its own.
+        let (offset, length): (usize, usize) = (index_lines[1][2].parse().unwrap(), index_lines[1][3].parse().unwrap());
+        let mut block = String::new();
+        MultiGzDecoder::new(&part[offset..offset + length]).read_to_string(&mut block).unwrap();
+        assert_eq!(block, lines[3..6].join("\n") + "\n");
+
+        // The whole part is the original file.
+        let mut all = String::new();
+        MultiGzDecoder::new(&part[..]).read_to_string(&mut all).unwrap();
+        assert_eq!(all, lines.join("\n") + "\n");
+
+        let (part, index) = ZipNumWriter::new(Vec::new(), Vec::new(), "part").finish().unwrap();
+        assert!(part.is_empty() && index.is_empty());
+    }
+
+    #[test]
+    fn test_zipnum_writer_unsorted() {
+        let mut writer = ZipNumWriter::new(Vec::new(), Vec::new(), "part");
+        writer.write_line("com,example)/b 20200101000000").unwrap();
+        writer.write_line("com,example)/b 20200101000000").unwrap();
+
+        let err = writer.write_line("com,example)/a 20200101000000").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_write_zipnum_lookup() {
+        let dir = std::env::temp_dir().join(format!("surtr-zipnum-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let urls = [
+            "http://www.example.com/",
+            "http://example.com/about?b=1&a=2",
+            "http://EXAMPLE.com/about/team",
+            "http://shop.example.com/",
+            "http://example.org/",
+            "http://examples.com/",
+        ];
+        let mut lines: Vec<String> = urls
+            .iter()
+            .map(|url| CdxRecord::new(url, "20200101000000", None).unwrap().to_string())
+            .collect();
+        lines.sort();
+
+        let path = write_zipnum(lines.join("\n").as_bytes(), &dir, "cluster", 2).unwrap();
+        assert!(dir.join("cluster.cdx.gz").is_file());
+        assert_eq!(fs::read_to_string(dir.join("cluster.loc")).unwrap(), "cluster\tcluster.cdx.gz\n");
+
+        let index = ZipNumIndex::open(path).unwrap();
+        let found = |url: &str, match_type: MatchType| -> Vec<String> {
+            index
+                .lookup(url, match_type, None)
+                .unwrap()
+                .map(|l| l.unwrap().split(' ').nth(2).unwrap().to_string())
+                .collect()
+        };
+
+        assert_eq!(found("http://example.com/about?a=2&b=1", MatchType::Exact), ["http://example.com/about?b=1&a=2"]);
+        assert_eq!(found("http://example.com/about", MatchType::Prefix).len(), 2);
+        assert_eq!(found("http://example.com/", MatchType::Host).len(), 3);
+        assert_eq!(found("http://example.com/", MatchType::Domain).len(), 4);
+        assert_eq!(found("http://example.org/", MatchType::Domain), ["http://example.org/"]);
+
+        let unsorted = "com,example)/b 20200101000000\ncom,example)/a 20200101000000\n";
+        assert!(write_zipnum(unsorted.as_bytes(), &dir, "unsorted", 2).is_err());
+        assert!(!dir.join("unsorted.cdx.gz").exists());
+        assert!(!dir.join("unsorted.idx").exists());
+
+        // A failed rebuild keeps the location file of the earlier cluster.
+        fs::write(dir.join("unsorted.loc"), "unsorted\tunsorted.cdx.gz\n").unwrap();
+        assert!(write_zipnum(unsorted.as_bytes(), &dir, "unsorted", 2).is_err());
+        assert!(dir.join("unsorted.loc").is_file());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,772 @@
+//! `surtr` - Convert URLs into SURTs from the command line.
+//!
+//! URLs are read one per line from the given files, or stdin, and the SURT of each
+//! is written on its own line. Every `SurtrOptions` toggle is available as a
+//! `--<option>` / `--no-<option>` switch pair, applied on top of the chosen `--profile`.
+//!
+//! With `--format tsv|csv|jsonl`, the URL is read from the `--field` of each record, and the
+//! SURT replaces it, or is appended as a new field with `--append`. With `--format cdx`, the
+//! keys of CDX and CDXJ lines are recomputed from their original URLs. With `--format warc`,
+//! each file is a WARC or ARC file, and a CDXJ index of its records is written.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::process::ExitCode;
+use std::str::FromStr;
+
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use surtr::{
+    CdxRekeyer, CdxWriter, FieldMode, FieldSelector, FieldTransformer, HostGranularity, NestedUrlMode, NestedUrls, RecordFormat, ReplayMatcher, SurtrError, SurtrOptions, TransformError, WarcIndexer,
+};
+
+/// What to do with a line which cannot be converted into a SURT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum ErrorPolicy {
+    /// Drop the line from the output.
+    Skip,
+    /// Write `-` in place of the SURT, keeping the output aligned with the input.
+    #[default]
+    Dash,
+    /// Stop processing and report the line number.
+    Abort,
+}
+
+impl fmt::Display for ErrorPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::Skip => "skip",
+            Self::Dash => "dash",
+            Self::Abort => "abort",
+        };
+
+        write!(f, "{}", s)
+    }
+}
+
+impl FromStr for ErrorPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "skip" => Ok(Self::Skip),
+            "dash" => Ok(Self::Dash),
+            "abort" => Ok(Self::Abort),
+            _ => Err(format!("invalid error policy '{}'. Expected skip, dash or abort", s)),
+        }
+    }
+}
+
+/// How the input is read, set by `--format`.
+#[derive(Debug, Clone, Default)]
+enum InputMode {
+    /// One URL per line.
+    #[default]
+    Lines,
+    /// TSV, CSV or JSON Lines records, with the URL in one field.
+    Records(Box<FieldTransformer>),
+    /// CDX or CDXJ lines to re-key.
+    Cdx,
+    /// WARC or ARC files to index.
+    Warc,
+}
+
+/// Everything needed to process a stream of URLs.
+#[derive(Debug, Clone, Default)]
+struct Config {
+    options: SurtrOptions,
+    with_url: bool,
+    on_error: ErrorPolicy,
+    mode: InputMode,
+}
+
+/// The errors which stop the CLI.
+#[derive(Debug)]
+enum CliError {
+    /// An invalid command line argument.
+    Usage(String),
+    /// Reading the input or writing the output failed.
+    Io(String, io::Error),
+    /// A URL could not be converted, and the error policy is `abort`.
+    Url { source: String, line: usize, url: String, error: SurtrError },
+    /// A record could not be transformed, and the error policy is `abort`.
+    Record(String, TransformError),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Usage(s) => write!(f, "{}", s),
+            Self::Io(path, e) => write!(f, "{}: {}", path, e),
+            Self::Url { source, line, url, error } => write!(f, "{}:{}: {}: {}", source, line, error, url),
+            Self::Record(source, error) => write!(f, "{}: {}", source, error),
+        }
+    }
+}
+
+// Convert an option name into its command line flag. e.g. `with_scheme` -> `with-scheme`
+fn flag_name(option: &str) -> String {
+    option.replace('_', "-")
+}
+
+fn command() -> Command {
+    let mut cmd = Command::new("surtr")
+        .version(clap::crate_version!())
+        .about("Convert URLs into Sort-friendly URI Reordering Transforms (SURTs).")
+        .arg(
+            Arg::new("files")
+                .value_name("FILE")
+                .num_args(0..)
+                .help("Files containing one URL per line. Reads stdin if none are given, or for `-`."),
+        )
+        .arg(
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .value_name("FILE")
+                .help("Write the SURTs to a file instead of stdout."),
+        )
+        .arg(
+            Arg::new("with-url")
+                .short('u')
+                .long("with-url")
+                .action(ArgAction::SetTrue)
+                .help("Write the original URL after the SURT, separated by a tab."),
+        )
+        .arg(
+            Arg::new("on-error")
+                .long("on-error")
+                .value_name("POLICY")
+                .value_parser(["skip", "dash", "abort"])
+                .default_value("dash")
+                .help("What to do with lines which cannot be converted: drop them, write `-`, or stop."),
+        )
+        .arg(
+            Arg::new("format")
+                .short('f')
+                .long("format")
+                .value_name("FORMAT")
+                .value_parser(["lines", "tsv", "csv", "jsonl", "cdx", "warc"])
+                .default_value("lines")
+                .help(
+                    "The input format. `lines` reads one URL per line. `cdx` re-keys CDX and CDXJ lines. \
+                     `warc` indexes WARC and ARC files as CDXJ.",
+                ),
+        )
+        .arg(
+            Arg::new("field")
+                .long("field")
+                .value_name("FIELD")
+                .help("The field holding the URL: a column number starting at 1, a column name, or a JSON pointer."),
+        )
+        .arg(
+            Arg::new("header")
+                .long("header")
+                .action(ArgAction::SetTrue)
+                .help("The first TSV or CSV record is a header row."),
+        )
+        .arg(
+            Arg::new("append")
+                .long("append")
+                .value_name("NAME")
+                .help("Keep the URL and add the SURT as a new field with this name, instead of replacing the URL."),
+        )
+        .arg(
+            Arg::new("profile")
+                .short('p')
+                .long("profile")
+                .value_name("PROFILE")
+                .value_parser(SurtrOptions::PROFILES)
+                .default_value("default")
+                .help("The set of options to start from. Individual options override the profile."),
+        )
+        .arg(
+            Arg::new("host-granularity")
+                .long("host-granularity")
+                .value_name("GRANULARITY")
+                .help("How much of the host to keep: full, suffix, registered_domain or registered_domain+N."),
+        )
+        .arg(
+            Arg::new("path-depth")
+                .long("path-depth")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .help("The number of path segments to keep."),
+        )
+        .arg(
+            Arg::new("unwrap-replay")
+                .long("unwrap-replay")
+                .action(ArgAction::SetTrue)
+                .help("Key the original URL of Wayback and pywb replay URLs, such as http://web.archive.org/web/2020/http://example.com/."),
+        )
+        .arg(
+            Arg::new("replay-host")
+                .long("replay-host")
+                .value_name("HOST")
+                .action(ArgAction::Append)
+                .help("Only unwrap replay URLs on this host, or its subdomains. Implies --unwrap-replay. Can be repeated."),
+        )
+        .arg(
+            Arg::new("nested-urls")
+                .long("nested-urls")
+                .value_name("MODE")
+                .help("Handle URLs nested in query parameters, such as ?url=http://example.com/: canonicalize or extract."),
+        )
+        .arg(
+            Arg::new("nested-param")
+                .long("nested-param")
+                .value_name("NAME")
+                .action(ArgAction::Append)
+                .help("A query parameter which may hold a nested URL, instead of url, u, r and redirect. Can be repeated."),
+        );
+
+    for option in SurtrOptions::OPTION_NAMES {
+        let flag = flag_name(option);
+        let no_flag = format!("no-{}", flag);
+
+        cmd = cmd
+            .arg(
+                Arg::new(flag.clone())
+                    .long(flag.clone())
+                    .action(ArgAction::SetTrue)
+                    .overrides_with(no_flag.clone())
+                    .help_heading("Options")
+                    .help(format!("Enable the {} option.", option)),
+            )
+            .arg(
+                Arg::new(no_flag.clone())
+                    .long(no_flag)
+                    .action(ArgAction::SetTrue)
+                    .overrides_with(flag)
+                    .help_heading("Options")
+                    .help(format!("Disable the {} option.", option)),
+            );
+    }
+
+    cmd
+}
+
+fn build_config(matches: &ArgMatches) -> Result<Config, CliError> {
+    let profile = matches.get_one::<String>("profile").map(String::as_str).unwrap_or("default");
+    let mut options = SurtrOptions::profile(profile).map_err(CliError::Usage)?;
+
+    for option in SurtrOptions::OPTION_NAMES {
+        let flag = flag_name(option);
+
+        if matches.get_flag(&flag) {
+            options.set(option, true);
+        } else if matches.get_flag(&format!("no-{}", flag)) {
+            options.set(option, false);
+        }
+    }
+
+    if let Some(granularity) = matches.get_one::<String>("host-granularity") {
+        let granularity: HostGranularity = granularity.parse().map_err(CliError::Usage)?;
+        options.set_host_granularity(granularity);
+    }
+    options.set_path_depth(matches.get_one::<usize>("path-depth").copied());
+
+    let replay_hosts: Vec<String> = matches.get_many::<String>("replay-host").unwrap_or_default().cloned().collect();
+    if matches.get_flag("unwrap-replay") || !replay_hosts.is_empty() {
+        options.set_replay_unwrap(Some(ReplayMatcher::default().with_hosts(replay_hosts)));
+    }
+
+    if let Some(mode) = matches.get_one::<String>("nested-urls") {
+        let mode: NestedUrlMode = mode.parse().map_err(CliError::Usage)?;
+        let mut nested = NestedUrls::default().with_mode(mode);
+        if let Some(params) = matches.get_many::<String>("nested-param") {
+            nested = nested.with_params(params.cloned().collect());
+        }
+        options.set_nested_urls(Some(nested));
+    }
+
+    // The defaults of surt() are resolved once, so each URL is keyed without copying the options.
+    let options = options.with_surt_defaults();
+
+    let on_error = match matches.get_one::<String>("on-error") {
+        Some(policy) => policy.parse().map_err(CliError::Usage)?,
+        None => ErrorPolicy::default(),
+    };
+
+    let format = matches.get_one::<String>("format").map(String::as_str).unwrap_or("lines");
+    let with_url = matches.get_flag("with-url");
+
+    if format != "lines" && with_url {
+        return Err(CliError::Usage(format!("--with-url cannot be used with --format {}", format)));
+    }
+
+    let mode = match format {
+        "lines" => InputMode::Lines,
+        "cdx" => InputMode::Cdx,
+        "warc" => InputMode::Warc,
+        format => {
+            let format: RecordFormat = format.parse().map_err(CliError::Usage)?;
+
+            let field = match matches.get_one::<String>("field") {
+                Some(field) => parse_field(field, format)?,
+                None => return Err(CliError::Usage(format!("--field is required with --format {}", format))),
+            };
+            let mode = match matches.get_one::<String>("append") {
+                Some(name) => FieldMode::Append(name.clone()),
+                None => FieldMode::Replace,
+            };
+
+            InputMode::Records(Box::new(
+                FieldTransformer::new(format, field)
+                    .with_header(matches.get_flag("header"))
+                    .with_mode(mode)
+                    .with_options(options.clone()),
+            ))
+        }
+    };
+
+    Ok(Config {
+        options,
+        with_url,
+        on_error,
+        mode,
+    })
+}
+
+// Parse the `--field` argument. Columns are numbered from 1, as they are by `cut` and `awk`.
+fn parse_field(field: &str, format: RecordFormat) -> Result<FieldSelector, CliError> {
+    if format == RecordFormat::JsonLines {
+        return match field.starts_with('/') || field.is_empty() {
+            true => Ok(FieldSelector::Pointer(field.to_string())),
+            false => Ok(FieldSelector::Name(field.to_string())),
+        };
+    }
+
+    match field.parse::<usize>() {
+        Ok(0) => Err(CliError::Usage("--field columns are numbered from 1".to_string())),
+        Ok(n) => Ok(FieldSelector::Index(n - 1)),
+        Err(_) => Ok(FieldSelector::Name(field.to_string())),
+    }
+}
+
+/// Convert every URL in `input`, writing the results to `output`.
+///
+/// `source` is the name of the input, used when reporting errors.
+fn process<R: BufRead, W: Write>(source: &str, mut input: R, output: &mut W, config: &Config) -> Result<(), CliError> {
+    match &config.mode {
+        InputMode::Records(transformer) => {
+            return process_records(source, input, output, (**transformer).clone(), config.on_error);
+        }
+        InputMode::Cdx => return process_cdx(source, input, output, config),
+        InputMode::Warc => return process_warc(source, input, output, config),
+        InputMode::Lines => {}
+    }
+
+    let io_err = |e| CliError::Io(source.to_string(), e);
+    let mut buf = Vec::new();
+    let mut line = 0;
+
+    loop {
+        buf.clear();
+        if input.read_until(b'\n', &mut buf).map_err(io_err)? == 0 {
+            return Ok(());
+        }
+        line += 1;
+
+        // Invalid UTF-8 is replaced rather than rejected, as crawl logs often contain it.
+        let text = String::from_utf8_lossy(&buf);
+        let url = text.trim_end_matches(['\n', '\r']);
+
+        // A blank line has no URL to convert, so it is handled like any other failed URL.
+        let result = match url.trim() {
+            "" => Err(SurtrError::UrlParseError("empty url".to_string())),
+            u => surtr::surt_with(u, &config.options),
+        };
+        let key = match result {
+            Ok(key) => key,
+            Err(error) => match config.on_error {
+                ErrorPolicy::Skip => continue,
+                ErrorPolicy::Dash => "-".to_string(),
+                ErrorPolicy::Abort => {
+                    return Err(CliError::Url {
+                        source: source.to_string(),
+                        line,
+                        url: url.to_string(),
+                        error,
+                    });
+                }
+            },
+        };
+
+        let result = match config.with_url {
+            true => writeln!(output, "{}\t{}", key, url),
+            false => writeln!(output, "{}", key),
+        };
+        result.map_err(|e| CliError::Io("output".to_string(), e))?;
+    }
+}
+
+/// Convert the URL field of every record in `input`, writing the records to `output`.
+///
+/// A field which cannot be found in the header, or without one, is a configuration error,
+/// so it stops the conversion whatever the error policy.
+fn process_records<R: BufRead, W: Write>(
+    source: &str,
+    input: R,
+    output: &mut W,
+    transformer: FieldTransformer,
+    on_error: ErrorPolicy,
+) -> Result<(), CliError> {
+    let out_err = |e| CliError::Io("output".to_string(), e);
+    let mut records = transformer.records(input);
+
+    while let Some(result) = records.next() {
+        let (line, record) = match result {
+            Ok(out) => {
+                output.write_all(&out).map_err(out_err)?;
+                continue;
+            }
+            Err(TransformError::Io(e)) => return Err(CliError::Io(source.to_string(), e)),
+            Err(e) if on_error == ErrorPolicy::Abort || records.column().is_none() => {
+                return Err(CliError::Record(source.to_string(), e));
+            }
+            Err(TransformError::Field { line, record, .. } | TransformError::Surt { line, record, .. }) => (line, record),
+        };
+
+        if on_error == ErrorPolicy::Skip {
+            continue;
+        }
+
+        // Records missing the field are written unchanged when no placeholder can be set.
+        let column = records.column().unwrap_or_default();
+        let out = records.transformer().set_field(&record, column, line, "-").unwrap_or(record);
+        output.write_all(&out).map_err(out_err)?;
+        output.write_all(records.line_ending()).map_err(out_err)?;
+    }
+
+    Ok(())
+}
+
+/// Re-key every CDX or CDXJ line in `input`, writing the lines to `output`.
+///
+/// The `dash` error policy writes lines which cannot be re-keyed unchanged. The number of
+/// changed keys is reported on stderr, as the output must be re-sorted if any have changed,
+/// along with the number of lines which could not be re-keyed.
+fn process_cdx<R: BufRead, W: Write>(source: &str, input: R, output: &mut W, config: &Config) -> Result<(), CliError> {
+    let out_err = |e| CliError::Io("output".to_string(), e);
+    let mut lines = CdxRekeyer::new(Some(config.options.clone())).rekey(input);
+
+    while let Some(result) = lines.next() {
+        match result {
+            Ok(l) => output.write_all(&l.output).map_err(out_err)?,
+            Err(TransformError::Io(e)) => return Err(CliError::Io(source.to_string(), e)),
+            Err(e) if config.on_error == ErrorPolicy::Abort => return Err(CliError::Record(source.to_string(), e)),
+            Err(_) if config.on_error == ErrorPolicy::Skip => continue,
+            Err(TransformError::Field { record, .. } | TransformError::Surt { record, .. }) => {
+                output.write_all(&record).map_err(out_err)?;
+                output.write_all(lines.line_ending()).map_err(out_err)?;
+            }
+        }
+    }
+
+    eprintln!(
+        "surtr: {}: {} of {} keys changed, {} lines failed",
+        source,
+        lines.changed(),
+        lines.lines(),
+        lines.failed()
+    );
+    Ok(())
+}
+
+/// Index the WARC or ARC file in `input`, writing a CDXJ line for each record to `output`.
+///
+/// Errors reading the file always stop the index, as the position of the next record is unknown.
+/// The `dash` error policy reports records which cannot be keyed on stderr and continues.
+fn process_warc<R: Read, W: Write>(source: &str, input: R, output: &mut W, config: &Config) -> Result<(), CliError> {
+    let filename = Path::new(source).file_name().map(|f| f.to_string_lossy()).unwrap_or_default();
+    let mut writer = CdxWriter::without_header(output);
+
+    for result in WarcIndexer::new(input, &filename).with_options(config.options.clone()) {
+        match result {
+            Ok(record) => writer.write_cdxj(&record).map_err(|e| CliError::Io("output".to_string(), e))?,
+            Err(e) if e.kind() != io::ErrorKind::InvalidData || config.on_error == ErrorPolicy::Abort => {
+                return Err(CliError::Io(source.to_string(), e));
+            }
+            Err(_) if config.on_error == ErrorPolicy::Skip => continue,
+            Err(e) => eprintln!("surtr: {}: {}", source, e),
+        }
+    }
+
+    Ok(())
+}
+
+fn run(matches: &ArgMatches) -> Result<(), CliError> {
+    let config = build_config(matches)?;
+
+    let mut output: Box<dyn Write> = match matches.get_one::<String>("output") {
+        Some(path) => Box::new(File::create(path).map_err(|e| CliError::Io(path.clone(), e))?),
+        None => Box::new(io::stdout().lock()),
+    };
+    let mut output = BufWriter::new(&mut output);
+
+    let files: Vec<&String> = matches.get_many::<String>("files").map(|f| f.collect()).unwrap_or_default();
+    if files.is_empty() {
+        process("<stdin>", io::stdin().lock(), &mut output, &config)?;
+    }
+
+    for path in files {
+        match path.as_str() {
+            "-" => process("<stdin>", io::stdin().lock(), &mut output, &config)?,
+            _ => {
+                let file = File::open(path).map_err(|e| CliError::Io(path.clone(), e))?;
+                process(path, BufReader::new(file), &mut output, &config)?;
+            }
+        }
+    }
+
+    output.flush().map_err(|e| CliError::Io("output".to_string(), e))
+}
+
+fn main() -> ExitCode {
+    let matches = command().get_matches();
+
+    match run(&matches) {
+        Ok(()) => ExitCode::SUCCESS,
+        // A closed pipe, such as `surtr urls.txt | head`, is not an error.
+        Err(CliError::Io(_, e)) if e.kind() == io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("surtr: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(args: &[&str]) -> Config {
+        let mut argv = vec!["surtr"];
+        argv.extend_from_slice(args);
+
+        build_config(&command().try_get_matches_from(argv).unwrap()).unwrap()
+    }
+
+    fn run_lines(input: &str, config: &Config) -> Result<String, CliError> {
+        let mut output = Vec::new();
+        process("test", input.as_bytes(), &mut output, config)?;
+
+        Ok(String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn test_process() {
+        let input = "http://www.example.com/\r\nhttp://archive.org/goo/?b&a\n\nhttp://archive.org/";
+        assert_eq!(
+            run_lines(input, &config(&[])).unwrap(),
+            "com,example)/\norg,archive)/goo?a&b\n-\norg,archive)/\n"
+        );
+
+        assert_eq!(
+            run_lines("http://www.example.com/\n", &config(&["--with-url"])).unwrap(),
+            "com,example)/\thttp://www.example.com/\n"
+        );
+    }
+
+    #[test]
+    fn test_options() {
+        let url = "http://www.example.com/\n";
+
+        assert_eq!(run_lines(url, &config(&["--with-scheme"])).unwrap(), "http://(com,example)/\n");
+        assert_eq!(
+            run_lines(url, &config(&["--profile", "heritrix"])).unwrap(),
+            "http://(com,example,www,)/\n"
+        );
+        assert_eq!(
+            run_lines(url, &config(&["--profile", "heritrix", "--no-with-scheme"])).unwrap(),
+            "com,example,www,)/\n"
+        );
+        assert_eq!(
+            run_lines("http://a.news.bbc.co.uk/a/b\n", &config(&["--host-granularity", "registered_domain", "--path-depth", "1"]))
+                .unwrap(),
+            "uk,co,bbc)/a\n"
+        );
+
+        let replay = "http://web.archive.org/web/2020/http://example.com/page\nhttp://archive.example.org/web/2020/http://example.com/page\n";
+        assert_eq!(
+            run_lines(replay, &config(&["--unwrap-replay"])).unwrap(),
+            "com,example)/page\ncom,example)/page\n"
+        );
+        assert_eq!(
+            run_lines(replay, &config(&["--replay-host", "archive.org"])).unwrap(),
+            "com,example)/page\norg,example,archive)/web/2020/http:/example.com/page\n"
+        );
+
+        let nested = "http://example.com/out?to=http%3A%2F%2FWWW.example.org%2F\n";
+        assert_eq!(
+            run_lines(nested, &config(&["--nested-urls", "canonicalize", "--nested-param", "to"])).unwrap(),
+            "com,example)/out?to=http://example.org/\n"
+        );
+        assert_eq!(
+            run_lines(nested, &config(&["--nested-urls", "extract", "--nested-param", "to"])).unwrap(),
+            "org,example)/\n"
+        );
+
+        for option in SurtrOptions::OPTION_NAMES {
+            let flag = format!("--{}", flag_name(option));
+            let no_flag = format!("--no-{}", flag_name(option));
+
+            assert_eq!(config(&[&flag]).options.get(option), Some(true));
+            assert_eq!(config(&[&flag, &no_flag]).options.get(option), Some(false));
+        }
+    }
+
+    #[test]
+    fn test_error_policy() {
+        let input = "http://example.com/\nhttp://example.com/%ff\nhttp://example.org/\n";
+
+        assert_eq!(
+            run_lines(input, &config(&["--on-error", "dash"])).unwrap(),
+            "com,example)/\n-\norg,example)/\n"
+        );
+        assert_eq!(
+            run_lines(input, &config(&["--on-error", "skip"])).unwrap(),
+            "com,example)/\norg,example)/\n"
+        );
+
+        match run_lines(input, &config(&["--on-error", "abort"])) {
+            Err(CliError::Url { line, url, .. }) => {
+                assert_eq!(line, 2);
+                assert_eq!(url, "http://example.com/%ff");
+            }
+            other => panic!("expected a url error, got {:?}", other),
+        }
+
+        // Blank lines follow the error policy too.
+        let input = "http://example.com/\n\n  \nhttp://example.org/\n";
+        assert_eq!(
+            run_lines(input, &config(&["--on-error", "dash"])).unwrap(),
+            "com,example)/\n-\n-\norg,example)/\n"
+        );
+        assert_eq!(
+            run_lines(input, &config(&["--on-error", "skip"])).unwrap(),
+            "com,example)/\norg,example)/\n"
+        );
+        match run_lines(input, &config(&["--on-error", "abort"])) {
+            Err(CliError::Url { line, .. }) => assert_eq!(line, 2),
+            other => panic!("expected a url error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_formats() {
+        assert_eq!(
+            run_lines("a\thttp://www.example.com/\n", &config(&["--format", "tsv", "--field", "2"])).unwrap(),
+            "a\tcom,example)/\n"
+        );
+        assert_eq!(
+            run_lines(
+                "id,url\n1,http://www.example.com/\n",
+                &config(&["--format", "csv", "--field", "url", "--header", "--append", "surt", "--with-scheme"])
+            )
+            .unwrap(),
+            "id,url,surt\n1,http://www.example.com/,\"http://(com,example)/\"\n"
+        );
+        assert_eq!(
+            run_lines("{\"req\": {\"url\": \"http://www.example.com/\"}}\n", &config(&["-f", "jsonl", "--field", "/req/url"]))
+                .unwrap(),
+            "{\"req\": {\"url\": \"com,example)/\"}}\n"
+        );
+    }
+
+    #[test]
+    fn test_format_cdx() {
+        let input = "com,example,www)/ 20200101000000 http://www.example.com/ text/html 200 ABC - - 1043 333 a.warc.gz\n\
+            com,example)/ 20200101000000\n";
+
+        assert_eq!(
+            run_lines(input, &config(&["--format", "cdx"])).unwrap(),
+            "com,example)/ 20200101000000 http://www.example.com/ text/html 200 ABC - - 1043 333 a.warc.gz\n\
+            com,example)/ 20200101000000\n"
+        );
+        assert_eq!(
+            run_lines(input, &config(&["--format", "cdx", "--on-error", "skip", "--profile", "heritrix"])).unwrap(),
+            "http://(com,example,www,)/ 20200101000000 http://www.example.com/ text/html 200 ABC - - 1043 333 a.warc.gz\n"
+        );
+        assert!(run_lines(input, &config(&["--format", "cdx", "--on-error", "abort"])).is_err());
+    }
+
+    #[test]
+    fn test_format_warc() {
+        let record = |url: &str| {
+            format!(
+                "WARC/1.0\r\nWARC-Type: resource\r\nWARC-Target-URI: {}\r\nWARC-Date: 2020-01-01T00:00:00Z\r\n\
+                 Content-Type: text/plain\r\nContent-Length: 5\r\n\r\nhello\r\n\r\n",
+                url
+            )
+        };
+        let warc = format!("{}{}", record("http://example.com/%ff"), record("http://www.example.com/"));
+        let offset = record("http://example.com/%ff").len();
+
+        let mut output = Vec::new();
+        process_warc("/data/a.warc", warc.as_bytes(), &mut output, &config(&["--format", "warc"])).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!(
+                "com,example)/ 20200101000000 {{\"url\": \"http://www.example.com/\", \"mime\": \"text/plain\", \
+                 \"digest\": \"VL2MMHO4YXUKFWV63YHTWSBM3GXKSQ2N\", \"length\": \"{}\", \"offset\": \"{}\", \
+                 \"filename\": \"a.warc\"}}\n",
+                record("http://www.example.com/").len(),
+                offset
+            )
+        );
+
+        let abort = config(&["--format", "warc", "--on-error", "abort"]);
+        assert!(process_warc("a.warc", warc.as_bytes(), &mut Vec::new(), &abort).is_err());
+    }
+
+    #[test]
+    fn test_formats_error_policy() {
+        let input = "1\thttp://example.com/%ff\n2\n3\thttp://example.com/\n";
+        let args = ["--format", "tsv", "--field", "2"];
+
+        let dash: Vec<&str> = args.iter().copied().chain(["--on-error", "dash"]).collect();
+        assert_eq!(run_lines(input, &config(&dash)).unwrap(), "1\t-\n2\n3\tcom,example)/\n");
+
+        let skip: Vec<&str> = args.iter().copied().chain(["--on-error", "skip"]).collect();
+        assert_eq!(run_lines(input, &config(&skip)).unwrap(), "3\tcom,example)/\n");
+
+        let abort: Vec<&str> = args.iter().copied().chain(["--on-error", "abort"]).collect();
+        match run_lines(input, &config(&abort)) {
+            Err(CliError::Record(_, TransformError::Surt { line, .. })) => assert_eq!(line, 1),
+            other => panic!("expected a record error, got {:?}", other),
+        }
+
+        // Failed records keep their own line ending.
+        let crlf = "1\thttp://example.com/%ff\r\n2\r\n3\thttp://example.com/\r\n";
+        assert_eq!(run_lines(crlf, &config(&dash)).unwrap(), "1\t-\r\n2\r\n3\tcom,example)/\r\n");
+
+        // A column missing from the header stops the conversion, whatever the policy.
+        let missing = "id,link\n1,http://example.com/\n";
+        for policy in ["dash", "skip"] {
+            let args = ["--format", "csv", "--field", "url", "--header", "--on-error", policy];
+            match run_lines(missing, &config(&args)) {
+                Err(CliError::Record(_, TransformError::Field { line, .. })) => assert_eq!(line, 1),
+                other => panic!("expected a field error, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_arguments() {
+        assert!(command().try_get_matches_from(["surtr", "--profile", "wayback"]).is_err());
+        assert!(command().try_get_matches_from(["surtr", "--on-error", "ignore"]).is_err());
+
+        for args in [
+            vec!["surtr", "--host-granularity", "subdomain"],
+            vec!["surtr", "--format", "tsv"],
+            vec!["surtr", "--format", "tsv", "--field", "0"],
+            vec!["surtr", "--format", "csv", "--field", "1", "--with-url"],
+        ] {
+            let matches = command().try_get_matches_from(args.clone()).unwrap();
+            assert!(build_config(&matches).is_err(), "{:?}", args);
+        }
+    }
+}
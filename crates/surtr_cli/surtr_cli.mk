@@ -0,0 +1,9 @@
+
+build-surtr-cli:
+	@cargo build -p surtr_cli
+
+build-surtr-cli-release:
+	@cargo build -p surtr_cli --release
+
+test-surtr-cli:
+	@cargo test -p surtr_cli